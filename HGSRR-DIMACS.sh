@@ -31,7 +31,7 @@ fi
 
 if [ $ROUNDED -eq 1 ]
 then
-    ./hybridcvrp $INSTANCE -t $TIME_LIMIT -r
+    ./hybridcvrp $INSTANCE -t $TIME_LIMIT -r --set dimacs=true
 else
-    ./hybridcvrp $INSTANCE -t $TIME_LIMIT
+    ./hybridcvrp $INSTANCE -t $TIME_LIMIT -r false --set dimacs=true
 fi
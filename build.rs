@@ -0,0 +1,20 @@
+fn main() {
+    // Generate the header of the C API into the output directory, where the tests check it against
+    // the header in include/hybridcvrp.h
+    #[cfg(feature = "capi")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("No manifest directory");
+        let out_dir = std::env::var("OUT_DIR").expect("No output directory");
+        let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir))
+            .expect("Failed to read cbindgen.toml");
+        cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("Failed to generate the C header")
+            .write_to_file(format!("{}/hybridcvrp.h", out_dir));
+
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+    }
+}
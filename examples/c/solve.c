@@ -0,0 +1,52 @@
+// Solve a small problem through the C API
+//
+//   cargo build --release --features capi
+//   cc examples/c/solve.c -Iinclude -Ltarget/release -lhybridcvrp -o solve
+//   LD_LIBRARY_PATH=target/release ./solve
+
+#include <stdio.h>
+
+#include "hybridcvrp.h"
+
+static bool report(double cost, double time, uint64_t iteration, void *user_data) {
+    (void)user_data;
+    printf("New best %.2f after %.2fs in iteration %llu\n", cost, time,
+           (unsigned long long)iteration);
+    return true;
+}
+
+int main(void) {
+    // The first node is the depot
+    const double x[] = {0.0, 3.0, 3.0, 0.0, -2.0};
+    const double y[] = {0.0, 0.0, 4.0, 4.0, -1.0};
+    const double demands[] = {0.0, 1.0, 1.0, 1.0, 2.0};
+
+    HcvrpProblem *problem = hcvrp_problem_new(5, x, y, demands, 3.0);
+    HcvrpParams *params = hcvrp_params_new();
+    if (hcvrp_params_set(params, "max_iterations", "500") != HCVRP_STATUS_OK) {
+        fprintf(stderr, "%s\n", hcvrp_last_error());
+        return 1;
+    }
+
+    HcvrpSolution *solution = NULL;
+    if (hcvrp_solve(problem, params, 10, report, NULL, &solution) != HCVRP_STATUS_OK) {
+        fprintf(stderr, "%s\n", hcvrp_last_error());
+        return 1;
+    }
+
+    printf("Cost %.2f\n", hcvrp_solution_cost(solution));
+    for (size_t route = 0; route < hcvrp_solution_num_routes(solution); route++) {
+        size_t length = 0;
+        const size_t *customers = hcvrp_solution_route(solution, route, &length);
+        printf("Route #%zu:", route + 1);
+        for (size_t i = 0; i < length; i++) {
+            printf(" %zu", customers[i]);
+        }
+        printf("\n");
+    }
+
+    hcvrp_solution_free(solution);
+    hcvrp_params_free(params);
+    hcvrp_problem_free(problem);
+    return 0;
+}
@@ -0,0 +1,146 @@
+#ifndef HYBRIDCVRP_H
+#define HYBRIDCVRP_H
+
+/* Generated by cbindgen from src/capi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status returned by the functions of the C API
+typedef enum HcvrpStatus {
+  HCVRP_STATUS_OK = 0,
+  HCVRP_STATUS_INVALID_ARGUMENT = 1,
+  HCVRP_STATUS_FAILED = 2,
+} HcvrpStatus;
+
+// Config of the solver
+typedef struct HcvrpParams HcvrpParams;
+
+// Problem with the nodes, where the first node is the depot
+typedef struct HcvrpProblem HcvrpProblem;
+
+// Best solution found by the solver
+typedef struct HcvrpSolution HcvrpSolution;
+
+// Called on every new best solution with the cost, the seconds since the start, the iteration
+// and the user data given to `hcvrp_solve`. Returning false stops the search.
+typedef bool (*HcvrpProgressCallback)(double cost, double time, uint64_t iteration, void *user_data);
+
+
+
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message of the last error on the calling thread, or null if there was no error. The message is
+// valid until the next failing call on the thread.
+const char *hcvrp_last_error(void);
+
+// Create a problem from arrays of `num_nodes` coordinates and demands, where the first node is
+// the depot. Returns null on failure.
+//
+// # Safety
+//
+// `x`, `y` and `demands` must point to `num_nodes` values.
+struct HcvrpProblem *hcvrp_problem_new(size_t num_nodes,
+                                       const double *x,
+                                       const double *y,
+                                       const double *demands,
+                                       double capacity);
+
+// Replace the euclidean distances with a full matrix of `num_nodes * num_nodes` distances in
+// row-major order, which must be symmetric.
+//
+// # Safety
+//
+// `problem` must be a problem from `hcvrp_problem_new`, and `distances` must point to
+// `num_nodes * num_nodes` values.
+enum HcvrpStatus hcvrp_problem_set_distance_matrix(struct HcvrpProblem *problem,
+                                                   const double *distances,
+                                                   size_t num_nodes);
+
+// # Safety
+//
+// `problem` must be null or a problem from `hcvrp_problem_new` which is not yet freed.
+void hcvrp_problem_free(struct HcvrpProblem *problem);
+
+// Create parameters with the default config, where the search is deterministic with seed 1 and
+// no lower bound is computed
+struct HcvrpParams *hcvrp_params_new(void);
+
+// Set a config parameter by its name in `config.yml`, where the value is parsed as YAML such as
+// `"60"`, `"true"` or `"tournament"`.
+//
+// # Safety
+//
+// `params` must be parameters from `hcvrp_params_new`, and `name` and `value` must be
+// null-terminated strings.
+enum HcvrpStatus hcvrp_params_set(struct HcvrpParams *params, const char *name, const char *value);
+
+// # Safety
+//
+// `params` must be null or parameters from `hcvrp_params_new` which are not yet freed.
+void hcvrp_params_free(struct HcvrpParams *params);
+
+// Solve the problem within the time limit in seconds, and store the best solution in `solution`,
+// which must be freed with `hcvrp_solution_free`. The parameters may be null for the defaults of
+// `hcvrp_params_new`, and the callback may be null.
+//
+// # Safety
+//
+// `problem` must be a problem from `hcvrp_problem_new`, `params` null or parameters from
+// `hcvrp_params_new`, and `solution` a valid pointer to store the solution in.
+enum HcvrpStatus hcvrp_solve(const struct HcvrpProblem *problem,
+                             const struct HcvrpParams *params,
+                             uint64_t time_limit,
+                             HcvrpProgressCallback callback,
+                             void *user_data,
+                             struct HcvrpSolution **solution);
+
+// Total distance of the routes, or infinity when no solution was found
+//
+// # Safety
+//
+// `solution` must be a solution from `hcvrp_solve`.
+double hcvrp_solution_cost(const struct HcvrpSolution *solution);
+
+// Whether all the routes are within the vehicle capacity
+//
+// # Safety
+//
+// `solution` must be a solution from `hcvrp_solve`.
+bool hcvrp_solution_feasible(const struct HcvrpSolution *solution);
+
+// Number of non-empty routes
+//
+// # Safety
+//
+// `solution` must be a solution from `hcvrp_solve`.
+size_t hcvrp_solution_num_routes(const struct HcvrpSolution *solution);
+
+// Customers of the route as node indices, where the depot is 0 and left out. The number of
+// customers is stored in `length`, and the array is valid until the solution is freed. Returns
+// null when the route does not exist.
+//
+// # Safety
+//
+// `solution` must be a solution from `hcvrp_solve`, and `length` a valid pointer.
+const size_t *hcvrp_solution_route(const struct HcvrpSolution *solution,
+                                   size_t route,
+                                   size_t *length);
+
+// # Safety
+//
+// `solution` must be null or a solution from `hcvrp_solve` which is not yet freed.
+void hcvrp_solution_free(struct HcvrpSolution *solution);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HYBRIDCVRP_H */
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::solver::{run_solver, Context, Solution};
+use crate::utils::ProblemParser;
+
+/// Format of the benchmark report
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    Csv,
+    Markdown,
+}
+
+/// Result of solving a benchmark instance with one seed
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BenchResult {
+    pub instance: String,
+    pub seed: u64,
+    pub cost: f64,
+    pub feasible: bool,
+    // Seconds until the best solution was found, and of the whole run
+    pub time_to_best: Option<f64>,
+    pub runtime: f64,
+    pub iterations: u64,
+    // Seconds and cost of each new best solution
+    pub trajectory: Vec<(f64, f64)>,
+}
+
+/// Results of all the seeds of an instance compared to the best known solution
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct InstanceSummary {
+    pub instance: String,
+    pub bks: Option<f64>,
+    pub runs: usize,
+    pub best: f64,
+    pub average: f64,
+    // Gaps to the best known solution in percent
+    pub best_gap: Option<f64>,
+    pub average_gap: Option<f64>,
+    pub time_to_best: Option<f64>,
+    pub primal_integral: Option<f64>,
+}
+
+/// Solve the instance of the config
+pub fn bench_instance(mut config: Config) -> BenchResult {
+    let start_time = Instant::now();
+    let instance = instance_name(&config.instance_path);
+    let seed = config.seed;
+
+    let mut parser = ProblemParser::new();
+    parser.parse(&mut config);
+    let ctx = run_solver(Context::new(parser, config, start_time));
+    let solution = Solution::from_context(&ctx);
+    let trajectory = ctx
+        .search_history
+        .borrow()
+        .entries()
+        .iter()
+        .map(|entry| (entry.timestamp.as_secs_f64(), entry.solution.cost))
+        .collect();
+
+    BenchResult {
+        instance,
+        seed,
+        cost: solution.cost,
+        feasible: solution.feasible,
+        time_to_best: solution
+            .statistics
+            .time_to_best
+            .map(|time| time.as_secs_f64()),
+        runtime: solution.statistics.runtime.as_secs_f64(),
+        iterations: solution.statistics.iterations,
+        trajectory,
+    }
+}
+
+/// File name of the instance without the extension
+pub fn instance_name(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(
+        || path.to_owned(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// Expand directories to the problem instances inside them, and glob patterns to the matching
+/// files
+pub fn expand_instance_paths(paths: &[String]) -> Vec<String> {
+    let mut instance_paths = Vec::new();
+    for path in paths.iter() {
+        if Path::new(path).is_dir() {
+            let mut entries: Vec<String> = fs::read_dir(path)
+                .unwrap_or_else(|err| panic!("Failed to read directory {}: {}", path, err))
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    matches!(
+                        path.extension().and_then(|extension| extension.to_str()),
+                        Some("vrp") | Some("json")
+                    )
+                })
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect();
+            entries.sort();
+            instance_paths.extend(entries);
+        } else if path.contains(&['*', '?', '['][..]) {
+            let entries = glob::glob(path)
+                .unwrap_or_else(|err| panic!("Invalid pattern {}: {}", path, err))
+                .filter_map(|entry| entry.ok())
+                .map(|path| path.to_string_lossy().into_owned());
+            instance_paths.extend(entries);
+        } else {
+            instance_paths.push(path.clone());
+        }
+    }
+    instance_paths
+}
+
+/// Read the best known solutions from a CSV file with the instance name and the value on each
+/// line, where a header line is skipped
+pub fn read_bks_csv(path: &str) -> HashMap<String, f64> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("Failed to read BKS file {}: {}", path, err));
+    text.lines()
+        .filter_map(|line| {
+            let mut values = line.split(',').map(|value| value.trim().trim_matches('"'));
+            let instance = values.next()?;
+            let bks = values.next()?.parse::<f64>().ok()?;
+            Some((instance_name(instance), bks))
+        })
+        .collect()
+}
+
+/// Best known solution from a CVRPLIB comment such as `(Augerat et al, Optimal value: 784)`
+pub fn bks_from_comment(comment: &str) -> Option<f64> {
+    let comment = comment.to_lowercase();
+    ["optimal value", "best value", "best known", "bks"]
+        .iter()
+        .find_map(|label| {
+            let start = comment.find(label)? + label.len();
+            let number: String = comment[start..]
+                .chars()
+                .skip_while(|c| !c.is_ascii_digit())
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect();
+            number.parse::<f64>().ok()
+        })
+}
+
+/// Best known solution from the `COMMENT` line of a TSPLIB instance
+pub fn bks_from_instance(path: &str) -> Option<f64> {
+    let text = fs::read_to_string(path).ok()?;
+    text.lines()
+        .find(|line| line.trim_start().starts_with("COMMENT"))
+        .and_then(bks_from_comment)
+}
+
+/// Gap between the cost and the best known solution in percent
+pub fn bks_gap(cost: f64, bks: f64) -> f64 {
+    100.0 * (cost - bks) / bks
+}
+
+/// Average gap over the run, where the gap is 100% until the first solution is found
+pub fn primal_integral(trajectory: &[(f64, f64)], runtime: f64, bks: f64) -> f64 {
+    let mut integral = 0.0;
+    let mut prev_time = 0.0;
+    let mut gap = 100.0;
+    for &(time, cost) in trajectory.iter() {
+        let time = time.min(runtime);
+        integral += gap * (time - prev_time);
+        prev_time = time;
+        gap = bks_gap(cost, bks);
+    }
+    integral += gap * (runtime - prev_time);
+    match runtime > 0.0 {
+        true => integral / runtime,
+        false => gap,
+    }
+}
+
+/// Summarize the results by instance, in the order the instances are first seen
+pub fn summarize(results: &[BenchResult], bks: &HashMap<String, f64>) -> Vec<InstanceSummary> {
+    let mut instances: Vec<&str> = Vec::new();
+    for result in results.iter() {
+        if !instances.contains(&result.instance.as_str()) {
+            instances.push(&result.instance);
+        }
+    }
+
+    instances
+        .into_iter()
+        .map(|instance| {
+            let runs: Vec<&BenchResult> = results
+                .iter()
+                .filter(|result| result.instance == instance)
+                .collect();
+            let bks = bks.get(instance).cloned();
+            let best = runs
+                .iter()
+                .map(|result| result.cost)
+                .fold(f64::INFINITY, f64::min);
+            let average = mean(runs.iter().map(|result| result.cost)).unwrap_or(f64::INFINITY);
+            InstanceSummary {
+                instance: instance.to_owned(),
+                bks,
+                runs: runs.len(),
+                best,
+                average,
+                best_gap: bks.map(|bks| bks_gap(best, bks)),
+                average_gap: bks.map(|bks| bks_gap(average, bks)),
+                time_to_best: mean(runs.iter().filter_map(|result| result.time_to_best)),
+                primal_integral: bks.and_then(|bks| {
+                    mean(
+                        runs.iter()
+                            .map(|result| primal_integral(&result.trajectory, result.runtime, bks)),
+                    )
+                }),
+            }
+        })
+        .collect()
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    match count {
+        0 => None,
+        _ => Some(sum / count as f64),
+    }
+}
+
+/// Read the average costs by instance from a previous CSV report
+pub fn read_report_csv(path: &str) -> HashMap<String, f64> {
+    let text = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("Failed to read report {}: {}", path, err));
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split(',').collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|&column| column == name)
+            .unwrap_or_else(|| panic!("Report {} has no {} column", path, name))
+    };
+    let (instance, average) = (column("instance"), column("average"));
+    lines
+        .filter_map(|line| {
+            let values: Vec<&str> = line.split(',').collect();
+            let value = values.get(average)?.parse::<f64>().ok()?;
+            Some((values.get(instance)?.to_string(), value))
+        })
+        .collect()
+}
+
+/// Write the report with one row per instance. With the average costs of a previous report, the
+/// change of the average cost in percent is added, where a positive change is a regression.
+pub fn write_report(
+    writer: &mut impl Write,
+    summaries: &[InstanceSummary],
+    format: ReportFormat,
+    previous: Option<&HashMap<String, f64>>,
+) -> io::Result<()> {
+    let mut header = vec![
+        "instance",
+        "bks",
+        "runs",
+        "best",
+        "average",
+        "best_gap",
+        "average_gap",
+        "time_to_best",
+        "primal_integral",
+    ];
+    if previous.is_some() {
+        header.extend(["previous_average", "change"]);
+    }
+
+    let number = |value: Option<f64>| match (value, format) {
+        (Some(value), ReportFormat::Csv) => value.to_string(),
+        (Some(value), ReportFormat::Markdown) => format!("{:.2}", value),
+        (None, _) => String::new(),
+    };
+    let rows: Vec<Vec<String>> = summaries
+        .iter()
+        .map(|summary| {
+            let mut row = vec![
+                summary.instance.clone(),
+                number(summary.bks),
+                summary.runs.to_string(),
+                number(Some(summary.best)),
+                number(Some(summary.average)),
+                number(summary.best_gap),
+                number(summary.average_gap),
+                number(summary.time_to_best),
+                number(summary.primal_integral),
+            ];
+            if let Some(previous) = previous {
+                let previous_average = previous.get(&summary.instance).cloned();
+                row.push(number(previous_average));
+                row.push(number(previous_average.map(|previous_average| {
+                    100.0 * (summary.average - previous_average) / previous_average
+                })));
+            }
+            row
+        })
+        .collect();
+
+    match format {
+        ReportFormat::Csv => {
+            writeln!(writer, "{}", header.join(","))?;
+            for row in rows.iter() {
+                writeln!(writer, "{}", row.join(","))?;
+            }
+        }
+        ReportFormat::Markdown => {
+            writeln!(writer, "| {} |", header.join(" | "))?;
+            let separator: Vec<&str> = header.iter().map(|_| "---").collect();
+            writeln!(writer, "|{}|", separator.join("|"))?;
+            for row in rows.iter() {
+                writeln!(writer, "| {} |", row.join(" | "))?;
+            }
+
+            // Averages over the instances with a best known solution
+            let with_bks = || summaries.iter().filter(|summary| summary.bks.is_some());
+            writeln!(
+                writer,
+                "| **mean** | | | | | {} | {} | {} | {} |{}",
+                number(mean(with_bks().filter_map(|summary| summary.best_gap))),
+                number(mean(with_bks().filter_map(|summary| summary.average_gap))),
+                number(mean(
+                    summaries.iter().filter_map(|summary| summary.time_to_best)
+                )),
+                number(mean(
+                    with_bks().filter_map(|summary| summary.primal_integral)
+                )),
+                match previous {
+                    Some(_) => " | |",
+                    None => "",
+                }
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bks_in_comment() {
+        assert_eq!(
+            Some(784.0),
+            bks_from_comment("COMMENT : (Augerat et al, No of trucks: 5, Optimal value: 784)")
+        );
+        assert_eq!(
+            None,
+            bks_from_comment("COMMENT : \"Generated by Uchoa et al. (2013)\"")
+        );
+    }
+
+    #[test]
+    fn primal_integral_of_trajectory() {
+        // 100% gap for the first second, then 10% for a second and 0% for two seconds
+        let trajectory = [(1.0, 110.0), (2.0, 100.0)];
+        assert!((primal_integral(&trajectory, 4.0, 100.0) - 27.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn summarize_seeds() {
+        let result = |seed, cost| BenchResult {
+            instance: "X-n101-k25".to_owned(),
+            seed,
+            cost,
+            feasible: true,
+            time_to_best: Some(1.0),
+            runtime: 2.0,
+            iterations: 100,
+            trajectory: vec![(1.0, cost)],
+        };
+        let bks = vec![("X-n101-k25".to_owned(), 100.0)].into_iter().collect();
+        let summaries = summarize(&[result(1, 102.0), result(2, 104.0)], &bks);
+
+        assert_eq!(1, summaries.len());
+        assert_eq!(2, summaries[0].runs);
+        assert_eq!(102.0, summaries[0].best);
+        assert_eq!(103.0, summaries[0].average);
+        assert_eq!(Some(2.0), summaries[0].best_gap);
+        assert_eq!(Some(3.0), summaries[0].average_gap);
+    }
+}
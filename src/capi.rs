@@ -0,0 +1,447 @@
+//! C API of the solver, where `include/hybridcvrp.h` is generated by cbindgen.
+//!
+//! Problems, parameters and solutions are opaque handles, which are created and freed by the
+//! functions of this module. Failing functions return an error status or a null pointer, and the
+//! message of the last error on the calling thread is given by `hcvrp_last_error`.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::config::Config;
+use crate::models::{Coordinate, DistanceMatrix, Node, ProblemBuilder, Vehicle};
+use crate::solver::{solve_with_progress, Params, Solution};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Status returned by the functions of the C API
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HcvrpStatus {
+    Ok = 0,
+    // A null pointer, a string which is not UTF-8 or an invalid value
+    InvalidArgument = 1,
+    // The solver failed, see `hcvrp_last_error`
+    Failed = 2,
+}
+
+/// Problem with the nodes, where the first node is the depot
+pub struct HcvrpProblem {
+    nodes: Vec<Node>,
+    vehicle: Vehicle,
+    // Explicit distances in the lower row format of the parser
+    matrix: Option<Vec<Vec<f64>>>,
+}
+
+/// Config of the solver
+pub struct HcvrpParams {
+    config: Config,
+}
+
+/// Best solution found by the solver
+pub struct HcvrpSolution {
+    solution: Solution,
+}
+
+/// Called on every new best solution with the cost, the seconds since the start, the iteration
+/// and the user data given to `hcvrp_solve`. Returning false stops the search.
+pub type HcvrpProgressCallback = Option<
+    unsafe extern "C" fn(cost: f64, time: f64, iteration: u64, user_data: *mut c_void) -> bool,
+>;
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+// Run the function, where a panic is recorded as the last error
+fn catch_panic<T>(function: impl FnOnce() -> Result<T, String>) -> Result<T, HcvrpStatus> {
+    match panic::catch_unwind(AssertUnwindSafe(function)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(message)) => {
+            set_last_error(message);
+            Err(HcvrpStatus::InvalidArgument)
+        }
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "The solver panicked".to_owned());
+            set_last_error(message);
+            Err(HcvrpStatus::Failed)
+        }
+    }
+}
+
+fn status(result: Result<(), HcvrpStatus>) -> HcvrpStatus {
+    result.err().unwrap_or(HcvrpStatus::Ok)
+}
+
+/// Message of the last error on the calling thread, or null if there was no error. The message is
+/// valid until the next failing call on the thread.
+#[no_mangle]
+pub extern "C" fn hcvrp_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Create a problem from arrays of `num_nodes` coordinates and demands, where the first node is
+/// the depot. Returns null on failure.
+///
+/// # Safety
+///
+/// `x`, `y` and `demands` must point to `num_nodes` values.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_problem_new(
+    num_nodes: usize,
+    x: *const f64,
+    y: *const f64,
+    demands: *const f64,
+    capacity: f64,
+) -> *mut HcvrpProblem {
+    let result = catch_panic(|| {
+        if x.is_null() || y.is_null() || demands.is_null() {
+            return Err("The coordinates and demands must not be null".to_owned());
+        }
+        if num_nodes < 2 {
+            return Err("The problem has no customers".to_owned());
+        }
+        let (x, y, demands) = (
+            slice::from_raw_parts(x, num_nodes),
+            slice::from_raw_parts(y, num_nodes),
+            slice::from_raw_parts(demands, num_nodes),
+        );
+        let nodes = (0..num_nodes)
+            .map(|index| Node {
+                id: index + 1,
+                coord: Coordinate {
+                    lng: x[index],
+                    lat: y[index],
+                },
+                demand: demands[index],
+            })
+            .collect();
+        Ok(HcvrpProblem {
+            nodes,
+            vehicle: Vehicle {
+                id: 0,
+                cap: capacity,
+            },
+            matrix: None,
+        })
+    });
+    match result {
+        Ok(problem) => Box::into_raw(Box::new(problem)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Replace the euclidean distances with a full matrix of `num_nodes * num_nodes` distances in
+/// row-major order, which must be symmetric.
+///
+/// # Safety
+///
+/// `problem` must be a problem from `hcvrp_problem_new`, and `distances` must point to
+/// `num_nodes * num_nodes` values.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_problem_set_distance_matrix(
+    problem: *mut HcvrpProblem,
+    distances: *const f64,
+    num_nodes: usize,
+) -> HcvrpStatus {
+    status(catch_panic(|| {
+        let problem = problem
+            .as_mut()
+            .ok_or_else(|| "The problem is null".to_owned())?;
+        let dimension = problem.nodes.len();
+        if distances.is_null() || num_nodes != dimension {
+            return Err(format!(
+                "The distance matrix must be {}x{}",
+                dimension, dimension
+            ));
+        }
+        let length = dimension
+            .checked_mul(dimension)
+            .ok_or_else(|| format!("The distance matrix of {} nodes is too large", dimension))?;
+        let distances = slice::from_raw_parts(distances, length);
+        problem.matrix = Some(DistanceMatrix::lower_row(distances, dimension)?);
+        Ok(())
+    }))
+}
+
+/// # Safety
+///
+/// `problem` must be null or a problem from `hcvrp_problem_new` which is not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_problem_free(problem: *mut HcvrpProblem) {
+    if !problem.is_null() {
+        drop(Box::from_raw(problem));
+    }
+}
+
+/// Create parameters with the default config, where the search is deterministic with seed 1 and
+/// no lower bound is computed
+#[no_mangle]
+pub extern "C" fn hcvrp_params_new() -> *mut HcvrpParams {
+    Box::into_raw(Box::new(HcvrpParams {
+        config: default_config(),
+    }))
+}
+
+fn default_config() -> Config {
+    let params = Params {
+        seed: Some(1),
+        ..Params::default()
+    };
+    params.into_config()
+}
+
+/// Set a config parameter by its name in `config.yml`, where the value is parsed as YAML such as
+/// `"60"`, `"true"` or `"tournament"`.
+///
+/// # Safety
+///
+/// `params` must be parameters from `hcvrp_params_new`, and `name` and `value` must be
+/// null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_params_set(
+    params: *mut HcvrpParams,
+    name: *const c_char,
+    value: *const c_char,
+) -> HcvrpStatus {
+    let result = catch_panic(|| {
+        let params = params
+            .as_mut()
+            .ok_or_else(|| "The parameters are null".to_owned())?;
+        let (name, value) = (to_str(name)?, to_str(value)?);
+        params.config.set(name, value)
+    });
+    status(result.map_err(|_| HcvrpStatus::InvalidArgument))
+}
+
+/// # Safety
+///
+/// `params` must be null or parameters from `hcvrp_params_new` which are not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_params_free(params: *mut HcvrpParams) {
+    if !params.is_null() {
+        drop(Box::from_raw(params));
+    }
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Result<&'a str, String> {
+    match string.is_null() {
+        true => Err("The string is null".to_owned()),
+        false => CStr::from_ptr(string)
+            .to_str()
+            .map_err(|_| "The string is not UTF-8".to_owned()),
+    }
+}
+
+/// Solve the problem within the time limit in seconds, and store the best solution in `solution`,
+/// which must be freed with `hcvrp_solution_free`. The parameters may be null for the defaults of
+/// `hcvrp_params_new`, and the callback may be null.
+///
+/// # Safety
+///
+/// `problem` must be a problem from `hcvrp_problem_new`, `params` null or parameters from
+/// `hcvrp_params_new`, and `solution` a valid pointer to store the solution in.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solve(
+    problem: *const HcvrpProblem,
+    params: *const HcvrpParams,
+    time_limit: u64,
+    callback: HcvrpProgressCallback,
+    user_data: *mut c_void,
+    solution: *mut *mut HcvrpSolution,
+) -> HcvrpStatus {
+    let result = catch_panic(|| {
+        let problem = problem
+            .as_ref()
+            .ok_or_else(|| "The problem is null".to_owned())?;
+        if solution.is_null() {
+            return Err("The solution pointer is null".to_owned());
+        }
+        let mut config = match params.as_ref() {
+            Some(params) => params.config.clone(),
+            None => default_config(),
+        };
+        config.time_limit = time_limit;
+
+        let built = ProblemBuilder::new(problem.nodes.clone(), problem.vehicle.clone()).build();
+        let params = Params::from_config(config);
+        Ok(solve_with_progress(
+            built,
+            problem.matrix.clone(),
+            params,
+            |progress| match callback {
+                Some(callback) => callback(
+                    progress.cost,
+                    progress.time.as_secs_f64(),
+                    progress.iteration,
+                    user_data,
+                ),
+                None => true,
+            },
+        ))
+    });
+    match result {
+        Ok(result) => {
+            *solution = Box::into_raw(Box::new(HcvrpSolution { solution: result }));
+            HcvrpStatus::Ok
+        }
+        Err(status) => status,
+    }
+}
+
+/// Total distance of the routes, or infinity when no solution was found
+///
+/// # Safety
+///
+/// `solution` must be a solution from `hcvrp_solve`.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solution_cost(solution: *const HcvrpSolution) -> f64 {
+    (&*solution).solution.cost
+}
+
+/// Whether all the routes are within the vehicle capacity
+///
+/// # Safety
+///
+/// `solution` must be a solution from `hcvrp_solve`.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solution_feasible(solution: *const HcvrpSolution) -> bool {
+    (&*solution).solution.feasible
+}
+
+/// Number of non-empty routes
+///
+/// # Safety
+///
+/// `solution` must be a solution from `hcvrp_solve`.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solution_num_routes(solution: *const HcvrpSolution) -> usize {
+    (&*solution).solution.routes.len()
+}
+
+/// Customers of the route as node indices, where the depot is 0 and left out. The number of
+/// customers is stored in `length`, and the array is valid until the solution is freed. Returns
+/// null when the route does not exist.
+///
+/// # Safety
+///
+/// `solution` must be a solution from `hcvrp_solve`, and `length` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solution_route(
+    solution: *const HcvrpSolution,
+    route: usize,
+    length: *mut usize,
+) -> *const usize {
+    match (&*solution).solution.routes.get(route) {
+        Some(route) => {
+            *length = route.customers.len();
+            route.customers.as_ptr()
+        }
+        None => {
+            *length = 0;
+            ptr::null()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `solution` must be null or a solution from `hcvrp_solve` which is not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn hcvrp_solution_free(solution: *mut HcvrpSolution) {
+    if !solution.is_null() {
+        drop(Box::from_raw(solution));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn count_progress(
+        _cost: f64,
+        _time: f64,
+        _iteration: u64,
+        user_data: *mut c_void,
+    ) -> bool {
+        *(user_data as *mut usize) += 1;
+        true
+    }
+
+    #[test]
+    fn solve_through_c_api() {
+        let x = [0.0, 3.0, 3.0, 0.0];
+        let y = [0.0, 0.0, 4.0, 4.0];
+        let demands = [0.0, 1.0, 1.0, 1.0];
+        unsafe {
+            let problem = hcvrp_problem_new(4, x.as_ptr(), y.as_ptr(), demands.as_ptr(), 2.0);
+            assert!(!problem.is_null());
+            let params = hcvrp_params_new();
+            let name = CString::new("max_iterations").unwrap();
+            let value = CString::new("20").unwrap();
+            assert_eq!(
+                HcvrpStatus::Ok,
+                hcvrp_params_set(params, name.as_ptr(), value.as_ptr())
+            );
+            let unknown = CString::new("no_such_field").unwrap();
+            assert_eq!(
+                HcvrpStatus::InvalidArgument,
+                hcvrp_params_set(params, unknown.as_ptr(), value.as_ptr())
+            );
+            assert!(!hcvrp_last_error().is_null());
+
+            let mut improvements = 0usize;
+            let mut solution = ptr::null_mut();
+            let status = hcvrp_solve(
+                problem,
+                params,
+                10,
+                Some(count_progress),
+                &mut improvements as *mut usize as *mut c_void,
+                &mut solution,
+            );
+            assert_eq!(HcvrpStatus::Ok, status);
+            assert!(improvements > 0);
+            assert!(hcvrp_solution_feasible(solution));
+            assert_eq!(18.0, hcvrp_solution_cost(solution).round());
+
+            let mut customers = Vec::new();
+            for route in 0..hcvrp_solution_num_routes(solution) {
+                let mut length = 0;
+                let stops = hcvrp_solution_route(solution, route, &mut length);
+                customers.extend_from_slice(slice::from_raw_parts(stops, length));
+            }
+            customers.sort_unstable();
+            assert_eq!(vec![1, 2, 3], customers);
+
+            hcvrp_solution_free(solution);
+            hcvrp_params_free(params);
+            hcvrp_problem_free(problem);
+        }
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/hybridcvrp.h"));
+        let header = include_str!("../include/hybridcvrp.h");
+        assert!(
+            generated == header,
+            "include/hybridcvrp.h is outdated, regenerate it with cbindgen"
+        );
+    }
+}
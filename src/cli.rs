@@ -1,78 +1,416 @@
-use clap::{App, Arg};
+use std::path::Path;
+
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use log::LevelFilter;
+
+use crate::bench::ReportFormat;
+use crate::config::Config;
+use crate::utils::FileFormat;
+
+// Config file which is used if it exists and no other file is given
+const DEFAULT_CONFIG_PATH: &str = "config.yml";
 
 /// Representing the resulting command line arguments
+#[derive(Debug, Parser)]
+#[command(
+    name = "hybridcvrp",
+    version,
+    author = "Martin Simensen",
+    about = "Hybrid Metaheuristic Solver for the Capacitated Vehicle Routing Problem",
+    subcommand_negates_reqs = true
+)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Solving is the default when no subcommand is given
+    #[command(flatten)]
+    pub solve: Option<SolveArgs>,
+
+    #[command(flatten)]
+    pub options: GlobalOptions,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Solve a problem instance
+    Solve(SolveArgs),
+    /// Validate a solution file against the problem instance
+    Validate(ValidateArgs),
+    /// Convert a problem instance, or a solution of it, to another format
+    Convert(ConvertArgs),
+    /// Solve a set of instances with several seeds and compare to the best known solutions
+    Bench(BenchArgs),
+    /// Tune the parameters of the config by racing candidate configurations over instances and seeds
+    Tune(TuneArgs),
+    /// Print information about a problem instance
+    Info(InfoArgs),
+}
+
+/// Options shared by all the commands
+#[derive(Debug, ClapArgs)]
+pub struct GlobalOptions {
+    /// Path to the config file, defaults to config.yml if it exists
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Override a config field, where the value is parsed as YAML
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    pub overrides: Vec<(String, String)>,
+
+    /// Seed of a deterministic run
+    #[arg(long, global = true)]
+    pub seed: Option<u64>,
+
+    /// Run deterministically with the seed of the config
+    #[arg(long, global = true)]
+    pub deterministic: bool,
+
+    /// Log level, which overrides RUST_LOG
+    #[arg(long, global = true, value_enum)]
+    pub log_level: Option<LogLevel>,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct SolveArgs {
+    /// Path to problem instance
     pub instance_path: String,
-    pub solution_path: Option<String>,
-    pub time_limit: Option<u64>,
+
+    /// Path to solution output
+    #[arg(short = 'o', long = "output", default_value = "output.sol")]
+    pub solution_path: String,
+
+    /// Maximum number of iterations without improvement
+    #[arg(short = 'i', long = "iterations")]
     pub max_iterations: Option<u64>,
+
+    /// Time limit in seconds
+    #[arg(short, long)]
+    pub time_limit: Option<u64>,
+
+    /// Round the distances to the nearest integer
+    #[arg(short = 'r', long)]
+    pub rounded: bool,
+
+    /// Do not round the distances, even if the config does
+    #[arg(long = "no-round", conflicts_with = "rounded")]
+    pub no_round: bool,
+
+    /// Format of the problem instance read, detected from the extension by default
+    #[arg(long = "instance-format", alias = "input-format", value_enum)]
+    pub instance_format: Option<FileFormat>,
+
+    /// Format of the solution written, detected from the extension by default
+    #[arg(long = "output-format", value_enum)]
+    pub output_format: Option<FileFormat>,
+
+    /// Path to an SVG plot of the best solution
+    #[arg(long = "plot")]
+    pub plot_path: Option<String>,
+
+    /// Directory for an SVG frame of each new best solution
+    #[arg(long)]
+    pub plot_frames: Option<String>,
+
+    /// Path to the search statistics at each log interval, as JSON with the .json extension and
+    /// CSV otherwise
+    #[arg(long = "trace", value_name = "PATH")]
+    pub trace_path: Option<String>,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct ValidateArgs {
+    /// Path to problem instance
+    pub instance_path: String,
+
+    /// Path to the TSPLIB or JSON solution
+    pub solution_path: String,
+
+    /// Round the distances to the nearest integer
+    #[arg(short = 'r', long)]
     pub rounded: bool,
+
+    /// Do not round the distances, even if the config does
+    #[arg(long = "no-round", conflicts_with = "rounded")]
+    pub no_round: bool,
+
+    /// Format of the problem instance read, detected from the extension by default
+    #[arg(long = "instance-format", alias = "input-format", value_enum)]
+    pub instance_format: Option<FileFormat>,
+
+    /// Format of the solution read, detected from the extension by default
+    #[arg(long = "solution-format", value_enum)]
+    pub solution_format: Option<FileFormat>,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct ConvertArgs {
+    /// Path to problem instance
+    pub instance_path: String,
+
+    /// Path to the converted problem, or solution if a solution is given
+    pub output_path: String,
+
+    /// Solution of the instance to convert instead of the instance
+    #[arg(long = "solution", value_name = "PATH")]
+    pub solution_path: Option<String>,
+
+    /// Format of the problem instance read, detected from the extension by default
+    #[arg(long = "instance-format", alias = "input-format", value_enum)]
+    pub instance_format: Option<FileFormat>,
+
+    /// Format of the solution read, detected from the extension by default
+    #[arg(long = "solution-format", value_enum)]
+    pub solution_format: Option<FileFormat>,
+
+    /// Format of the problem or solution written, detected from the extension by default
+    #[arg(long = "output-format", value_enum)]
+    pub output_format: Option<FileFormat>,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct BenchArgs {
+    /// Problem instances, directories of instances or glob patterns
+    #[arg(required = true)]
+    pub instance_paths: Vec<String>,
+
+    /// Time limit in seconds for each run
+    #[arg(short, long)]
+    pub time_limit: Option<u64>,
+
+    /// Seeds of the runs of each instance, defaults to the seed of the config
+    #[arg(long, value_delimiter = ',')]
+    pub seeds: Vec<u64>,
+
+    /// CSV file with the instance name and the best known solution on each line, where the
+    /// instance comment is used otherwise
+    #[arg(long, value_name = "PATH")]
+    pub bks: Option<String>,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value = "csv")]
+    pub format: ReportFormat,
+
+    /// Path to the report, which is printed by default
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    pub report_path: Option<String>,
+
+    /// Previous CSV report to compare the average costs with
+    #[arg(long, value_name = "PATH")]
+    pub compare: Option<String>,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct TuneArgs {
+    /// Problem instances, directories of instances or glob patterns
+    #[arg(required = true)]
+    pub instance_paths: Vec<String>,
+
+    /// YAML file with the config fields to tune and their domains
+    #[arg(long, value_name = "PATH")]
+    pub space: String,
+
+    /// Time limit in seconds for each run
+    #[arg(short, long)]
+    pub time_limit: Option<u64>,
+
+    /// Seeds of the runs of each instance, defaults to the seed of the config
+    #[arg(long, value_delimiter = ',')]
+    pub seeds: Vec<u64>,
+
+    /// Maximum number of solver runs
+    #[arg(long, default_value_t = 200)]
+    pub budget: usize,
+
+    /// Number of instances and seeds before candidates are eliminated
+    #[arg(long, default_value_t = 5)]
+    pub first_test: usize,
+
+    /// Path to the tuned config
+    #[arg(
+        short = 'o',
+        long = "output",
+        value_name = "PATH",
+        default_value = "tuned.yml"
+    )]
+    pub config_path: String,
+}
+
+#[derive(Clone, Debug, ClapArgs)]
+pub struct InfoArgs {
+    /// Path to problem instance
+    pub instance_path: String,
+
+    /// Format of the problem instance read, detected from the extension by default
+    #[arg(long = "instance-format", alias = "input-format", value_enum)]
+    pub instance_format: Option<FileFormat>,
+
+    /// Print the information as JSON
+    #[arg(long)]
+    pub json: bool,
 }
 
 impl Args {
-    /// Setup the clap app and parse the command line arguments
-    pub fn parse() -> Self {
-        let matches = App::new("hybridcvrp")
-            .version("0.1")
-            .author("Martin Simensen")
-            .about("Hybrid Metaheuristic Solver for the Capacitated Vehicle Routing Problem")
-            .arg(
-                Arg::with_name("instance_path")
-                    .required(true)
-                    .help("Path to problem instance"),
-            )
-            .arg(
-                Arg::with_name("solution_path")
-                    .short("o")
-                    .takes_value(true)
-                    .default_value("output.sol")
-                    .help("Path to solution output"),
-            )
-            .arg(
-                Arg::with_name("iterations")
-                    .short("i")
-                    .takes_value(true)
-                    .help("Maximum number of iterations without improvement"),
-            )
-            .arg(
-                Arg::with_name("time_limit")
-                    .short("t")
-                    .takes_value(true)
-                    .default_value("10")
-                    .help("Time limit in seconds"),
-            )
-            .arg(
-                Arg::with_name("rounded")
-                    .short("r")
-                    .takes_value(true)
-                    .default_value("true")
-                    .help("Rounded distances"),
-            )
-            .get_matches();
-
-        let instance_path = matches
-            .value_of("instance_path")
-            .expect("Instance path is not provided")
-            .to_owned();
-
-        let solution_path = matches.value_of("solution_path").map(String::from);
-
-        let max_iterations = matches
-            .value_of("iterations")
-            .map(|value| value.parse::<u64>().expect("Invalid iterations argument!"));
-
-        let time_limit = matches
-            .value_of("time_limit")
-            .map(|value| value.parse::<u64>().expect("Invalid time limit argument!"));
-
-        let rounded = matches.is_present("rounded");
-
-        Self {
-            instance_path,
-            solution_path,
-            time_limit,
-            max_iterations,
-            rounded,
+    /// The command to run, where solving is the default
+    pub fn command(&self) -> Command {
+        match (&self.command, &self.solve) {
+            (Some(command), _) => command.clone(),
+            (None, Some(solve)) => Command::Solve(solve.clone()),
+            (None, None) => unreachable!("clap requires the instance path without a subcommand"),
+        }
+    }
+}
+
+impl GlobalOptions {
+    /// Load the config file and apply the overrides of the command line
+    pub fn load_config(&self) -> Config {
+        let mut config = match self.config.as_ref() {
+            Some(path) => Config::load_yaml_file(path),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Config::load_yaml_file(DEFAULT_CONFIG_PATH)
+            }
+            None => Config::default(),
+        };
+        for (key, value) in self.overrides.iter() {
+            config
+                .set(key, value)
+                .unwrap_or_else(|err| panic!("Invalid override: {}", err));
+        }
+        if let Some(seed) = self.seed {
+            config.seed = seed;
+            config.deterministic = true;
+        }
+        if self.deterministic {
+            config.deterministic = true;
+        }
+        config
+    }
+}
+
+// Parse an override, where the field and the value are checked against the default config
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_owned(), value.to_owned()))
+        .ok_or_else(|| format!("Expected KEY=VALUE, got '{}'", value))?;
+    Config::default().set(&key, &value)?;
+    Ok((key, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        // Solving without a subcommand, where -r does not take the instance path as a value
+        let args = Args::try_parse_from(["hybridcvrp", "-r", "X-n101-k25.vrp", "-t", "5"]).unwrap();
+        match args.command() {
+            Command::Solve(solve) => {
+                assert_eq!("X-n101-k25.vrp", solve.instance_path);
+                assert!(solve.rounded);
+                assert!(!solve.no_round);
+                assert_eq!(Some(5), solve.time_limit);
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+        assert!(
+            Args::try_parse_from(["hybridcvrp", "-r", "--no-round", "X-n101-k25.vrp"]).is_err()
+        );
+
+        let args = Args::try_parse_from([
+            "hybridcvrp",
+            "--seed",
+            "7",
+            "validate",
+            "X-n101-k25.vrp",
+            "output.sol",
+            "--set",
+            "round_distances=false",
+        ])
+        .unwrap();
+        assert!(matches!(args.command(), Command::Validate(_)));
+        let config = args.options.load_config();
+        assert!(config.deterministic);
+        assert_eq!(7, config.seed);
+        assert!(!config.round_distances);
+
+        // Validating takes the same rounding flags as solving
+        let args = Args::try_parse_from([
+            "hybridcvrp",
+            "validate",
+            "X-n101-k25.vrp",
+            "output.sol",
+            "--no-round",
+        ])
+        .unwrap();
+        match args.command() {
+            Command::Validate(validate) => assert!(validate.no_round && !validate.rounded),
+            command => panic!("Unexpected command {:?}", command),
+        }
+        assert!(Args::try_parse_from([
+            "hybridcvrp",
+            "validate",
+            "X-n101-k25.vrp",
+            "output.sol",
+            "-r",
+            "--no-round"
+        ])
+        .is_err());
+
+        // The format flags are named by the file they apply to, in every subcommand
+        let args = Args::try_parse_from([
+            "hybridcvrp",
+            "convert",
+            "X-n101-k25.vrp",
+            "output.json",
+            "--solution",
+            "output.sol",
+            "--input-format",
+            "tsplib",
+            "--solution-format",
+            "tsplib",
+            "--output-format",
+            "json",
+        ])
+        .unwrap();
+        match args.command() {
+            Command::Convert(convert) => {
+                assert_eq!(Some(FileFormat::Tsplib), convert.instance_format);
+                assert_eq!(Some(FileFormat::Tsplib), convert.solution_format);
+                assert_eq!(Some(FileFormat::Json), convert.output_format);
+            }
+            command => panic!("Unexpected command {:?}", command),
+        }
+
+        // Unknown fields and invalid values are usage errors
+        for set in ["no_such_field=1", "time_limit=soon"].iter() {
+            let err =
+                Args::try_parse_from(["hybridcvrp", "X-n101-k25.vrp", "--set", set]).unwrap_err();
+            assert_eq!(clap::error::ErrorKind::ValueValidation, err.kind());
         }
     }
 }
@@ -4,7 +4,10 @@ use std::io::BufReader;
 use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 
-use crate::cli::Args;
+use crate::cli::SolveArgs;
+use crate::models::DistancePrecision;
+use crate::solver::genetic::{ParentSelectionKind, SurvivorSelectionKind};
+use crate::utils::FileFormat;
 
 /// Contains all the configuration parameters
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -12,14 +15,23 @@ pub struct Config {
     // General
     pub instance_path: String,
     pub solution_path: Option<String>,
+    pub instance_format: FileFormat,
+    pub solution_format: FileFormat,
+    pub plot_path: Option<String>,
+    pub plot_frames: Option<String>,
+    pub trace_path: Option<String>,
     pub time_limit: u64,
     pub bks: f64,
+    pub dimacs: bool,
+    pub dimacs_cpu_factor: f64,
     pub max_iterations: Option<u64>,
     pub max_iterations_without_improvement: u64,
     pub num_vehicles: u64,
     pub log_interval: u64,
     pub precompute_distance_size_limit: u64,
     pub round_distances: bool,
+    pub distance_precision: DistancePrecision,
+    pub triangular_distances: bool,
     pub decompose_limit: u64,
     pub decomposed_problem_min_size: u64,
 
@@ -37,6 +49,23 @@ pub struct Config {
     pub tournament_size: u64,
     pub repair_probability: f64,
 
+    // Selection
+    pub parent_selection: ParentSelectionKind,
+    pub survivor_selection: SurvivorSelectionKind,
+    pub selection_pressure: f64,
+    pub mating_candidates: u64,
+    pub mating_min_distance: f64,
+    pub mating_max_distance: f64,
+
+    // Crossover
+    pub crossover_ox: bool,
+    pub crossover_srex: bool,
+    pub crossover_eax: bool,
+    pub crossover_edge_assembly: bool,
+    pub adaptive_crossover: bool,
+    pub crossover_reaction_factor: f64,
+    pub crossover_update_interval: u64,
+
     // Split
     pub split_capacity_factor: f64,
     pub linear_split: bool,
@@ -51,6 +80,8 @@ pub struct Config {
     pub relocate_single: bool,
     pub relocate_double: bool,
     pub relocate_double_reverse: bool,
+    pub relocate_triple: bool,
+    pub relocate_triple_reverse: bool,
     pub swap_one_with_one: bool,
     pub swap_two_with_one: bool,
     pub swap_two_with_two: bool,
@@ -58,6 +89,14 @@ pub struct Config {
     pub two_opt_inter_reverse: bool,
     pub two_opt_inter: bool,
     pub swap_star: bool,
+    pub cross_exchange: bool,
+    pub cross_exchange_reverse: bool,
+    pub cross_exchange_max_length: u64,
+
+    // Route optimization
+    pub tsp_optimize_best: bool,
+    pub tsp_after_local_search: bool,
+    pub tsp_exact_size_limit: u64,
 
     // Penalties
     pub penalty_capacity: f64,
@@ -83,6 +122,26 @@ pub struct Config {
     pub elite_education_start_temp: f64,
     pub elite_education_time_based: bool,
     pub elite_education_time_fraction: f64,
+
+    // Route elimination
+    pub route_elimination: bool,
+    pub route_elimination_interval: u64,
+    pub route_elimination_max_iterations: u64,
+    pub route_elimination_max_ejections: u64,
+    pub route_elimination_perturbation_moves: u64,
+    pub fleet_minimization: bool,
+
+    // Set partitioning
+    pub set_partitioning: bool,
+    pub set_partitioning_interval: u64,
+    pub set_partitioning_time_limit: f64,
+    pub set_partitioning_neighborhood: u64,
+    pub route_pool_size: u64,
+
+    // Lower bound
+    pub lower_bound: bool,
+    pub lower_bound_max_iterations: u64,
+    pub lower_bound_time_limit: f64,
 }
 
 impl Config {
@@ -91,14 +150,23 @@ impl Config {
             // General
             instance_path: String::new(),
             solution_path: None,
+            instance_format: FileFormat::Auto,
+            solution_format: FileFormat::Auto,
+            plot_path: None,
+            plot_frames: None,
+            trace_path: None,
             time_limit: 60,
             bks: f64::INFINITY,
+            dimacs: false,
+            dimacs_cpu_factor: 1.0,
             max_iterations: None,
             max_iterations_without_improvement: 20_000,
             num_vehicles: 1_000_000,
             log_interval: 100,
             precompute_distance_size_limit: 2_000,
             round_distances: true,
+            distance_precision: DistancePrecision::F64,
+            triangular_distances: false,
             decompose_limit: 3000,
             decomposed_problem_min_size: 200,
 
@@ -116,6 +184,23 @@ impl Config {
             tournament_size: 2,
             repair_probability: 0.5,
 
+            // Selection
+            parent_selection: ParentSelectionKind::Tournament,
+            survivor_selection: SurvivorSelectionKind::BiasedFitness,
+            selection_pressure: 1.5,
+            mating_candidates: 4,
+            mating_min_distance: 0.05,
+            mating_max_distance: 0.5,
+
+            // Crossover
+            crossover_ox: true,
+            crossover_srex: false,
+            crossover_eax: false,
+            crossover_edge_assembly: false,
+            adaptive_crossover: false,
+            crossover_reaction_factor: 0.2,
+            crossover_update_interval: 100,
+
             // Split
             split_capacity_factor: 1.5,
             linear_split: true,
@@ -130,6 +215,8 @@ impl Config {
             relocate_single: true,
             relocate_double: true,
             relocate_double_reverse: false,
+            relocate_triple: false,
+            relocate_triple_reverse: false,
             swap_one_with_one: true,
             swap_two_with_one: true,
             swap_two_with_two: true,
@@ -137,6 +224,14 @@ impl Config {
             two_opt_inter_reverse: true,
             two_opt_inter: true,
             swap_star: true,
+            cross_exchange: false,
+            cross_exchange_reverse: false,
+            cross_exchange_max_length: 3,
+
+            // Route optimization
+            tsp_optimize_best: true,
+            tsp_after_local_search: false,
+            tsp_exact_size_limit: 12,
 
             // Penalties
             penalty_capacity: 100.0,
@@ -162,6 +257,26 @@ impl Config {
             elite_education_start_temp: 50.0,
             elite_education_time_based: false,
             elite_education_time_fraction: 0.02,
+
+            // Route elimination
+            route_elimination: false,
+            route_elimination_interval: 1000,
+            route_elimination_max_iterations: 10_000,
+            route_elimination_max_ejections: 3,
+            route_elimination_perturbation_moves: 10,
+            fleet_minimization: false,
+
+            // Set partitioning
+            set_partitioning: false,
+            set_partitioning_interval: 2000,
+            set_partitioning_time_limit: 1.0,
+            set_partitioning_neighborhood: 6,
+            route_pool_size: 20_000,
+
+            // Lower bound
+            lower_bound: false,
+            lower_bound_max_iterations: 1000,
+            lower_bound_time_limit: 10.0,
         }
     }
 
@@ -210,16 +325,58 @@ impl Config {
         *self = serde_yaml::from_value(config).expect("Failed to deserialize patched config");
     }
 
+    /// Time limit in seconds, which is scaled by the CPU factor in DIMACS mode
+    pub fn time_limit_secs(&self) -> f64 {
+        match self.dimacs {
+            true => self.time_limit as f64 * self.dimacs_cpu_factor,
+            false => self.time_limit as f64,
+        }
+    }
+
+    /// Set a single field from its name and a YAML value, as in `--set time_limit=60`, where an
+    /// unknown field or a value of the wrong type is an error
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut config: Value = serde_yaml::to_value(&self).expect("Failed to serialize config");
+        let value: Value = serde_yaml::from_str(value)
+            .map_err(|err| format!("Invalid value for {}: {}", key, err))?;
+        match config.get_mut(key) {
+            Some(config_value) => *config_value = value,
+            None => return Err(format!("Unknown config field: {}", key)),
+        }
+        *self = serde_yaml::from_value(config)
+            .map_err(|err| format!("Invalid value for {}: {}", key, err))?;
+        Ok(())
+    }
+
     /// Update config with command line arguments
-    pub fn update_from_args(&mut self, args: &Args) {
+    pub fn update_from_args(&mut self, args: &SolveArgs) {
         self.instance_path = args.instance_path.clone();
-        self.solution_path = args.solution_path.clone();
+        self.solution_path = Some(args.solution_path.clone());
+        if let Some(instance_format) = args.instance_format {
+            self.instance_format = instance_format;
+        }
+        if let Some(output_format) = args.output_format {
+            self.solution_format = output_format;
+        }
+        if let Some(plot_path) = args.plot_path.as_ref() {
+            self.plot_path = Some(plot_path.clone());
+        }
+        if let Some(plot_frames) = args.plot_frames.as_ref() {
+            self.plot_frames = Some(plot_frames.clone());
+        }
+        if let Some(trace_path) = args.trace_path.as_ref() {
+            self.trace_path = Some(trace_path.clone());
+        }
         if let Some(max_iterations) = args.max_iterations {
             self.max_iterations_without_improvement = max_iterations;
         }
         if let Some(time_limit) = args.time_limit {
             self.time_limit = time_limit;
         }
-        self.round_distances = args.rounded;
+        if args.rounded {
+            self.round_distances = true;
+        } else if args.no_round {
+            self.round_distances = false;
+        }
     }
 }
@@ -1,7 +1,14 @@
+pub mod bench;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod cli;
 pub mod config;
 pub mod models;
+pub mod plot;
+#[cfg(feature = "python")]
+pub mod python;
 pub mod solver;
+pub mod tune;
 pub mod utils;
 
 #[cfg(feature = "wasm")]
@@ -1,54 +1,287 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use clap::Parser;
 use instant::Instant;
+use serde_json::json;
 
-use hybridcvrp::cli::Args;
+use hybridcvrp::bench;
+use hybridcvrp::cli::{Args, BenchArgs, Command, ConvertArgs, InfoArgs, TuneArgs, ValidateArgs};
 use hybridcvrp::config::Config;
-use hybridcvrp::solver::genetic::{DecomposedGeneticAlgorithm, GeneticAlgorithm};
-use hybridcvrp::solver::{Context, Solver};
-use hybridcvrp::utils;
+use hybridcvrp::plot;
+use hybridcvrp::solver::improvement::route_distance;
+use hybridcvrp::solver::{dimacs, run_solver, Context, HistoricSolution};
+use hybridcvrp::tune::{self, ParameterSpace, TuneSettings, Tuner};
+use hybridcvrp::utils::{self, ProblemParser, SolutionFile};
 use log::LevelFilter;
 
 fn main() {
     // Start time of program
     let start_time = Instant::now();
 
-    // Initialize logger
-    env_logger::Builder::default()
+    // Parse command line arguments
+    let args = Args::parse();
+
+    // Initialize logger, where the log level argument takes precedence over RUST_LOG
+    let mut logger = env_logger::Builder::default();
+    logger
         .filter_level(LevelFilter::Info)
         .parse_default_env()
-        .format_module_path(false)
-        .init();
+        .format_module_path(false);
+    if let Some(log_level) = args.options.log_level {
+        logger.filter_level(log_level.into());
+    }
+    logger.init();
 
     // Load config
-    let mut config = Config::load_yaml_file("config.yml");
+    let config = args.options.load_config();
     log::info!("Loading config");
 
-    // Parse command line arguments
-    let args = Args::parse();
-    config.update_from_args(&args);
+    match args.command() {
+        Command::Solve(solve_args) => {
+            let mut config = config;
+            config.update_from_args(&solve_args);
+            solve(config, start_time);
+        }
+        Command::Validate(validate_args) => validate(config, validate_args),
+        Command::Convert(convert_args) => convert(config, convert_args),
+        Command::Bench(bench_args) => run_bench(config, bench_args),
+        Command::Tune(tune_args) => run_tune(config, tune_args),
+        Command::Info(info_args) => info(config, info_args),
+    }
+}
 
+fn solve(mut config: Config, start_time: Instant) {
     log::info!("Loading problem file: {}", config.instance_path);
-    let mut parser = utils::ProblemParser::new();
+    let mut parser = ProblemParser::new();
     parser.parse(&mut config);
     let ctx = Context::new(parser, config, start_time);
     log::info!("Problem load complete");
 
-    let should_decompose =
-        ctx.problem.num_customers() as u64 >= ctx.config.borrow().decompose_limit;
+    let ctx = run_solver(ctx);
+    utils::write_solution_file(&ctx);
+    utils::write_trace_file(&ctx);
+    plot::write_best_plot(&ctx);
+    if ctx.config.borrow().dimacs {
+        dimacs::report_primal_integral(&ctx);
+    }
+}
+
+fn validate(mut config: Config, args: ValidateArgs) {
+    config.instance_path = args.instance_path;
+    if let Some(instance_format) = args.instance_format {
+        config.instance_format = instance_format;
+    }
+    if let Some(solution_format) = args.solution_format {
+        config.solution_format = solution_format;
+    }
+    if args.rounded {
+        config.round_distances = true;
+    } else if args.no_round {
+        config.round_distances = false;
+    }
+
+    let report = utils::validate_solution_file(&config, &args.solution_path);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+    );
+    std::process::exit(if report.valid { 0 } else { 1 });
+}
 
-    match should_decompose {
-        true => {
-            let metaheuristic = DecomposedGeneticAlgorithm::new(&ctx);
-            let mut solver = Solver::new(ctx, metaheuristic);
-            log::info!("Algorithm: Decomposed Genetic Algorithm");
-            solver.run();
-            utils::write_solution_file(&solver.ctx);
+fn convert(mut config: Config, args: ConvertArgs) {
+    let output_path = args.output_path;
+    config.instance_path = args.instance_path;
+    if let Some(instance_format) = args.instance_format {
+        config.instance_format = instance_format;
+    }
+    let output_format = args
+        .output_format
+        .unwrap_or(config.solution_format)
+        .resolve(&output_path);
+
+    let mut parser = ProblemParser::new();
+    parser.parse(&mut config);
+    let file = File::create(&output_path)
+        .unwrap_or_else(|err| panic!("Failed to create {}: {}", output_path, err));
+    let mut writer = BufWriter::new(file);
+
+    match args.solution_path {
+        Some(solution_path) => {
+            let solution_format = args.solution_format.unwrap_or(config.solution_format);
+            let solution = SolutionFile::read(&solution_path, solution_format)
+                .unwrap_or_else(|err| panic!("Failed to read solution: {}", err));
+            let ctx = Context::new(parser, config, Instant::now());
+            let routes = solution
+                .routes_by_index(&ctx.problem)
+                .unwrap_or_else(|err| panic!("Invalid solution: {}", err));
+            let cost = routes
+                .iter()
+                .map(|route| route_distance(&ctx.matrix_provider.distance, route))
+                .sum();
+            ctx.search_history
+                .borrow_mut()
+                .add_solution(HistoricSolution { routes, cost });
+            utils::write_solution(&mut writer, &ctx, output_format)
         }
-        false => {
-            let metaheuristic = GeneticAlgorithm::new(&ctx);
-            let mut solver = Solver::new(ctx, metaheuristic);
-            log::info!("Algorithm: Genetic Algorithm");
-            solver.run();
-            utils::write_solution_file(&solver.ctx);
+        None => {
+            let problem = parser.problem.take().expect("Failed to parse problem");
+            let name = Path::new(&config.instance_path)
+                .file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+            utils::write_problem(
+                &mut writer,
+                &problem,
+                &name,
+                parser.matrix.as_deref(),
+                output_format,
+            )
         }
+    }
+    .and_then(|_| writer.flush())
+    .unwrap_or_else(|err| panic!("Failed to write {}: {}", output_path, err));
+}
+
+fn run_bench(config: Config, args: BenchArgs) {
+    let instance_paths = bench::expand_instance_paths(&args.instance_paths);
+    assert!(!instance_paths.is_empty(), "No instances found");
+    let seeds = match args.seeds.is_empty() {
+        true => vec![config.seed],
+        false => args.seeds.clone(),
     };
+    let mut bks = args
+        .bks
+        .as_deref()
+        .map(bench::read_bks_csv)
+        .unwrap_or_default();
+
+    let mut results = Vec::new();
+    for instance_path in instance_paths.iter() {
+        let instance = bench::instance_name(instance_path);
+        if !bks.contains_key(&instance) {
+            if let Some(value) = bench::bks_from_instance(instance_path) {
+                bks.insert(instance.clone(), value);
+            }
+        }
+        for &seed in seeds.iter() {
+            let mut config = config.clone();
+            config.instance_path = instance_path.clone();
+            config.deterministic = true;
+            config.seed = seed;
+            if let Some(time_limit) = args.time_limit {
+                config.time_limit = time_limit;
+            }
+            let result = bench::bench_instance(config);
+            log::warn!(
+                "{} with seed {}: {} after {:.2}s",
+                instance,
+                seed,
+                result.cost,
+                result.time_to_best.unwrap_or(result.runtime)
+            );
+            results.push(result);
+        }
+    }
+
+    let summaries = bench::summarize(&results, &bks);
+    let previous = args.compare.as_deref().map(bench::read_report_csv);
+    let report = match args.report_path.as_ref() {
+        Some(report_path) => {
+            let file = File::create(report_path)
+                .unwrap_or_else(|err| panic!("Failed to create {}: {}", report_path, err));
+            let mut writer = BufWriter::new(file);
+            bench::write_report(&mut writer, &summaries, args.format, previous.as_ref())
+                .and_then(|_| writer.flush())
+        }
+        None => bench::write_report(
+            &mut io::stdout().lock(),
+            &summaries,
+            args.format,
+            previous.as_ref(),
+        ),
+    };
+    report.expect("Failed to write benchmark report");
+}
+
+fn run_tune(mut config: Config, args: TuneArgs) {
+    let instance_paths = bench::expand_instance_paths(&args.instance_paths);
+    assert!(!instance_paths.is_empty(), "No instances found");
+    let seeds = match args.seeds.is_empty() {
+        true => vec![config.seed],
+        false => args.seeds.clone(),
+    };
+    if let Some(time_limit) = args.time_limit {
+        config.time_limit = time_limit;
+    }
+    let space = ParameterSpace::load_yaml_file(&args.space);
+    let settings = TuneSettings {
+        budget: args.budget,
+        first_test: args.first_test,
+    };
+
+    let mut tuner = Tuner::new(config.clone(), space, &instance_paths, &seeds, settings);
+    let result = tuner.run();
+    log::warn!(
+        "Best configuration with mean cost {:.2} over {} blocks after {} runs",
+        result.mean_cost,
+        result.blocks,
+        result.runs
+    );
+    println!(
+        "{}",
+        serde_yaml::to_string(&result.parameters).expect("Failed to serialize parameters")
+    );
+
+    tune::apply_parameters(&mut config, &result.parameters);
+    let file = File::create(&args.config_path)
+        .unwrap_or_else(|err| panic!("Failed to create {}: {}", args.config_path, err));
+    serde_yaml::to_writer(BufWriter::new(file), &config)
+        .unwrap_or_else(|err| panic!("Failed to write {}: {}", args.config_path, err));
+}
+
+fn info(mut config: Config, args: InfoArgs) {
+    config.instance_path = args.instance_path;
+    if let Some(instance_format) = args.instance_format {
+        config.instance_format = instance_format;
+    }
+    let mut parser = ProblemParser::new();
+    parser.parse(&mut config);
+    let problem = parser.problem.as_ref().expect("Failed to parse problem");
+
+    let name = Path::new(&config.instance_path)
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let total_demand = problem.total_demand();
+    let min_vehicles = (total_demand / problem.vehicle.cap).ceil();
+    let distances = match parser.matrix {
+        Some(_) => "explicit",
+        None => "euclidean",
+    };
+
+    if args.json {
+        let info = json!({
+            "name": name,
+            "dimension": problem.dim(),
+            "customers": problem.num_customers(),
+            "capacity": problem.vehicle.cap,
+            "total_demand": total_demand,
+            "max_demand": problem.max_demand(),
+            "min_vehicles": min_vehicles,
+            "distances": distances,
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&info).expect("Failed to serialize info")
+        );
+    } else {
+        println!("Name:         {}", name);
+        println!("Dimension:    {}", problem.dim());
+        println!("Customers:    {}", problem.num_customers());
+        println!("Capacity:     {}", problem.vehicle.cap);
+        println!("Total demand: {}", total_demand);
+        println!("Max demand:   {}", problem.max_demand().unwrap_or(0.0));
+        println!("Min vehicles: {}", min_vehicles);
+        println!("Distances:    {}", distances);
+    }
 }
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::Matrix;
+
+/// Number type of the precomputed distances
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistancePrecision {
+    F64,
+    F32,
+    // Only valid for rounded distances
+    I32,
+}
+
+impl DistancePrecision {
+    /// Number of bytes of a stored distance
+    pub fn bytes(&self) -> usize {
+        match self {
+            DistancePrecision::F64 => 8,
+            DistancePrecision::F32 | DistancePrecision::I32 => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Values {
+    F64(Matrix<f64>),
+    F32(Matrix<f32>),
+    I32(Matrix<i32>),
+}
+
+/// Storage of the precomputed distances.
+///
+/// The distances are stored as a full matrix, or only the upper triangle including the diagonal
+/// for symmetric distances, which halves the memory. Storing them as `f32` or `i32` halves the
+/// memory again.
+#[derive(Clone, Debug)]
+pub struct DistanceStorage {
+    values: Values,
+    size: usize,
+    triangular: bool,
+}
+
+impl DistanceStorage {
+    pub fn new(size: usize, precision: DistancePrecision, triangular: bool) -> Self {
+        let length = Self::length(size, triangular);
+        let values = match precision {
+            DistancePrecision::F64 => Values::F64(Matrix::new(1, length)),
+            DistancePrecision::F32 => Values::F32(Matrix::new(1, length)),
+            DistancePrecision::I32 => Values::I32(Matrix::new(1, length)),
+        };
+        Self {
+            values,
+            size,
+            triangular,
+        }
+    }
+
+    /// Number of bytes of the storage for `size` locations
+    pub fn memory(size: usize, precision: DistancePrecision, triangular: bool) -> usize {
+        Self::length(size, triangular) * precision.bytes()
+    }
+
+    fn length(size: usize, triangular: bool) -> usize {
+        match triangular {
+            true => size * (size + 1) / 2,
+            false => size * size,
+        }
+    }
+
+    /// Convert full storage of f64 into a square matrix, which is faster to access. Returns
+    /// the storage itself for the other layouts.
+    pub fn into_full_matrix(self) -> Result<Matrix<f64>, Self> {
+        match self {
+            Self {
+                values: Values::F64(values),
+                size,
+                triangular: false,
+            } => Ok(values.reshape(size, size)),
+            storage => Err(storage),
+        }
+    }
+
+    #[inline]
+    fn index(&self, row: usize, col: usize) -> usize {
+        // The triangular index of a location outside the storage can be within the buffer
+        assert!(
+            row < self.size && col < self.size,
+            "Index ({}, {}) out of bounds for {} locations",
+            row,
+            col,
+            self.size
+        );
+        match self.triangular {
+            true => {
+                let (row, col) = if row <= col { (row, col) } else { (col, row) };
+                row * self.size - row * (row + 1) / 2 + col
+            }
+            false => row * self.size + col,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        match &self.values {
+            Values::F64(values) => values.get(0, self.index(row, col)),
+            Values::F32(values) => values.get(0, self.index(row, col)) as f64,
+            Values::I32(values) => values.get(0, self.index(row, col)) as f64,
+        }
+    }
+
+    /// Set the distance, which also sets the symmetric distance with triangular storage
+    #[inline]
+    pub fn set(&mut self, row: usize, col: usize, value: f64) {
+        let index = self.index(row, col);
+        match &mut self.values {
+            Values::F64(values) => values.set(0, index, value),
+            Values::F32(values) => values.set(0, index, value as f32),
+            Values::I32(values) => values.set(0, index, value.round() as i32),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn precision(&self) -> DistancePrecision {
+        match self.values {
+            Values::F64(_) => DistancePrecision::F64,
+            Values::F32(_) => DistancePrecision::F32,
+            Values::I32(_) => DistancePrecision::I32,
+        }
+    }
+
+    pub fn is_triangular(&self) -> bool {
+        self.triangular
+    }
+
+    /// Largest distance, or None if the storage is empty
+    pub fn max(&self) -> Option<f64> {
+        let max = match &self.values {
+            Values::F64(values) if values.cols() > 0 => values.get_max(),
+            Values::F32(values) if values.cols() > 0 => values.get_max() as f64,
+            Values::I32(values) if values.cols() > 0 => values.get_max() as f64,
+            _ => return None,
+        };
+        Some(max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangular_storage_is_symmetric() {
+        let size = 7;
+        for &precision in [
+            DistancePrecision::F64,
+            DistancePrecision::F32,
+            DistancePrecision::I32,
+        ]
+        .iter()
+        {
+            let mut storage = DistanceStorage::new(size, precision, true);
+            for row in 0..size {
+                for col in row..size {
+                    storage.set(row, col, (row * 10 + col) as f64);
+                }
+            }
+            for row in 0..size {
+                for col in 0..size {
+                    let expected = (row.min(col) * 10 + row.max(col)) as f64;
+                    assert_eq!(expected, storage.get(row, col));
+                }
+            }
+            assert_eq!(Some(66.0), storage.max());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn triangular_index_out_of_bounds() {
+        // The triangular index of (0, 7) is within the buffer of 28 distances
+        let storage = DistanceStorage::new(7, DistancePrecision::F64, true);
+        storage.get(0, 7);
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::models::Coordinate;
+
+// Subtrees with at most this many points are searched linearly
+const LEAF_SIZE: usize = 8;
+
+/// Two dimensional k-d tree for nearest neighbour queries on the node locations.
+///
+/// The tree is stored implicitly in the point array, where the median of each range splits it
+/// alternately on the x and y coordinates.
+pub struct KdTree {
+    // Coordinates and index of the points, ordered as the tree
+    points: Vec<(f64, f64, usize)>,
+}
+
+impl KdTree {
+    /// Build the tree over the locations with the given indices
+    pub fn new(locations: &[Coordinate], indices: impl Iterator<Item = usize>) -> Self {
+        let mut points: Vec<(f64, f64, usize)> = indices
+            .map(|index| (locations[index].lng, locations[index].lat, index))
+            .collect();
+        Self::build(&mut points, 0);
+        Self { points }
+    }
+
+    fn build(points: &mut [(f64, f64, usize)], depth: usize) {
+        if points.len() <= LEAF_SIZE {
+            return;
+        }
+        let mid = points.len() / 2;
+        points.select_nth_unstable_by(mid, |a, b| compare_axis(a, b, depth));
+        let (left, right) = points.split_at_mut(mid);
+        Self::build(left, depth + 1);
+        Self::build(&mut right[1..], depth + 1);
+    }
+
+    /// Find the `k` points closest to the target, ordered by distance and then by index
+    pub fn nearest(&self, target: &Coordinate, k: usize, neighbors: &mut Vec<usize>) {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.search(0, self.points.len(), 0, target, k, &mut heap);
+        }
+        neighbors.clear();
+        neighbors.extend(
+            heap.into_sorted_vec()
+                .into_iter()
+                .map(|neighbor| neighbor.index),
+        );
+    }
+
+    fn search(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        target: &Coordinate,
+        k: usize,
+        heap: &mut BinaryHeap<Neighbor>,
+    ) {
+        if end - start <= LEAF_SIZE {
+            for point in self.points[start..end].iter() {
+                Self::visit(point, target, k, heap);
+            }
+            return;
+        }
+
+        let mid = start + (end - start) / 2;
+        let point = &self.points[mid];
+        Self::visit(point, target, k, heap);
+
+        // Search the side of the target first, and the other side only if it can contain
+        // closer points than the current k-th neighbour
+        let difference = if depth % 2 == 0 {
+            target.lng - point.0
+        } else {
+            target.lat - point.1
+        };
+        let (near, far) = if difference < 0.0 {
+            ((start, mid), (mid + 1, end))
+        } else {
+            ((mid + 1, end), (start, mid))
+        };
+        self.search(near.0, near.1, depth + 1, target, k, heap);
+        let is_reachable = heap.len() < k
+            || heap
+                .peek()
+                .is_some_and(|furthest| difference.powi(2) <= furthest.distance);
+        if is_reachable {
+            self.search(far.0, far.1, depth + 1, target, k, heap);
+        }
+    }
+
+    fn visit(
+        point: &(f64, f64, usize),
+        target: &Coordinate,
+        k: usize,
+        heap: &mut BinaryHeap<Neighbor>,
+    ) {
+        let neighbor = Neighbor {
+            distance: (point.0 - target.lng).powi(2) + (point.1 - target.lat).powi(2),
+            index: point.2,
+        };
+        if heap.len() < k {
+            heap.push(neighbor);
+        } else if heap.peek().is_some_and(|furthest| neighbor < *furthest) {
+            heap.pop();
+            heap.push(neighbor);
+        }
+    }
+}
+
+fn compare_axis(a: &(f64, f64, usize), b: &(f64, f64, usize), depth: usize) -> Ordering {
+    let ordering = if depth % 2 == 0 {
+        a.0.partial_cmp(&b.0)
+    } else {
+        a.1.partial_cmp(&b.1)
+    };
+    ordering.unwrap()
+}
+
+/// Candidate neighbour with the squared distance to the target
+struct Neighbor {
+    distance: f64,
+    index: usize,
+}
+
+impl PartialEq for Neighbor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Neighbor {}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .partial_cmp(&other.distance)
+            .unwrap()
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let locations: Vec<Coordinate> = (0..500)
+            .map(|i| Coordinate {
+                lng: (i * 7919 % 1000) as f64,
+                lat: (i * 104_729 % 997) as f64,
+            })
+            .collect();
+        let tree = KdTree::new(&locations, 1..locations.len());
+
+        let mut neighbors = Vec::new();
+        for target in locations.iter().step_by(37) {
+            let mut expected: Vec<(f64, usize)> = (1..locations.len())
+                .map(|j| {
+                    let distance = (locations[j].lng - target.lng).powi(2)
+                        + (locations[j].lat - target.lat).powi(2);
+                    (distance, j)
+                })
+                .collect();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            let expected: Vec<usize> = expected.iter().take(30).map(|&(_, j)| j).collect();
+
+            tree.nearest(target, 30, &mut neighbors);
+            assert_eq!(expected, neighbors);
+        }
+    }
+}
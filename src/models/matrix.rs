@@ -1,94 +1,123 @@
-use std::{
-    alloc::{alloc_zeroed, dealloc, Layout},
-    fmt::Display,
-};
+use std::fmt::Display;
 
 use lazysort::SortedBy;
 
 use crate::{
     config::Config,
-    models::{Coordinate, Problem},
+    models::{Coordinate, DistancePrecision, DistanceStorage, KdTree, Problem},
     utils::FloatCompare,
 };
 
-#[derive(Debug)]
+/// Dense row-major matrix on an owned buffer.
+///
+/// The buffer always holds exactly `rows * cols` elements, which is checked when the matrix is
+/// created or reshaped. Every access is checked against the buffer, and debug builds also check
+/// that the row and column are within the dimensions, which catches indices running into the next
+/// row. The buffer is a plain `Vec`, so the matrix is `Send` and `Sync` exactly when `T` is.
+#[derive(Clone, Debug, Default)]
 pub struct Matrix<T>
 where
     T: Copy,
 {
-    ptr: *mut T,
-    pub rows: usize,
-    pub cols: usize,
+    data: Vec<T>,
+    rows: usize,
+    cols: usize,
 }
 
-impl<T: Copy> Matrix<T> {
+impl<T: Copy + Default> Matrix<T> {
     pub fn new(rows: usize, cols: usize) -> Self {
-        // Allocate memory for the matrix
-        let elements = rows * cols;
-        let layout = Layout::array::<T>(elements).expect("Failed to create layout for matrix");
-        let ptr = unsafe {
-            let ptr = alloc_zeroed(layout) as *mut T;
-            ptr
-        };
+        Self::init(T::default(), rows, cols)
+    }
 
-        Self { rows, cols, ptr }
+    pub fn from_mapping(&self, mapping: &[usize]) -> Self {
+        let new_size = mapping.len();
+        let mut new_matrix = Self::new(new_size, new_size);
+        for i in 0..new_size {
+            for j in 0..new_size {
+                new_matrix.set(i, j, self.get(mapping[i], mapping[j]));
+            }
+        }
+        new_matrix
     }
+}
 
+impl<T: Copy> Matrix<T> {
     pub fn init(init: T, rows: usize, cols: usize) -> Self {
-        let mut matrix = Self::new(rows, cols);
-        for row in 0..rows {
-            for col in 0..cols {
-                matrix.set(row, col, init);
-            }
+        let len = rows
+            .checked_mul(cols)
+            .unwrap_or_else(|| panic!("Matrix of {}x{} elements is too large", rows, cols));
+        Self {
+            data: vec![init; len],
+            rows,
+            cols,
         }
-        matrix
     }
 
-    #[inline]
+    /// Change the dimensions of the matrix, keeping the elements in row-major order
+    pub fn reshape(mut self, rows: usize, cols: usize) -> Self {
+        assert_eq!(
+            Some(self.data.len()),
+            rows.checked_mul(cols),
+            "Cannot reshape {}x{} matrix into {}x{}",
+            self.rows,
+            self.cols,
+            rows,
+            cols
+        );
+        self.rows = rows;
+        self.cols = cols;
+        self
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    #[inline(always)]
+    fn index(&self, row: usize, col: usize) -> usize {
+        debug_assert!(
+            row < self.rows && col < self.cols,
+            "Index ({}, {}) out of bounds for {}x{} matrix",
+            row,
+            col,
+            self.rows,
+            self.cols
+        );
+        row * self.cols + col
+    }
+
+    #[inline(always)]
     pub fn get(&self, row: usize, col: usize) -> T {
-        unsafe { self.ptr.offset((row * self.cols + col) as isize).read() }
+        self.data[self.index(row, col)]
     }
 
-    #[inline]
-    pub fn get_mut(&self, row: usize, col: usize) -> &mut T {
-        unsafe { &mut *self.ptr.offset((row * self.cols + col) as isize) }
+    #[inline(always)]
+    pub fn get_mut(&mut self, row: usize, col: usize) -> &mut T {
+        let index = self.index(row, col);
+        &mut self.data[index]
     }
 
     #[inline]
     pub fn set(&mut self, row: usize, col: usize, value: T) {
-        unsafe {
-            self.ptr
-                .offset((row * self.cols + col) as isize)
-                .write(value)
-        }
+        *self.get_mut(row, col) = value;
     }
 
+    /// Slice of `number` elements starting at the row and column, which can continue on the
+    /// next rows
     #[inline]
     pub fn slice(&self, row: usize, col: usize, number: usize) -> &[T] {
-        unsafe {
-            std::slice::from_raw_parts(self.ptr.offset((row * self.cols + col) as isize), number)
-        }
+        let start = row * self.cols + col;
+        &self.data[start..start + number]
     }
 
     #[inline]
-    pub fn slice_mut(&self, row: usize, col: usize, number: usize) -> &mut [T] {
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                self.ptr.offset((row * self.cols + col) as isize),
-                number,
-            )
-        }
-    }
-
-    pub fn from_mapping(&self, mapping: &[usize]) -> Self {
-        let new_size = mapping.len();
-        let new_matrix = Self::new(new_size, new_size);
-        for i in 0..new_size {
-            for j in 0..new_size {
-                *new_matrix.get_mut(i, j) = self.get(mapping[i], mapping[j]);
-            }
-        }
-        new_matrix
+    pub fn slice_mut(&mut self, row: usize, col: usize, number: usize) -> &mut [T] {
+        let start = row * self.cols + col;
+        &mut self.data[start..start + number]
     }
 
     pub fn get_max(&self) -> T
@@ -96,34 +125,10 @@ impl<T: Copy> Matrix<T> {
         T: PartialOrd,
     {
         *self
-            .slice(0, 0, self.rows * self.cols)
-            .into_iter()
+            .data
+            .iter()
             .max_by(|&a, &b| a.partial_cmp(b).unwrap())
-            .unwrap()
-    }
-}
-
-impl<T: Copy> Clone for Matrix<T> {
-    fn clone(&self) -> Self {
-        let mut new_matrix = Self::new(self.rows, self.cols);
-
-        for i in 0..self.rows {
-            for j in 0..self.cols {
-                new_matrix.set(i, j, self.get(i, j));
-            }
-        }
-        new_matrix
-    }
-}
-
-impl<T> Drop for Matrix<T>
-where
-    T: Copy,
-{
-    fn drop(&mut self) {
-        let layout =
-            Layout::array::<T>(self.rows * self.cols).expect("Failed to create layout for matrix");
-        unsafe { dealloc(self.ptr as *mut u8, layout) };
+            .expect("Empty matrix")
     }
 }
 
@@ -157,6 +162,8 @@ pub struct DistanceMatrixBuilder {
     rounded: bool,
     input: Option<Vec<Vec<f64>>>,
     max_distance: Option<f64>,
+    precision: DistancePrecision,
+    triangular: bool,
 }
 
 impl DistanceMatrixBuilder {
@@ -167,6 +174,8 @@ impl DistanceMatrixBuilder {
             rounded: false,
             input: None,
             max_distance: None,
+            precision: DistancePrecision::F64,
+            triangular: false,
         }
     }
 
@@ -190,11 +199,22 @@ impl DistanceMatrixBuilder {
         self
     }
 
+    pub fn precision(mut self, precision: DistancePrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    pub fn triangular(mut self, triangular: bool) -> Self {
+        self.triangular = triangular;
+        self
+    }
+
     pub fn build(mut self) -> DistanceMatrix {
+        let explicit = self.input.is_some();
         let matrix = if let Some(input) = self.input {
             self.precompute = true;
             let n = self.locations.len();
-            let mut matrix = Matrix::new(n, n);
+            let mut matrix = DistanceStorage::new(n, self.precision, self.triangular);
             for (i, row) in input.iter().enumerate() {
                 for (j, &distance) in row.iter().enumerate() {
                     let mut distance = distance;
@@ -221,7 +241,7 @@ impl DistanceMatrixBuilder {
             match self.precompute {
                 true => {
                     let n = self.locations.len();
-                    let mut matrix = Matrix::new(n, n);
+                    let mut matrix = DistanceStorage::new(n, self.precision, self.triangular);
 
                     // Assumes a symmetic matrix
                     for i in 0..n {
@@ -248,7 +268,7 @@ impl DistanceMatrixBuilder {
                     }
                     matrix
                 }
-                false => Matrix::new(0, 0),
+                false => DistanceStorage::new(0, self.precision, false),
             }
         };
 
@@ -257,11 +277,16 @@ impl DistanceMatrixBuilder {
             matrix,
             self.precompute,
             self.rounded,
+            explicit,
             self.max_distance,
         )
     }
 }
 
+/// Explicit distances in the lower row format of the parser, where row `i - 1` holds the
+/// distances from node `i` to the nodes before it
+pub type LowerRowMatrix = Vec<Vec<f64>>;
+
 /// Distance matrix.
 ///
 /// Supports lazy evaluation where the distance is calculated every time
@@ -269,85 +294,173 @@ impl DistanceMatrixBuilder {
 #[derive(Debug)]
 pub struct DistanceMatrix {
     locations: Vec<Coordinate>,
+    // Full matrix of f64 distances, which is used if precomputed
     storage: Matrix<f64>,
+    // Distances in compact storage, which is used if not precomputed
+    compact: Option<DistanceStorage>,
     precomputed: bool,
     rounded: bool,
+    // The distances are given explicitly instead of calculated from the locations
+    explicit: bool,
+    // d(i, j) = d(j, i) for all locations, which only asymmetric test distances are not
+    symmetric: bool,
     max_distance: Option<f64>,
 }
 
 impl DistanceMatrix {
     pub fn new(
         locations: Vec<Coordinate>,
-        storage: Matrix<f64>,
+        storage: DistanceStorage,
         precomputed: bool,
         rounded: bool,
+        explicit: bool,
         max_distance: Option<f64>,
     ) -> Self {
+        let (storage, compact) = match (precomputed, storage.into_full_matrix()) {
+            (true, Ok(matrix)) => (matrix, None),
+            (true, Err(storage)) => (Matrix::new(0, 0), Some(storage)),
+            (false, _) => (Matrix::new(0, 0), None),
+        };
         Self {
             locations,
             storage,
-            precomputed,
+            precomputed: compact.is_none() && precomputed,
+            compact,
             rounded,
+            explicit,
+            symmetric: true,
             max_distance,
         }
     }
 
-    pub fn from_input(storage: Matrix<f64>, rounded: bool, max_distance: Option<f64>) -> Self {
+    /// Convert a full matrix of `dimension` x `dimension` distances in row-major order into the
+    /// lower row format of the parser. The explicit distances must be symmetric, as only the lower
+    /// triangle is kept.
+    pub fn lower_row(distances: &[f64], dimension: usize) -> Result<LowerRowMatrix, String> {
+        if distances.len() != dimension * dimension {
+            return Err(format!(
+                "The distance matrix must be {}x{}",
+                dimension, dimension
+            ));
+        }
+        for i in 0..dimension {
+            for j in 0..i {
+                let (lower, upper) = (distances[i * dimension + j], distances[j * dimension + i]);
+                if !lower.approx_eq(upper) {
+                    return Err(format!(
+                        "The distance matrix must be symmetric, but the distance from {} to {} is {} and from {} to {} is {}",
+                        i, j, lower, j, i, upper
+                    ));
+                }
+            }
+        }
+        Ok((1..dimension)
+            .map(|i| distances[i * dimension..i * dimension + i].to_vec())
+            .collect())
+    }
+
+    /// Distances from a full matrix, which can be asymmetric, where all the locations are at the
+    /// origin. The parsers reject asymmetric distances, so this is how the handling of asymmetric
+    /// distances is tested.
+    #[cfg(test)]
+    pub(crate) fn from_input(
+        storage: Matrix<f64>,
+        rounded: bool,
+        max_distance: Option<f64>,
+    ) -> Self {
+        assert_eq!(
+            storage.rows(),
+            storage.cols(),
+            "The distances must be square"
+        );
+        let symmetric = (0..storage.rows())
+            .all(|i| (0..i).all(|j| storage.get(i, j).approx_eq(&storage.get(j, i))));
         Self {
-            locations: Vec::new(),
+            locations: vec![Coordinate { lng: 0.0, lat: 0.0 }; storage.rows()],
             storage,
+            compact: None,
             precomputed: true,
             rounded,
+            explicit: true,
+            symmetric,
             max_distance,
         }
     }
 
-    #[inline]
+    #[inline(always)]
     pub fn get(&self, row: usize, col: usize) -> f64 {
         match self.precomputed {
             true => self.storage.get(row, col),
-            false => {
-                let mut distance = euclidian(&self.locations[row], &self.locations[col]);
-                if self.rounded {
-                    distance = distance.round();
-                }
-                distance
-            }
+            false => match self.compact.as_ref() {
+                Some(compact) => compact.get(row, col),
+                None => self.get_lazy(row, col),
+            },
         }
     }
 
-    pub fn get_vec(&self, row: usize, col: usize, number: usize) -> Vec<f64> {
-        match self.precomputed {
-            true => self
-                .storage
-                .slice(row, col, number)
-                .iter()
-                .copied()
-                .collect(),
-            false => {
-                let size = self.size();
-                let mut row_index = row;
-                let mut col_index = col;
-                (0..number)
-                    .map(|_| {
-                        let value = self.get(row_index, col_index);
-                        if col_index < size - 1 {
-                            col_index += 1;
-                        } else {
-                            row_index += 1;
-                            col_index = 0;
-                        }
-                        value
-                    })
-                    .collect()
-            }
+    // Kept out of line, such that the precomputed cases stay small enough to be inlined
+    #[inline(never)]
+    fn get_lazy(&self, row: usize, col: usize) -> f64 {
+        let mut distance = euclidian(&self.locations[row], &self.locations[col]);
+        if self.rounded {
+            distance = distance.round();
         }
+        distance
+    }
+
+    pub fn get_vec(&self, row: usize, col: usize, number: usize) -> Vec<f64> {
+        let size = self.size();
+        let mut row_index = row;
+        let mut col_index = col;
+        (0..number)
+            .map(|_| {
+                let value = self.get(row_index, col_index);
+                if col_index < size - 1 {
+                    col_index += 1;
+                } else {
+                    row_index += 1;
+                    col_index = 0;
+                }
+                value
+            })
+            .collect()
     }
 
     pub fn size(&self) -> usize {
         self.locations.len()
     }
 
+    pub fn precision(&self) -> DistancePrecision {
+        self.compact
+            .as_ref()
+            .map_or(DistancePrecision::F64, |compact| compact.precision())
+    }
+
+    pub fn is_triangular(&self) -> bool {
+        self.compact
+            .as_ref()
+            .is_some_and(|compact| compact.is_triangular())
+    }
+
+    pub fn locations(&self) -> &[Coordinate] {
+        &self.locations
+    }
+
+    /// True if the distances are given explicitly and not Euclidean distances between the
+    /// locations
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
+    pub fn is_rounded(&self) -> bool {
+        self.rounded
+    }
+
+    /// True if the distance from `i` to `j` equals the distance from `j` to `i` for all locations
+    pub fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+
     pub fn max(&self) -> Option<f64> {
         self.max_distance
     }
@@ -358,30 +471,33 @@ impl DistanceMatrix {
             .map(|&index| self.locations[index].clone())
             .collect();
         let size = mapping.len();
-        let storage = Matrix::new(size, size);
+        let mut storage = DistanceStorage::new(size, self.precision(), self.is_triangular());
         for i in 0..size {
             for j in 0..size {
-                *storage.get_mut(i, j) = self.get(mapping[i], mapping[j]);
+                storage.set(i, j, self.get(mapping[i], mapping[j]));
             }
         }
-        let max_distance = if self.precomputed {
-            Some(storage.get_max())
+        let max_distance = if self.precomputed || self.compact.is_some() {
+            storage.max()
         } else {
             None
         };
-        Self {
+        let mut matrix = Self::new(
             locations,
             storage,
-            precomputed: true,
-            rounded: self.rounded,
+            true,
+            self.rounded,
+            self.explicit,
             max_distance,
-        }
+        );
+        matrix.symmetric = self.symmetric;
+        matrix
     }
 }
 
 const CORRELATION_LIMIT: usize = 200;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CorrelationMatrix {
     storage: Matrix<usize>,
     width: usize,
@@ -389,6 +505,14 @@ pub struct CorrelationMatrix {
 
 impl CorrelationMatrix {
     pub fn new(distance_matrix: &DistanceMatrix) -> Self {
+        match distance_matrix.is_explicit() {
+            true => Self::from_distances(distance_matrix),
+            false => Self::from_locations(distance_matrix.locations()),
+        }
+    }
+
+    /// Build the correlation matrix by sorting the rows of the distance matrix
+    fn from_distances(distance_matrix: &DistanceMatrix) -> Self {
         let size = distance_matrix.size();
         let width = CORRELATION_LIMIT.min(size - 2);
         let mut matrix: Matrix<usize> = Matrix::new(size, width);
@@ -412,15 +536,45 @@ impl CorrelationMatrix {
         }
     }
 
+    /// Build the correlation matrix with nearest neighbour queries in a k-d tree over the
+    /// customer locations
+    fn from_locations(locations: &[Coordinate]) -> Self {
+        let size = locations.len();
+        let width = CORRELATION_LIMIT.min(size - 2);
+        let mut matrix: Matrix<usize> = Matrix::new(size, width);
+        let tree = KdTree::new(locations, 1..size);
+        let mut neighbors = Vec::with_capacity(width + 1);
+        for (i, location) in locations.iter().enumerate() {
+            tree.nearest(location, width + 1, &mut neighbors);
+            neighbors
+                .iter()
+                .filter(|&&j| j != i)
+                .take(width)
+                .enumerate()
+                .for_each(|(number, &index)| {
+                    matrix.set(i, number, index);
+                });
+        }
+        Self {
+            storage: matrix,
+            width,
+        }
+    }
+
     pub fn get(&self, index: usize) -> &[usize] {
         self.slice(index, 0, self.width)
     }
 
+    /// Number of correlated nodes stored for each node
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
     pub fn top_slice(&self, index: usize, number: usize) -> &[usize] {
         self.slice(index, 0, number)
     }
 
-    pub fn top_slice_mut(&self, index: usize, number: usize) -> &mut [usize] {
+    pub fn top_slice_mut(&mut self, index: usize, number: usize) -> &mut [usize] {
         self.storage.slice_mut(index, 0, number)
     }
 
@@ -438,13 +592,30 @@ pub struct MatrixProvider {
 impl MatrixProvider {
     pub fn new(problem: &Problem, config: &Config, input_matrix: Option<Vec<Vec<f64>>>) -> Self {
         let rounded: bool = config.round_distances;
+        let precision = match config.distance_precision {
+            DistancePrecision::I32 if !rounded => {
+                log::warn!("Integer distances require rounded distances, using f64");
+                DistancePrecision::F64
+            }
+            precision => precision,
+        };
         let locations = problem.nodes.iter().map(|node| node.coord).collect();
+        // The size limit is the memory of a full f64 matrix, such that compact storage
+        // precomputes larger problems
+        let memory_limit = DistanceStorage::memory(
+            config.precompute_distance_size_limit as usize + 1,
+            DistancePrecision::F64,
+            false,
+        );
         let precompute: bool =
-            problem.nodes.len() - 1 < config.precompute_distance_size_limit as usize;
+            DistanceStorage::memory(problem.nodes.len(), precision, config.triangular_distances)
+                < memory_limit;
         let mut distance_builder = DistanceMatrixBuilder::new()
             .locations(locations)
             .precompute(precompute)
-            .rounded(rounded);
+            .rounded(rounded)
+            .precision(precision)
+            .triangular(config.triangular_distances);
 
         if let Some(input) = input_matrix {
             distance_builder = distance_builder.input(input);
@@ -469,3 +640,154 @@ impl MatrixProvider {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ProblemParser;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn matrix_is_send_and_sync() {
+        assert_send_sync::<Matrix<f64>>();
+        assert_send_sync::<DistanceMatrix>();
+        assert_send_sync::<CorrelationMatrix>();
+    }
+
+    #[test]
+    fn matrix_get_and_set() {
+        let mut matrix = Matrix::new(2, 3);
+        matrix.set(1, 2, 5);
+        *matrix.get_mut(0, 1) += 2;
+        assert_eq!(5, matrix.get(1, 2));
+        assert_eq!(&[0, 2, 0, 0], matrix.slice(0, 0, 4));
+        assert_eq!(5, matrix.get_max());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn matrix_index_out_of_bounds() {
+        let matrix: Matrix<f64> = Matrix::new(2, 3);
+        // Within the buffer, but in the wrong row
+        matrix.get(0, 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn matrix_index_outside_buffer() {
+        let matrix: Matrix<f64> = Matrix::new(2, 2);
+        matrix.get(200_000_000, 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn matrix_too_large() {
+        Matrix::<u8>::new(usize::MAX, 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn matrix_reshape_to_other_size() {
+        Matrix::<f64>::new(2, 3).reshape(3, 3);
+    }
+
+    fn parse_instance(name: &str) -> Problem {
+        let mut config = Config::default();
+        config.instance_path = format!("{}/instances/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let mut parser = ProblemParser::new();
+        parser.parse(&mut config);
+        parser.problem.unwrap()
+    }
+
+    fn distance_config(precision: DistancePrecision, triangular: bool, size_limit: u64) -> Config {
+        let mut config = Config::default();
+        config.round_distances = true;
+        config.distance_precision = precision;
+        config.triangular_distances = triangular;
+        config.precompute_distance_size_limit = size_limit;
+        config
+    }
+
+    #[test]
+    fn lower_row_of_full_matrix() {
+        #[rustfmt::skip]
+        let distances = [
+            0.0, 1.0, 2.0,
+            1.0, 0.0, 3.0,
+            2.0, 3.0, 0.0,
+        ];
+        assert_eq!(
+            Ok(vec![vec![1.0], vec![2.0, 3.0]]),
+            DistanceMatrix::lower_row(&distances, 3)
+        );
+        assert_eq!(
+            Err("The distance matrix must be 2x2".to_owned()),
+            DistanceMatrix::lower_row(&distances, 2)
+        );
+
+        let mut asymmetric = distances;
+        asymmetric[5] = 4.0;
+        let err = DistanceMatrix::lower_row(&asymmetric, 3).unwrap_err();
+        assert!(err.starts_with("The distance matrix must be symmetric"));
+    }
+
+    #[test]
+    fn compact_storage_agrees_with_f64() {
+        let problem = parse_instance("X-n101-k25.vrp");
+        let full = MatrixProvider::new(
+            &problem,
+            &distance_config(DistancePrecision::F64, false, 3000),
+            None,
+        )
+        .distance;
+        assert!(full.precomputed);
+
+        let n = problem.nodes.len();
+        for &(precision, triangular) in [
+            (DistancePrecision::F64, true),
+            (DistancePrecision::F32, false),
+            (DistancePrecision::F32, true),
+            (DistancePrecision::I32, false),
+            (DistancePrecision::I32, true),
+        ]
+        .iter()
+        {
+            let config = distance_config(precision, triangular, 3000);
+            let compact = MatrixProvider::new(&problem, &config, None).distance;
+            assert!(compact.compact.is_some());
+            assert_eq!(precision, compact.precision());
+            assert_eq!(triangular, compact.is_triangular());
+            assert_eq!(full.max(), compact.max());
+            for i in 0..n {
+                for j in 0..n {
+                    assert_eq!(full.get(i, j), compact.get(i, j));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn size_limit_is_based_on_memory() {
+        // 100 customers, where the full f64 matrix exceeds the memory of a limit of 90 customers,
+        // and requires a limit above 100
+        let problem = parse_instance("X-n101-k25.vrp");
+        let is_precomputed = |precision, triangular, size_limit| {
+            let config = distance_config(precision, triangular, size_limit);
+            let distance = MatrixProvider::new(&problem, &config, None).distance;
+            distance.precomputed || distance.compact.is_some()
+        };
+
+        assert!(is_precomputed(DistancePrecision::F64, false, 101));
+        assert!(!is_precomputed(DistancePrecision::F64, false, 90));
+        assert!(is_precomputed(DistancePrecision::F32, false, 90));
+        assert!(is_precomputed(DistancePrecision::I32, false, 90));
+        assert!(is_precomputed(DistancePrecision::F64, true, 90));
+
+        // Only halving the memory twice fits within the limit of 60 customers
+        assert!(!is_precomputed(DistancePrecision::F64, true, 60));
+        assert!(!is_precomputed(DistancePrecision::F32, false, 60));
+        assert!(is_precomputed(DistancePrecision::F32, true, 60));
+    }
+}
@@ -1,6 +1,12 @@
 mod circle_sector;
 pub use self::circle_sector::*;
 
+mod distance_storage;
+pub use self::distance_storage::*;
+
+mod kd_tree;
+pub use self::kd_tree::*;
+
 mod matrix;
 pub use self::matrix::*;
 
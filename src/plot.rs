@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use crate::models::{Coordinate, Problem};
+use crate::solver::Context;
+
+// Width of the plot in pixels, where the height follows from the coordinates
+const WIDTH: f64 = 800.0;
+const MARGIN: f64 = 20.0;
+
+/// Maps the coordinates of the problem into the plot, with the y axis pointing up
+struct Scale {
+    min_x: f64,
+    max_y: f64,
+    factor: f64,
+    height: f64,
+}
+
+impl Scale {
+    fn new(problem: &Problem) -> Self {
+        let coords = problem.nodes.iter().map(|node| node.coord);
+        let (mut min_x, mut max_x) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
+        for coord in coords {
+            min_x = min_x.min(coord.lng);
+            max_x = max_x.max(coord.lng);
+            min_y = min_y.min(coord.lat);
+            max_y = max_y.max(coord.lat);
+        }
+        // Avoid dividing by zero when all the nodes are on a line
+        let width = (max_x - min_x).max(1e-9);
+        let height = (max_y - min_y).max(1e-9);
+        let factor = (WIDTH - 2.0 * MARGIN) / width.max(height);
+        Self {
+            min_x,
+            max_y,
+            factor,
+            height: height * factor + 2.0 * MARGIN,
+        }
+    }
+
+    fn point(&self, coord: &Coordinate) -> (f64, f64) {
+        (
+            MARGIN + (coord.lng - self.min_x) * self.factor,
+            MARGIN + (self.max_y - coord.lat) * self.factor,
+        )
+    }
+}
+
+/// Distinct colour for each route, spreading the hues by the golden angle
+fn route_colour(route_number: usize) -> String {
+    let hue = (route_number as f64 * 137.508) % 360.0;
+    format!("hsl({:.0},70%,45%)", hue)
+}
+
+/// Render the routes of a solution to SVG, where each route is a list of customer indices
+/// without the depot
+pub fn solution_svg(problem: &Problem, routes: &[Vec<usize>], title: Option<&str>) -> String {
+    let scale = Scale::new(problem);
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h:.0}" viewBox="0 0 {w:.0} {h:.0}">"#,
+        w = WIDTH,
+        h = scale.height
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+    // Draw the routes from the depot through the customers and back
+    for (route_number, route) in routes.iter().filter(|route| !route.is_empty()).enumerate() {
+        let points: Vec<String> = std::iter::once(0)
+            .chain(route.iter().cloned())
+            .chain(std::iter::once(0))
+            .map(|node| {
+                let (x, y) = scale.point(&problem.nodes[node].coord);
+                format!("{:.2},{:.2}", x, y)
+            })
+            .collect();
+        let colour = route_colour(route_number);
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            points.join(" "),
+            colour
+        )
+        .unwrap();
+        for &customer in route.iter() {
+            let (x, y) = scale.point(&problem.nodes[customer].coord);
+            writeln!(
+                svg,
+                r#"<circle cx="{:.2}" cy="{:.2}" r="2.5" fill="{}"/>"#,
+                x, y, colour
+            )
+            .unwrap();
+        }
+    }
+
+    // Mark the depot with a square on top of the routes
+    let (x, y) = scale.point(&problem.nodes[0].coord);
+    writeln!(
+        svg,
+        r#"<rect x="{:.2}" y="{:.2}" width="10" height="10" fill="black"/>"#,
+        x - 5.0,
+        y - 5.0
+    )
+    .unwrap();
+
+    if let Some(title) = title {
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-family="sans-serif" font-size="14">{}</text>"#,
+            MARGIN,
+            MARGIN - 5.0,
+            title
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Write the routes of a solution to an SVG file
+pub fn write_solution_svg(
+    path: impl AsRef<Path>,
+    problem: &Problem,
+    routes: &[Vec<usize>],
+    title: Option<&str>,
+) {
+    let path = path.as_ref();
+    fs::write(path, solution_svg(problem, routes, title))
+        .unwrap_or_else(|err| panic!("Failed to write plot {}: {}", path.display(), err));
+}
+
+/// Plot the best solution of the search to the configured `plot_path`
+pub fn write_best_plot(ctx: &Context) {
+    if let Some(plot_path) = ctx.config.borrow().plot_path.as_ref() {
+        if let Some(entry) = ctx.search_history.borrow().last_entry() {
+            let title = format!("Cost {}", entry.solution.cost.round());
+            write_solution_svg(
+                plot_path,
+                &ctx.problem,
+                &entry.solution.routes,
+                Some(&title),
+            );
+        }
+    }
+}
+
+/// Plot a new best solution as a numbered frame in the configured `plot_frames` directory
+pub fn write_frame(ctx: &Context, frame: usize, routes: &[Vec<usize>], cost: f64, time: f64) {
+    if let Some(plot_frames) = ctx.config.borrow().plot_frames.as_ref() {
+        let directory = Path::new(plot_frames);
+        fs::create_dir_all(directory)
+            .unwrap_or_else(|err| panic!("Failed to create {}: {}", directory.display(), err));
+        let title = format!("{:.1}s, Cost {}", time, cost.round());
+        write_solution_svg(
+            directory.join(format!("{:05}.svg", frame)),
+            &ctx.problem,
+            routes,
+            Some(&title),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Node, ProblemBuilder, Vehicle};
+
+    #[test]
+    fn plot_routes() {
+        let nodes = [(0.0, 0.0), (10.0, 0.0), (0.0, 10.0), (-10.0, -10.0)]
+            .iter()
+            .enumerate()
+            .map(|(id, &(lng, lat))| Node {
+                id,
+                coord: Coordinate { lng, lat },
+                demand: 1.0,
+            })
+            .collect();
+        let problem = ProblemBuilder::new(nodes, Vehicle { id: 0, cap: 2.0 }).build();
+        let svg = solution_svg(&problem, &[vec![1, 2], vec![], vec![3]], Some("Cost 42"));
+
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(2, svg.matches("<polyline").count());
+        assert_eq!(3, svg.matches("<circle").count());
+        assert!(svg.contains("Cost 42"));
+
+        // The depot is in the middle of the plot, with the y axis pointing up
+        let scale = Scale::new(&problem);
+        assert_eq!((400.0, 400.0), scale.point(&problem.nodes[0].coord));
+        assert_eq!((400.0, MARGIN), scale.point(&problem.nodes[2].coord));
+    }
+}
@@ -0,0 +1,372 @@
+//! Python bindings of the solver, built as the `hybridcvrp` extension module.
+//!
+//! The search runs without the GIL, which is only taken to report new best solutions to the
+//! progress callback and to check for signals such as `KeyboardInterrupt` every 100 ms.
+
+use std::time::Duration;
+
+use instant::Instant;
+use numpy::{AllowTypeChange, PyArrayLike1, PyArrayLike2};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList, PyString, PyTuple};
+use serde_yaml::{Mapping, Value};
+
+use crate::config::Config;
+use crate::models::{Coordinate, DistanceMatrix, Node, ProblemBuilder, Vehicle};
+use crate::solver::{report_progress, run_solver_until, Context, Solution};
+
+// Interval between the checks for signals during the search
+const SIGNAL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Problem with the nodes, where the first node is the depot
+#[pyclass(name = "Problem", module = "hybridcvrp", frozen)]
+pub struct PyProblem {
+    nodes: Vec<Node>,
+    vehicle: Vehicle,
+    // Explicit distances in the lower row format of the parser
+    matrix: Option<Vec<Vec<f64>>>,
+}
+
+#[pymethods]
+impl PyProblem {
+    /// Create a problem from an (n, 2) array of coordinates, n demands and the vehicle capacity,
+    /// with an optional (n, n) matrix replacing the euclidean distances
+    #[new]
+    #[pyo3(signature = (coordinates, demands, capacity, distances = None))]
+    fn new(
+        coordinates: PyArrayLike2<'_, f64, AllowTypeChange>,
+        demands: PyArrayLike1<'_, f64, AllowTypeChange>,
+        capacity: f64,
+        distances: Option<PyArrayLike2<'_, f64, AllowTypeChange>>,
+    ) -> PyResult<Self> {
+        let coordinates = coordinates.as_array();
+        let demands = demands.as_array();
+        let dimension = coordinates.nrows();
+        if coordinates.ncols() != 2 {
+            return Err(PyValueError::new_err(
+                "The coordinates must have shape (n, 2)",
+            ));
+        }
+        if demands.len() != dimension {
+            return Err(PyValueError::new_err(format!(
+                "Expected {} demands, got {}",
+                dimension,
+                demands.len()
+            )));
+        }
+        if dimension < 2 {
+            return Err(PyValueError::new_err("The problem has no customers"));
+        }
+
+        let matrix = match distances {
+            Some(distances) => {
+                let distances = distances.as_array();
+                if distances.dim() != (dimension, dimension) {
+                    return Err(PyValueError::new_err(format!(
+                        "The distance matrix must be {}x{}",
+                        dimension, dimension
+                    )));
+                }
+                let distances: Vec<f64> = distances.iter().copied().collect();
+                Some(
+                    DistanceMatrix::lower_row(&distances, dimension)
+                        .map_err(PyValueError::new_err)?,
+                )
+            }
+            None => None,
+        };
+
+        let nodes = (0..dimension)
+            .map(|index| Node {
+                id: index + 1,
+                coord: Coordinate {
+                    lng: coordinates[[index, 0]],
+                    lat: coordinates[[index, 1]],
+                },
+                demand: demands[index],
+            })
+            .collect();
+        Ok(Self {
+            nodes,
+            vehicle: Vehicle {
+                id: 0,
+                cap: capacity,
+            },
+            matrix,
+        })
+    }
+
+    #[getter]
+    fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[getter]
+    fn capacity(&self) -> f64 {
+        self.vehicle.cap
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Problem(num_nodes={}, capacity={})",
+            self.nodes.len(),
+            self.vehicle.cap
+        )
+    }
+}
+
+/// Best solution found by the solver, with the statistics of the search
+#[pyclass(name = "Solution", module = "hybridcvrp", frozen, get_all)]
+pub struct PySolution {
+    /// The non-empty routes as lists of node indices, which exclude the depot
+    routes: Vec<Vec<usize>>,
+    /// Demand served by each route
+    loads: Vec<f64>,
+    /// Distance of each route
+    distances: Vec<f64>,
+    /// Total distance of the routes, or infinity when no solution was found
+    cost: f64,
+    feasible: bool,
+    iterations: u64,
+    /// Seconds from the start to the end of the search
+    runtime: f64,
+    /// Seconds from the start until the best solution was found
+    time_to_best: Option<f64>,
+    /// Number of times a new best solution was found
+    improvements: usize,
+    lower_bound: Option<f64>,
+    /// Gap between the cost and the lower bound in percent
+    gap: Option<f64>,
+}
+
+#[pymethods]
+impl PySolution {
+    fn __repr__(&self) -> String {
+        format!(
+            "Solution(cost={}, routes={}, feasible={})",
+            self.cost,
+            self.routes.len(),
+            if self.feasible { "True" } else { "False" }
+        )
+    }
+}
+
+impl From<Solution> for PySolution {
+    fn from(solution: Solution) -> Self {
+        let statistics = solution.statistics;
+        Self {
+            loads: solution.routes.iter().map(|route| route.load).collect(),
+            distances: solution.routes.iter().map(|route| route.distance).collect(),
+            routes: solution
+                .routes
+                .into_iter()
+                .map(|route| route.customers)
+                .collect(),
+            cost: solution.cost,
+            feasible: solution.feasible,
+            iterations: statistics.iterations,
+            runtime: statistics.runtime.as_secs_f64(),
+            time_to_best: statistics.time_to_best.map(|time| time.as_secs_f64()),
+            improvements: statistics.improvements,
+            lower_bound: statistics.lower_bound,
+            gap: statistics.gap,
+        }
+    }
+}
+
+/// Solve a problem, where the keyword arguments set the fields of `config.yml` such as
+/// `time_limit=10` or `seed=1, deterministic=True`.
+///
+/// `progress` is called with the cost, the seconds since the start and the iteration on every new
+/// best solution, and stops the search by returning False.
+#[pyfunction]
+#[pyo3(signature = (problem, progress = None, **config))]
+fn solve(
+    py: Python<'_>,
+    problem: &PyProblem,
+    progress: Option<Py<PyAny>>,
+    config: Option<&Bound<'_, PyDict>>,
+) -> PyResult<PySolution> {
+    let config = config_from_kwargs(config)?;
+    let built = ProblemBuilder::new(problem.nodes.clone(), problem.vehicle.clone()).build();
+    let matrix = problem.matrix.clone();
+
+    let (solution, error) = py.detach(move || {
+        let ctx = Context::from_problem(built, matrix, config, Instant::now());
+        let mut reported = 0;
+        let mut error = None;
+        let mut signals_checked = Instant::now();
+        let ctx = run_solver_until(ctx, |ctx| {
+            let new_best =
+                progress.is_some() && ctx.search_history.borrow().entries().len() > reported;
+            let check_signals = signals_checked.elapsed() >= SIGNAL_INTERVAL;
+            if !new_best && !check_signals {
+                return false;
+            }
+
+            Python::attach(|py| {
+                if check_signals {
+                    signals_checked = Instant::now();
+                    if let Err(err) = py.check_signals() {
+                        error = Some(err);
+                        return true;
+                    }
+                }
+                let progress = match progress.as_ref() {
+                    Some(progress) => progress,
+                    None => return false,
+                };
+                !report_progress(ctx, &mut reported, &mut |p| {
+                    match progress.call1(py, (p.cost, p.time.as_secs_f64(), p.iteration)) {
+                        // Only an explicit False stops the search, and not a missing return
+                        Ok(keep_going) => !matches!(keep_going.extract::<bool>(py), Ok(false)),
+                        Err(err) => {
+                            error = Some(err);
+                            false
+                        }
+                    }
+                })
+            })
+        });
+        (Solution::from_context(&ctx), error)
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(solution.into()),
+    }
+}
+
+/// Default config updated with the keyword arguments, where unknown fields are rejected
+fn config_from_kwargs(kwargs: Option<&Bound<'_, PyDict>>) -> PyResult<Config> {
+    let mut config: Value =
+        serde_yaml::to_value(Config::default()).expect("Failed to serialize config");
+    let mut values = Mapping::new();
+    if let Some(kwargs) = kwargs {
+        for (key, value) in kwargs.iter() {
+            let key: String = key.extract()?;
+            if config.get(&key).is_none() {
+                return Err(PyTypeError::new_err(format!(
+                    "Unknown config field: {}",
+                    key
+                )));
+            }
+            values.insert(Value::String(key), to_yaml(&value)?);
+        }
+    }
+    config
+        .as_mapping_mut()
+        .expect("Config is not a mapping")
+        .extend(values);
+    serde_yaml::from_value(config).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+// Convert a Python value of a config field to YAML
+fn to_yaml(value: &Bound<'_, PyAny>) -> PyResult<Value> {
+    if value.is_none() {
+        Ok(Value::Null)
+    } else if let Ok(value) = value.cast::<PyBool>() {
+        Ok(Value::Bool(value.is_true()))
+    } else if let Ok(value) = value.extract::<i64>() {
+        Ok(Value::from(value))
+    } else if let Ok(value) = value.extract::<f64>() {
+        Ok(Value::from(value))
+    } else if let Ok(value) = value.cast::<PyString>() {
+        Ok(Value::String(value.to_str()?.to_owned()))
+    } else if let Ok(value) = value.cast::<PyList>() {
+        let values: PyResult<Vec<Value>> = value.iter().map(|value| to_yaml(&value)).collect();
+        Ok(Value::Sequence(values?))
+    } else if let Ok(value) = value.cast::<PyTuple>() {
+        let values: PyResult<Vec<Value>> = value.iter().map(|value| to_yaml(&value)).collect();
+        Ok(Value::Sequence(values?))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "Unsupported config value: {}",
+            value.repr()?
+        )))
+    }
+}
+
+#[pymodule]
+fn hybridcvrp(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyProblem>()?;
+    m.add_class::<PySolution>()?;
+    m.add_function(wrap_pyfunction!(solve, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_from_keyword_arguments() {
+        Python::initialize();
+        Python::attach(|py| {
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("time_limit", 5).unwrap();
+            kwargs.set_item("deterministic", true).unwrap();
+            kwargs.set_item("rr_gamma", 0.5).unwrap();
+            kwargs.set_item("max_iterations", py.None()).unwrap();
+            let config = config_from_kwargs(Some(&kwargs)).unwrap();
+            assert_eq!(5, config.time_limit);
+            assert!(config.deterministic);
+            assert_eq!(0.5, config.rr_gamma);
+            assert_eq!(None, config.max_iterations);
+
+            kwargs.set_item("time_limit", "soon").unwrap();
+            assert!(config_from_kwargs(Some(&kwargs)).is_err());
+
+            let unknown = PyDict::new(py);
+            unknown.set_item("no_such_field", 1).unwrap();
+            assert!(config_from_kwargs(Some(&unknown))
+                .unwrap_err()
+                .is_instance_of::<PyTypeError>(py));
+        });
+    }
+
+    #[test]
+    fn stop_from_progress_callback() {
+        Python::initialize();
+        Python::attach(|py| {
+            let nodes = [
+                (0.0, 0.0, 0.0),
+                (3.0, 0.0, 1.0),
+                (3.0, 4.0, 1.0),
+                (0.0, 4.0, 1.0),
+            ]
+            .iter()
+            .enumerate()
+            .map(|(index, &(x, y, demand))| Node {
+                id: index + 1,
+                coord: Coordinate { lng: x, lat: y },
+                demand,
+            })
+            .collect();
+            let problem = PyProblem {
+                nodes,
+                vehicle: Vehicle { id: 0, cap: 2.0 },
+                matrix: None,
+            };
+            let reported = PyList::empty(py);
+            let globals = PyDict::new(py);
+            globals.set_item("reported", &reported).unwrap();
+            let progress = py
+                .eval(
+                    pyo3::ffi::c_str!(
+                        "lambda cost, time, iteration: reported.append(cost) or False"
+                    ),
+                    Some(&globals),
+                    None,
+                )
+                .unwrap();
+            let solution = solve(py, &problem, Some(progress.unbind()), None).unwrap();
+            assert_eq!(vec![solution.cost], reported.extract::<Vec<f64>>().unwrap());
+            assert_eq!(1, solution.improvements);
+            let mut customers: Vec<usize> = solution.routes.concat();
+            customers.sort_unstable();
+            assert_eq!(vec![1, 2, 3], customers);
+        });
+    }
+}
@@ -1,8 +1,10 @@
 use std::cell::RefCell;
-use std::time::{Duration, Instant};
+
+use instant::{Duration, Instant};
 
 use crate::config::Config;
 use crate::models::{MatrixProvider, Problem};
+use crate::solver::improvement::RouteOptimizer;
 use crate::solver::SearchHistory;
 use crate::utils::{ProblemParser, Random};
 
@@ -14,11 +16,24 @@ pub struct Context {
     pub random: Random,
     pub search_history: RefCell<SearchHistory>,
     pub iteration: RefCell<u64>,
+    // Shared by the local searches and the optimization of new best solutions
+    pub route_optimizer: RefCell<RouteOptimizer>,
 }
 
 impl Context {
     pub fn new(mut parser: ProblemParser, config: Config, start_time: Instant) -> Self {
         let problem = parser.problem.take().expect("Failed to parse problem");
+        Self::from_problem(problem, parser.matrix.take(), config, start_time)
+    }
+
+    /// Create the context for an in-memory problem, with an optional explicit distance matrix in
+    /// the lower row format of the parser
+    pub fn from_problem(
+        problem: Problem,
+        matrix: Option<Vec<Vec<f64>>>,
+        config: Config,
+        start_time: Instant,
+    ) -> Self {
         let random = if config.deterministic {
             log::info!("Deterministic with seed: {}", config.seed);
             Random::from_seed(config.seed)
@@ -26,8 +41,10 @@ impl Context {
             Random::new()
         };
 
-        let matrix_provider = MatrixProvider::new(&problem, &config, parser.matrix.take());
+        let matrix_provider = MatrixProvider::new(&problem, &config, matrix);
         log::info!("Matrices built!");
+        let route_optimizer =
+            RouteOptimizer::with_exact_size_limit(config.tsp_exact_size_limit as usize);
 
         let context = Self {
             problem,
@@ -36,6 +53,7 @@ impl Context {
             random,
             search_history: RefCell::new(SearchHistory::new(start_time)),
             iteration: RefCell::new(0),
+            route_optimizer: RefCell::new(route_optimizer),
         };
 
         context.setup();
@@ -44,9 +62,17 @@ impl Context {
 
     pub fn setup(&self) {
         self.config.borrow_mut().num_vehicles = self.initial_num_vehicles();
+        self.limit_granularity();
         self.reset_penalty();
     }
 
+    // Small problems have fewer correlated nodes than the granularity
+    fn limit_granularity(&self) {
+        let width = self.matrix_provider.correlation.width() as u64;
+        let mut config = self.config.borrow_mut();
+        config.local_search_granularity = config.local_search_granularity.min(width);
+    }
+
     pub fn elapsed_as_secs(&self) -> u64 {
         self.elapsed().as_secs()
     }
@@ -61,7 +87,7 @@ impl Context {
 
     pub fn terminate(&self) -> bool {
         let config = self.config.borrow();
-        self.elapsed_as_secs() >= config.time_limit
+        self.elapsed_as_secs_f64() >= config.time_limit_secs()
             || config.max_iterations.map_or(false, |max_iterations| {
                 *self.iteration.borrow() >= max_iterations
             })
@@ -110,6 +136,9 @@ impl Context {
             random: self.random.clone(),
             search_history: RefCell::new(search_history),
             iteration: RefCell::new(0),
+            route_optimizer: RefCell::new(RouteOptimizer::with_exact_size_limit(
+                self.config.borrow().tsp_exact_size_limit as usize,
+            )),
         }
     }
 }
@@ -0,0 +1,71 @@
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::bench::{bks_from_instance, primal_integral};
+use crate::solver::{Context, HistoricSolution};
+
+/// Format a new best solution as in the DIMACS CVRP competition, with the routes, the cost and the
+/// time in seconds since the start.
+///
+/// The cost is an integer with rounded distances, and otherwise printed with full precision.
+pub fn format_solution(solution: &HistoricSolution, timestamp: Duration, rounded: bool) -> String {
+    let mut output = String::new();
+    for (route_number, route) in (1..).zip(solution.routes.iter().filter(|route| !route.is_empty()))
+    {
+        let stops: Vec<String> = route.iter().map(|stop| stop.to_string()).collect();
+        writeln!(output, "Route #{}: {}", route_number, stops.join(" ")).unwrap();
+    }
+    match rounded {
+        true => writeln!(output, "Cost {}", solution.cost.round() as u64).unwrap(),
+        false => writeln!(output, "Cost {}", solution.cost).unwrap(),
+    }
+    write!(output, "Time {:.3}", timestamp.as_secs_f64()).unwrap();
+    output
+}
+
+/// Print the primal integral of the search over the scaled time limit, which needs the best known
+/// solution from the config or the instance comment
+pub fn report_primal_integral(ctx: &Context) {
+    let config = ctx.config.borrow();
+    let bks = Some(config.bks)
+        .filter(|bks| bks.is_finite())
+        .or_else(|| bks_from_instance(&config.instance_path));
+
+    match bks {
+        Some(bks) => {
+            let search_history = ctx.search_history.borrow();
+            let trajectory: Vec<(f64, f64)> = search_history
+                .entries()
+                .iter()
+                .map(|entry| (entry.timestamp.as_secs_f64(), entry.solution.cost))
+                .collect();
+            let time_limit = config.time_limit_secs();
+            println!(
+                "Primal integral {:.4}",
+                primal_integral(&trajectory, time_limit, bks)
+            );
+        }
+        None => log::warn!("The primal integral needs the bks in the config or instance comment"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dimacs_output() {
+        let solution = HistoricSolution {
+            routes: vec![vec![3, 1], vec![], vec![2]],
+            cost: 123.4567,
+        };
+        assert_eq!(
+            "Route #1: 3 1\nRoute #2: 2\nCost 123\nTime 1.500",
+            format_solution(&solution, Duration::from_millis(1500), true)
+        );
+        assert!(
+            format_solution(&solution, Duration::from_millis(1500), false)
+                .contains("Cost 123.4567\n")
+        );
+    }
+}
@@ -0,0 +1,967 @@
+use std::collections::HashSet;
+
+use crate::solver::evaluate::route_cost;
+use crate::solver::genetic::Individual;
+use crate::solver::Context;
+
+/// The crossover operators available to the genetic algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrossoverOperator {
+    // Order crossover on the giant tours
+    Ox,
+    // Selective route exchange crossover
+    Srex,
+    // Edge assembly crossover recombining a single AB-cycle
+    Eax,
+    // Greedy giant tour construction from the edges of both parents
+    EdgeAssembly,
+}
+
+impl CrossoverOperator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ox => "OX",
+            Self::Srex => "SREX",
+            Self::Eax => "EAX",
+            Self::EdgeAssembly => "EdgeAssembly",
+        }
+    }
+}
+
+// Scores given to the operator which produced the child
+const SCORE_NEW_BEST: f64 = 3.0;
+const SCORE_BETTER_THAN_PARENTS: f64 = 1.0;
+
+// Lower limit on the weights to prevent operators from never being selected again
+const MIN_WEIGHT: f64 = 0.05;
+
+/// Selects and performs crossover between two parents.
+///
+/// When adaptive selection is enabled, the probability of selecting an operator is
+/// proportional to a weight which is periodically updated from the quality of the
+/// children the operator has produced since the last update.
+pub struct Crossover {
+    pub operators: Vec<CrossoverOperator>,
+    pub weights: Vec<f64>,
+    scores: Vec<f64>,
+    uses: Vec<u64>,
+    adaptive: bool,
+    reaction_factor: f64,
+    update_interval: u64,
+    next_update: u64,
+}
+
+impl Crossover {
+    pub fn new(ctx: &Context) -> Self {
+        let config = ctx.config.borrow();
+        let mut operators = Vec::new();
+        if config.crossover_ox {
+            operators.push(CrossoverOperator::Ox);
+        }
+        if config.crossover_srex {
+            operators.push(CrossoverOperator::Srex);
+        }
+        if config.crossover_eax {
+            operators.push(CrossoverOperator::Eax);
+        }
+        if config.crossover_edge_assembly {
+            operators.push(CrossoverOperator::EdgeAssembly);
+        }
+        if operators.is_empty() {
+            log::warn!("No crossover operator enabled, using OX");
+            operators.push(CrossoverOperator::Ox);
+        }
+
+        let num_operators = operators.len();
+        Self {
+            operators,
+            weights: vec![1.0; num_operators],
+            scores: vec![0.0; num_operators],
+            uses: vec![0; num_operators],
+            adaptive: config.adaptive_crossover,
+            reaction_factor: config.crossover_reaction_factor,
+            update_interval: config.crossover_update_interval,
+            next_update: config.crossover_update_interval,
+        }
+    }
+
+    /// Select the operator to use with a roulette wheel on the weights
+    pub fn select(&self, ctx: &Context) -> CrossoverOperator {
+        if self.operators.len() == 1 {
+            return self.operators[0];
+        }
+        let total: f64 = self.weights.iter().sum();
+        let mut value = ctx.random.real() * total;
+        for (index, &weight) in self.weights.iter().enumerate() {
+            if value < weight {
+                return self.operators[index];
+            }
+            value -= weight;
+        }
+        *self.operators.last().expect("No crossover operators")
+    }
+
+    /// Register the outcome of a child produced by `operator`
+    pub fn reward(
+        &mut self,
+        operator: CrossoverOperator,
+        new_best: bool,
+        better_than_parents: bool,
+    ) {
+        if !self.adaptive {
+            return;
+        }
+        if let Some(index) = self.operators.iter().position(|&op| op == operator) {
+            self.uses[index] += 1;
+            if new_best {
+                self.scores[index] += SCORE_NEW_BEST;
+            } else if better_than_parents {
+                self.scores[index] += SCORE_BETTER_THAN_PARENTS;
+            }
+        }
+    }
+
+    /// Update the weights of the operators at the update interval
+    pub fn update(&mut self, iteration: u64) {
+        if !self.adaptive || iteration < self.next_update {
+            return;
+        }
+        self.next_update = iteration + self.update_interval;
+        for index in 0..self.operators.len() {
+            if self.uses[index] > 0 {
+                let performance = self.scores[index] / self.uses[index] as f64;
+                self.weights[index] = (1.0 - self.reaction_factor) * self.weights[index]
+                    + self.reaction_factor * performance;
+            }
+            self.weights[index] = self.weights[index].max(MIN_WEIGHT);
+            self.scores[index] = 0.0;
+            self.uses[index] = 0;
+        }
+    }
+
+    /// Perform the crossover and return the child.
+    ///
+    /// If the operator works on routes, the child has its phenotype set, while
+    /// children from giant tour based operators only have a genotype.
+    pub fn run(
+        &self,
+        ctx: &Context,
+        operator: CrossoverOperator,
+        parent_one: &Individual,
+        parent_two: &Individual,
+        number: u64,
+    ) -> Individual {
+        let routes = match operator {
+            CrossoverOperator::Ox => None,
+            CrossoverOperator::Srex => self.srex(ctx, parent_one, parent_two),
+            CrossoverOperator::Eax => self.eax(ctx, parent_one, parent_two),
+            CrossoverOperator::EdgeAssembly => {
+                let genotype = self.edge_assembly(ctx, parent_one, parent_two);
+                return Individual::new(genotype, number);
+            }
+        };
+
+        match routes {
+            Some(routes) => {
+                let genotype = routes.iter().flatten().copied().collect();
+                let mut child = Individual::new(genotype, number);
+                child.phenotype = routes;
+                child
+            }
+            // Fall back to OX when the route based operators are not applicable
+            None => self.random_ox(ctx, parent_one, parent_two, number),
+        }
+    }
+
+    fn random_ox(
+        &self,
+        ctx: &Context,
+        parent_one: &Individual,
+        parent_two: &Individual,
+        number: u64,
+    ) -> Individual {
+        // Randomly select start of the crossover zone
+        let start = ctx.random.range_usize(0, parent_one.genotype.len());
+
+        // Randomly select end of the crossover zone different from the start
+        let mut end;
+        loop {
+            end = ctx.random.range_usize(0, parent_one.genotype.len());
+            if start != end {
+                break;
+            }
+        }
+
+        // Use the OX crossover
+        Self::ox(ctx, parent_one, parent_two, start, end, number)
+    }
+
+    pub fn ox(
+        ctx: &Context,
+        parent_one: &Individual,
+        parent_two: &Individual,
+        start: usize,
+        end: usize,
+        number: u64,
+    ) -> Individual {
+        // Helper function to wrap indices in the vector around
+        #[inline]
+        fn wrap_index(index: usize, length: usize) -> usize {
+            if index == length {
+                0
+            } else {
+                index
+            }
+        }
+        // Clone the genotype of the first parent
+        let mut genotype = parent_one.genotype.clone();
+
+        // Set with all the genes added to the genotype
+        let mut added_genes =
+            HashSet::with_capacity_and_hasher(genotype.len(), ctx.random.random_state());
+
+        // Index into the genotype
+        let mut index = start;
+
+        loop {
+            // Add the gene from parent one into the set of genes
+            added_genes.insert(genotype[index]);
+
+            // Done with parent one
+            if index == end {
+                index = wrap_index(index + 1, genotype.len());
+                break;
+            }
+
+            // Move index one to the right, possibly wrapping around
+            index = wrap_index(index + 1, genotype.len());
+        }
+
+        // Add genes from parent_two until all genes have been added
+        for &parent_two_gene in parent_two.genotype.iter() {
+            // Check to see if the gene is already added
+            if !added_genes.contains(&parent_two_gene) {
+                // Add the parent two gene to the set of added genes
+                added_genes.insert(parent_two_gene);
+                // Update the childs genotype with the gene from parent two
+                genotype[index] = parent_two_gene;
+                // Update the index into the childs genotype
+                index = wrap_index(index + 1, genotype.len());
+            }
+        }
+        // Return the child
+        Individual::new(genotype, number)
+    }
+
+    /// Selective route exchange crossover (SREX).
+    ///
+    /// A random number of consecutive non-empty routes, starting at a random route and wrapping
+    /// around, is removed from the first parent. The routes are taken in the order of the
+    /// phenotype, which is by the angle of the route centers around the depot when the parent is
+    /// sorted with `Individual::sort_routes`, as after the local search. They are replaced by the
+    /// same number of consecutive routes from the second parent with the largest overlap in
+    /// customers. Both ways of removing the duplicated customers are tried, and the cheapest child
+    /// is kept.
+    fn srex(
+        &self,
+        ctx: &Context,
+        parent_one: &Individual,
+        parent_two: &Individual,
+    ) -> Option<Vec<Vec<usize>>> {
+        let routes_a = nonempty_routes(parent_one);
+        let routes_b = nonempty_routes(parent_two);
+        let (num_a, num_b) = (routes_a.len(), routes_b.len());
+        if num_a < 2 || num_b < 2 {
+            return None;
+        }
+
+        let dim = ctx.problem.dim();
+
+        // Number of routes to exchange
+        let max_exchanged = (num_a.min(num_b) / 2).max(1);
+        let num_exchanged = ctx.random.range_usize(1, max_exchanged + 1);
+
+        // Select the routes removed from parent one
+        let start_a = ctx.random.range_usize(0, num_a);
+        let mut in_removed = vec![false; dim];
+        let mut removed_routes = vec![false; num_a];
+        for offset in 0..num_exchanged {
+            let route_index = (start_a + offset) % num_a;
+            removed_routes[route_index] = true;
+            for &customer in routes_a[route_index].iter() {
+                in_removed[customer] = true;
+            }
+        }
+
+        // Select the routes from parent two with the largest overlap of customers
+        let mut best_start_b = 0;
+        let mut best_overlap = 0;
+        for start_b in 0..num_b {
+            let overlap: usize = (0..num_exchanged)
+                .map(|offset| {
+                    routes_b[(start_b + offset) % num_b]
+                        .iter()
+                        .filter(|&&customer| in_removed[customer])
+                        .count()
+                })
+                .sum();
+            if overlap > best_overlap {
+                best_overlap = overlap;
+                best_start_b = start_b;
+            }
+        }
+        let inserted: Vec<Vec<usize>> = (0..num_exchanged)
+            .map(|offset| routes_b[(best_start_b + offset) % num_b].clone())
+            .collect();
+        let mut in_inserted = vec![false; dim];
+        for &customer in inserted.iter().flatten() {
+            in_inserted[customer] = true;
+        }
+
+        let kept: Vec<Vec<usize>> = routes_a
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| !removed_routes[index])
+            .map(|(_, route)| route.clone())
+            .collect();
+        let mut in_kept = vec![false; dim];
+        for &customer in kept.iter().flatten() {
+            in_kept[customer] = true;
+        }
+
+        // Variant one: the inserted routes are kept intact
+        let mut child_one: Vec<Vec<usize>> = kept
+            .iter()
+            .map(|route| {
+                route
+                    .iter()
+                    .copied()
+                    .filter(|&customer| !in_inserted[customer])
+                    .collect()
+            })
+            .chain(inserted.iter().cloned())
+            .collect();
+
+        // Variant two: the kept routes of the first parent are kept intact
+        let mut child_two: Vec<Vec<usize>> = kept
+            .iter()
+            .cloned()
+            .chain(inserted.iter().map(|route| {
+                route
+                    .iter()
+                    .copied()
+                    .filter(|&customer| !in_kept[customer])
+                    .collect()
+            }))
+            .collect();
+
+        // Insert the customers that are no longer served
+        for child in [&mut child_one, &mut child_two] {
+            child.retain(|route| !route.is_empty());
+            let mut served = vec![false; dim];
+            for &customer in child.iter().flatten() {
+                served[customer] = true;
+            }
+            let mut missing: Vec<usize> = (1..dim).filter(|&c| !served[c]).collect();
+            ctx.random.shuffle(missing.as_mut_slice());
+            for customer in missing {
+                insert_cheapest(ctx, child, customer);
+            }
+        }
+
+        if routes_cost(ctx, &child_one) <= routes_cost(ctx, &child_two) {
+            Some(child_one)
+        } else {
+            Some(child_two)
+        }
+    }
+
+    /// Edge assembly crossover (EAX) with the single strategy.
+    ///
+    /// The edges of the two parents are combined into alternating cycles (AB-cycles), where
+    /// every other edge is from parent one and parent two. A random AB-cycle is applied to
+    /// the first parent by replacing its edges from parent one with the edges from parent two.
+    /// Subtours created by the exchange are then merged into the routes with the cheapest
+    /// 2-opt move.
+    fn eax(
+        &self,
+        ctx: &Context,
+        parent_one: &Individual,
+        parent_two: &Individual,
+    ) -> Option<Vec<Vec<usize>>> {
+        let dim = ctx.problem.dim();
+        let mut edges_a = route_edges(dim, &parent_one.phenotype);
+        let mut edges_b = route_edges(dim, &parent_two.phenotype);
+
+        // Remove the edges found in both parents
+        for node in 0..dim {
+            let mut index = 0;
+            while index < edges_a[node].len() {
+                let other = edges_a[node][index];
+                if node <= other {
+                    if let Some(pos) = edges_b[node].iter().position(|&n| n == other) {
+                        edges_a[node].swap_remove(index);
+                        edges_b[node].swap_remove(pos);
+                        if node != other {
+                            remove_edge(&mut edges_a[other], node);
+                            remove_edge(&mut edges_b[other], node);
+                        }
+                        continue;
+                    }
+                }
+                index += 1;
+            }
+        }
+
+        let cycle = Self::ab_cycle(ctx, &mut edges_a, &mut edges_b)?;
+
+        // Apply the AB-cycle to the edges of parent one
+        let mut child_edges = route_edges(dim, &parent_one.phenotype);
+        for (index, pair) in cycle.windows(2).enumerate() {
+            let (from, to) = (pair[0], pair[1]);
+            if index % 2 == 0 {
+                remove_edge(&mut child_edges[from], to);
+                remove_edge(&mut child_edges[to], from);
+            } else {
+                child_edges[from].push(to);
+                child_edges[to].push(from);
+            }
+        }
+
+        // Decompose the edges into routes starting at the depot and subtours without the depot
+        let mut routes: Vec<Vec<usize>> = Vec::new();
+        while let Some(&first) = child_edges[0].last() {
+            remove_edge(&mut child_edges[0], first);
+            remove_edge(&mut child_edges[first], 0);
+            let mut route = Vec::new();
+            let mut node = first;
+            while node != 0 {
+                route.push(node);
+                let next = *child_edges[node].last().expect("Broken route in EAX");
+                remove_edge(&mut child_edges[node], next);
+                remove_edge(&mut child_edges[next], node);
+                node = next;
+            }
+            routes.push(route);
+        }
+        let mut subtours: Vec<Vec<usize>> = Vec::new();
+        for start in 1..dim {
+            if child_edges[start].is_empty() {
+                continue;
+            }
+            let mut subtour = Vec::new();
+            let mut node = start;
+            loop {
+                subtour.push(node);
+                let next = match child_edges[node].last() {
+                    Some(&next) => next,
+                    None => break,
+                };
+                remove_edge(&mut child_edges[node], next);
+                remove_edge(&mut child_edges[next], node);
+                if next == start {
+                    break;
+                }
+                node = next;
+            }
+            subtours.push(subtour);
+        }
+
+        for subtour in subtours {
+            merge_subtour(ctx, &mut routes, subtour);
+        }
+        routes.retain(|route| !route.is_empty());
+        Some(routes)
+    }
+
+    /// Finds a random AB-cycle by walking alternately on edges from the two parents
+    fn ab_cycle(
+        ctx: &Context,
+        edges_a: &mut [Vec<usize>],
+        edges_b: &mut [Vec<usize>],
+    ) -> Option<Vec<usize>> {
+        let candidates: Vec<usize> = (0..edges_a.len())
+            .filter(|&node| !edges_a[node].is_empty())
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let start = candidates[ctx.random.range_usize(0, candidates.len())];
+        let mut path = vec![start];
+        loop {
+            let current = *path.last().expect("Empty path");
+            // Edges at even positions of the path are from parent one
+            let take_a = path.len() % 2 == 1;
+            let edges = if take_a {
+                &mut edges_a[current]
+            } else {
+                &mut edges_b[current]
+            };
+            if edges.is_empty() {
+                // The walk is stuck at the depot as the parents have a different number of routes
+                return None;
+            }
+            let next = edges.swap_remove(ctx.random.range_usize(0, edges.len()));
+            if take_a {
+                remove_edge(&mut edges_a[next], current);
+            } else {
+                remove_edge(&mut edges_b[next], current);
+            }
+            path.push(next);
+
+            // The cycle is closed when returning to a node that was left with an edge from
+            // parent one, using an edge from parent two
+            if !take_a {
+                let length = path.len();
+                for position in (0..length - 1).step_by(2) {
+                    if path[position] == next {
+                        return Some(path.split_off(position));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Greedy construction of a giant tour using the edges of both parents.
+    ///
+    /// Starting from a random customer, the tour is extended with the closest unvisited
+    /// neighbor in either of the parents, preferring edges found in both. If every neighbor
+    /// is visited, the closest unvisited correlated customer is used instead.
+    fn edge_assembly(
+        &self,
+        ctx: &Context,
+        parent_one: &Individual,
+        parent_two: &Individual,
+    ) -> Vec<usize> {
+        let dim = ctx.problem.dim();
+        let distance = &ctx.matrix_provider.distance;
+        let mut visited = vec![false; dim];
+        visited[0] = true;
+
+        // Customers in random order, used when no correlated customer is unvisited
+        let mut remaining: Vec<usize> = (1..dim).collect();
+        ctx.random.shuffle(remaining.as_mut_slice());
+        let mut remaining_index = 0;
+
+        let mut current = remaining[0];
+        let mut genotype = Vec::with_capacity(dim - 1);
+        loop {
+            visited[current] = true;
+            genotype.push(current);
+            if genotype.len() == dim - 1 {
+                break;
+            }
+
+            let candidates = [
+                parent_one.successor(current),
+                parent_one.predecessor(current),
+                parent_two.successor(current),
+                parent_two.predecessor(current),
+            ];
+            let mut best: Option<(bool, f64, usize)> = None;
+            for (index, &candidate) in candidates.iter().enumerate() {
+                if visited[candidate] {
+                    continue;
+                }
+                // Edges present in both parents are preferred
+                let common = if index < 2 {
+                    candidates[2..].contains(&candidate)
+                } else {
+                    candidates[..2].contains(&candidate)
+                };
+                let cost = distance.get(current, candidate);
+                let better = match best {
+                    Some((best_common, best_cost, _)) => {
+                        (common && !best_common) || (common == best_common && cost < best_cost)
+                    }
+                    None => true,
+                };
+                if better {
+                    best = Some((common, cost, candidate));
+                }
+            }
+
+            current = match best {
+                Some((_, _, next)) => next,
+                None => {
+                    match ctx
+                        .matrix_provider
+                        .correlation
+                        .get(current)
+                        .iter()
+                        .find(|&&neighbor| !visited[neighbor])
+                    {
+                        Some(&neighbor) => neighbor,
+                        None => {
+                            while visited[remaining[remaining_index]] {
+                                remaining_index += 1;
+                            }
+                            remaining[remaining_index]
+                        }
+                    }
+                }
+            };
+        }
+        genotype
+    }
+}
+
+fn nonempty_routes(individual: &Individual) -> Vec<Vec<usize>> {
+    individual
+        .phenotype
+        .iter()
+        .filter(|route| !route.is_empty())
+        .cloned()
+        .collect()
+}
+
+/// Adjacency lists of the undirected edges in the routes, where the depot is node 0
+fn route_edges(dim: usize, routes: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut edges = vec![Vec::new(); dim];
+    for route in routes.iter().filter(|route| !route.is_empty()) {
+        let mut prev = 0;
+        for &node in route.iter().chain(std::iter::once(&0)) {
+            edges[prev].push(node);
+            edges[node].push(prev);
+            prev = node;
+        }
+    }
+    edges
+}
+
+fn remove_edge(edges: &mut Vec<usize>, node: usize) {
+    if let Some(pos) = edges.iter().position(|&n| n == node) {
+        edges.swap_remove(pos);
+    }
+}
+
+/// Penalized cost of a set of routes
+fn routes_cost(ctx: &Context, routes: &[Vec<usize>]) -> f64 {
+    let distance = &ctx.matrix_provider.distance;
+    let capacity = ctx.problem.vehicle.cap;
+    let penalty = ctx.config.borrow().penalty_capacity;
+    routes
+        .iter()
+        .map(|route| {
+            let mut prev = 0;
+            let mut route_distance = 0.0;
+            let mut load = 0.0;
+            for &node in route.iter() {
+                route_distance += distance.get(prev, node);
+                load += ctx.problem.nodes[node].demand;
+                prev = node;
+            }
+            route_distance += distance.get(prev, 0);
+            route_cost(route_distance, load - capacity, penalty)
+        })
+        .sum()
+}
+
+/// Insert the customer at the position with the cheapest penalized cost
+fn insert_cheapest(ctx: &Context, routes: &mut Vec<Vec<usize>>, customer: usize) {
+    let distance = &ctx.matrix_provider.distance;
+    let capacity = ctx.problem.vehicle.cap;
+    let penalty = ctx.config.borrow().penalty_capacity;
+    let demand = ctx.problem.nodes[customer].demand;
+
+    // Cost of serving the customer in a new route
+    let mut best_cost = if (routes.len() as u64) < ctx.config.borrow().num_vehicles {
+        2.0 * distance.get(0, customer)
+    } else {
+        f64::INFINITY
+    };
+    let mut best_position: Option<(usize, usize)> = None;
+
+    for (route_index, route) in routes.iter().enumerate() {
+        let load: f64 = route.iter().map(|&c| ctx.problem.nodes[c].demand).sum();
+        let delta_penalty =
+            penalty * (0f64.max(load + demand - capacity) - 0f64.max(load - capacity));
+        for position in 0..=route.len() {
+            let prev = if position == 0 {
+                0
+            } else {
+                route[position - 1]
+            };
+            let next = if position == route.len() {
+                0
+            } else {
+                route[position]
+            };
+            let cost = distance.get(prev, customer) + distance.get(customer, next)
+                - distance.get(prev, next)
+                + delta_penalty;
+            if cost < best_cost {
+                best_cost = cost;
+                best_position = Some((route_index, position));
+            }
+        }
+    }
+
+    match best_position {
+        Some((route_index, position)) => routes[route_index].insert(position, customer),
+        None => routes.push(vec![customer]),
+    }
+}
+
+/// Merge a subtour into the routes by removing one edge from the subtour and one edge from
+/// the routes, and reconnecting them in the cheapest way
+fn merge_subtour(ctx: &Context, routes: &mut Vec<Vec<usize>>, subtour: Vec<usize>) {
+    let distance = &ctx.matrix_provider.distance;
+    let dim = ctx.problem.dim();
+    let length = subtour.len();
+
+    // Location of each customer on the routes
+    let mut location: Vec<Option<(usize, usize)>> = vec![None; dim];
+    for (route_index, route) in routes.iter().enumerate() {
+        for (position, &customer) in route.iter().enumerate() {
+            location[customer] = Some((route_index, position));
+        }
+    }
+
+    // (cost, route index, insert position, subtour start, reversed)
+    let mut best: Option<(f64, usize, usize, usize, bool)> = None;
+    let mut consider = |route_index: usize, position: usize, route: &[usize]| {
+        let prev = if position == 0 {
+            0
+        } else {
+            route[position - 1]
+        };
+        let next = if position == route.len() {
+            0
+        } else {
+            route[position]
+        };
+        for i in 0..length {
+            let (s, t) = (subtour[i], subtour[(i + 1) % length]);
+            let removed = distance.get(prev, next) + distance.get(s, t);
+            // Forward: prev -> t ... s -> next
+            let forward = distance.get(prev, t) + distance.get(s, next) - removed;
+            // Reversed: prev -> s ... t -> next
+            let reversed = distance.get(prev, s) + distance.get(t, next) - removed;
+            for (cost, is_reversed) in [(forward, false), (reversed, true)] {
+                if best.map_or(true, |b| cost < b.0) {
+                    best = Some((cost, route_index, position, i, is_reversed));
+                }
+            }
+        }
+    };
+
+    // First try the positions next to the correlated customers
+    let granularity = ctx.config.borrow().local_search_granularity as usize;
+    let mut found_neighbor = false;
+    for &node in subtour.iter() {
+        for &neighbor in ctx
+            .matrix_provider
+            .correlation
+            .get(node)
+            .iter()
+            .take(granularity)
+        {
+            if let Some((route_index, position)) = location[neighbor] {
+                consider(route_index, position, &routes[route_index]);
+                consider(route_index, position + 1, &routes[route_index]);
+                found_neighbor = true;
+            }
+        }
+    }
+
+    // Otherwise, try every position in the routes
+    if !found_neighbor {
+        for (route_index, route) in routes.iter().enumerate() {
+            for position in 0..=route.len() {
+                consider(route_index, position, route);
+            }
+        }
+    }
+
+    match best {
+        Some((_, route_index, position, i, reversed)) => {
+            // Sequence of the subtour after removing the edge (subtour[i], subtour[i + 1])
+            let mut sequence: Vec<usize> =
+                (1..=length).map(|k| subtour[(i + k) % length]).collect();
+            if reversed {
+                sequence.reverse();
+            }
+            let route = &mut routes[route_index];
+            let tail = route.split_off(position);
+            route.extend(sequence);
+            route.extend(tail);
+        }
+        None => routes.push(subtour),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::{random_individuals, spread_context};
+
+    const NUM_CUSTOMERS: usize = 12;
+
+    fn assert_permutation(customers: &[usize]) {
+        let mut customers = customers.to_vec();
+        customers.sort_unstable();
+        assert_eq!((1..=NUM_CUSTOMERS).collect::<Vec<usize>>(), customers);
+    }
+
+    // Every pair of parents gives a child with each customer exactly once, where the routes of
+    // route based operators agree with the genotype
+    fn assert_children_are_permutations(operator: CrossoverOperator) {
+        let ctx = spread_context(Config::default());
+        let crossover = Crossover::new(&ctx);
+        let parents = random_individuals(&ctx, 6);
+        for parent_one in parents.iter() {
+            for parent_two in parents.iter() {
+                let child = crossover.run(&ctx, operator, parent_one, parent_two, 0);
+                assert_permutation(&child.genotype);
+                if !child.phenotype.is_empty() {
+                    let customers: Vec<usize> = child.phenotype.concat();
+                    assert_eq!(child.genotype, customers);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn srex_child_is_permutation() {
+        let ctx = spread_context(Config::default());
+        let crossover = Crossover::new(&ctx);
+        let parents = random_individuals(&ctx, 6);
+        for parent_one in parents.iter() {
+            for parent_two in parents.iter() {
+                let routes = crossover
+                    .srex(&ctx, parent_one, parent_two)
+                    .expect("Parents with a single route");
+                assert!(routes.iter().all(|route| !route.is_empty()));
+                assert_permutation(&routes.concat());
+            }
+        }
+    }
+
+    #[test]
+    fn eax_child_is_permutation() {
+        assert_children_are_permutations(CrossoverOperator::Eax);
+    }
+
+    #[test]
+    fn edge_assembly_child_is_permutation() {
+        assert_children_are_permutations(CrossoverOperator::EdgeAssembly);
+    }
+
+    #[test]
+    fn ab_cycle_alternates_between_parents() {
+        let ctx = spread_context(Config::default());
+        let dim = ctx.problem.dim();
+        let parents = random_individuals(&ctx, 6);
+        let mut num_cycles = 0;
+        for parent_two in parents[1..].iter() {
+            let edges_one = route_edges(dim, &parents[0].phenotype);
+            let edges_two = route_edges(dim, &parent_two.phenotype);
+            let (mut edges_a, mut edges_b) = (edges_one.clone(), edges_two.clone());
+            let cycle = match Crossover::ab_cycle(&ctx, &mut edges_a, &mut edges_b) {
+                Some(cycle) => cycle,
+                None => continue,
+            };
+            num_cycles += 1;
+
+            // The cycle is closed, and its edges are alternately from parent one and two
+            assert_eq!(cycle.first(), cycle.last());
+            assert_eq!(0, (cycle.len() - 1) % 2);
+            for (index, pair) in cycle.windows(2).enumerate() {
+                let edges = if index % 2 == 0 {
+                    &edges_one
+                } else {
+                    &edges_two
+                };
+                assert!(edges[pair[0]].contains(&pair[1]));
+            }
+        }
+        assert!(num_cycles > 0);
+    }
+
+    #[test]
+    fn merge_subtour_keeps_customers() {
+        let ctx = spread_context(Config::default());
+        let mut routes = vec![vec![1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]];
+        merge_subtour(&ctx, &mut routes, vec![10, 11, 12]);
+        assert_eq!(3, routes.len());
+        assert_permutation(&routes.concat());
+
+        // The subtour is inserted as a consecutive sequence
+        let route = routes
+            .iter()
+            .find(|route| route.contains(&10))
+            .expect("Subtour not merged");
+        let start = route
+            .iter()
+            .position(|customer| [10, 11, 12].contains(customer))
+            .unwrap();
+        let mut sequence = route[start..start + 3].to_vec();
+        sequence.sort_unstable();
+        assert_eq!(vec![10, 11, 12], sequence);
+    }
+
+    #[test]
+    fn insert_cheapest_keeps_customers() {
+        let ctx = spread_context(Config::default());
+        let base = vec![vec![1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11]];
+        let mut routes = base.clone();
+        insert_cheapest(&ctx, &mut routes, 12);
+        assert_permutation(&routes.concat());
+
+        // No other position, or a new route, is cheaper
+        let cost = routes_cost(&ctx, &routes);
+        let mut new_route = base.clone();
+        new_route.push(vec![12]);
+        assert!(cost <= routes_cost(&ctx, &new_route) + 1e-9);
+        for route_index in 0..base.len() {
+            for position in 0..=base[route_index].len() {
+                let mut alternative = base.clone();
+                alternative[route_index].insert(position, 12);
+                assert!(cost <= routes_cost(&ctx, &alternative) + 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn weights_follow_rewards() {
+        let mut config = Config::default();
+        config.crossover_srex = true;
+        config.crossover_eax = true;
+        config.adaptive_crossover = true;
+        let ctx = spread_context(config);
+        let mut crossover = Crossover::new(&ctx);
+        assert_eq!(
+            vec![
+                CrossoverOperator::Ox,
+                CrossoverOperator::Srex,
+                CrossoverOperator::Eax
+            ],
+            crossover.operators
+        );
+
+        // OX finds new bests, SREX only children worse than the parents and EAX is unused
+        for _ in 0..10 {
+            crossover.reward(CrossoverOperator::Ox, true, true);
+            crossover.reward(CrossoverOperator::Srex, false, false);
+        }
+
+        // The weights are only updated at the interval
+        crossover.update(1);
+        assert_eq!(vec![1.0, 1.0, 1.0], crossover.weights);
+
+        crossover.update(100);
+        assert!(crossover.weights[0] > 1.0);
+        assert!(crossover.weights[1] < 1.0);
+        assert_eq!(1.0, crossover.weights[2]);
+
+        // Operators without rewards decay towards the minimum weight
+        for iteration in 2..100 {
+            crossover.reward(CrossoverOperator::Srex, false, false);
+            crossover.update(iteration * 100);
+        }
+        assert_eq!(MIN_WEIGHT, crossover.weights[1]);
+    }
+}
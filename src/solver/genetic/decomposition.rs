@@ -81,18 +81,18 @@ impl Metaheuristic for DecomposedGeneticAlgorithm {
                 // panic!("{:?}", self.global_solution);
                 // println!("{:?}", self.global_solution);
                 // self.global_solution.print();
-                println!(
+                log::debug!(
                     "Global before decomp: {}",
                     self.global_solution.penalized_cost()
                 );
                 let mut decomposition = Decomposition::new(&mut self.global_solution, ctx);
-                println!(
+                log::debug!(
                     "Sub solution before: {}",
                     decomposition.solution.penalized_cost()
                 );
                 decomposition.run();
                 decomposition.finish(ctx);
-                println!(
+                log::debug!(
                     "Global after before: {}",
                     self.global_solution.penalized_cost()
                 );
@@ -139,7 +139,7 @@ impl<'a> Decomposition<'a> {
         let sub_ctx = ctx.from_mapping(&mapping[..]);
         sub_solution.sort_routes(&sub_ctx);
         sub_solution.evaluate(&sub_ctx);
-        println!("Decomposed size: {}", sub_ctx.problem.dim());
+        log::debug!("Decomposed size: {}", sub_ctx.problem.dim());
         Self {
             global,
             mapping,
@@ -153,7 +153,7 @@ impl<'a> Decomposition<'a> {
             let mut config = self.ctx.config.borrow_mut();
             config.elite_education = false;
             config.max_iterations = Some(2000);
-            println!("{:?}", config);
+            log::debug!("{:?}", config);
         }
 
         let mut sub_ga = GeneticAlgorithm::new(&self.ctx);
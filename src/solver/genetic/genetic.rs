@@ -1,8 +1,6 @@
-use std::collections::HashSet;
-
-use crate::solver::genetic::{Individual, Population, Split};
-use crate::solver::improvement::{LocalSearch, RuinRecreate};
-use crate::solver::{Context, Metaheuristic};
+use crate::solver::genetic::{Crossover, Individual, Population, RoutePool, Split};
+use crate::solver::improvement::{LocalSearch, RouteElimination, RuinRecreate};
+use crate::solver::{is_improvement, Context, Metaheuristic, TracePoint};
 use crate::utils::FloatCompare;
 
 #[derive(PartialEq)]
@@ -17,12 +15,17 @@ enum State {
 pub struct GeneticAlgorithm {
     state: State,
     pub population: Population,
+    pub crossover: Crossover,
     pub ls: LocalSearch,
     pub rr: RuinRecreate,
+    pub re: RouteElimination,
+    pub route_pool: RoutePool,
     pub split: Split,
     pub iterations: u64,
     pub next_penalty_update: u64,
     pub next_log_interval: u64,
+    pub next_route_elimination: u64,
+    pub next_set_partitioning: u64,
 
     pub child: Individual,
     pub num_initialized: u64,
@@ -32,6 +35,7 @@ pub struct GeneticAlgorithm {
     pub diversified_start: u64,
     pub diversify: bool,
     pub current_best_solution_cost: f64,
+    pub current_best_num_routes: usize,
     pub diversity: f64,
 }
 
@@ -40,104 +44,31 @@ impl GeneticAlgorithm {
         Self {
             state: State::Created,
             population: Population::new(ctx),
+            crossover: Crossover::new(ctx),
             split: Split::new(ctx),
             ls: LocalSearch::new(ctx, 1.0),
             rr: RuinRecreate::new(ctx),
+            re: RouteElimination::new(ctx),
+            route_pool: RoutePool::new(ctx),
             iterations: 0,
             child: Individual::new_random(ctx, 0),
             num_initialized: 0,
             // Update penalty at this iteration
             next_penalty_update: 0,
             next_log_interval: 0,
+            next_route_elimination: ctx.config.borrow().route_elimination_interval,
+            next_set_partitioning: ctx.config.borrow().set_partitioning_interval,
             diversified_start: 0,
             diversify: false,
 
             best_solution: None,
             current_best_solution_cost: f64::INFINITY,
+            current_best_num_routes: usize::MAX,
             best_iteration: 0,
             diversity: 1.0,
         }
     }
 
-    fn crossover(
-        &self,
-        ctx: &Context,
-        parent_one: &Individual,
-        parent_two: &Individual,
-    ) -> Individual {
-        // Randomly select start of the crossover zone
-        let start = ctx.random.range_usize(0, parent_one.genotype.len());
-
-        // Randomly select end of the crossover zone different from the start
-        let mut end;
-        loop {
-            end = ctx.random.range_usize(0, parent_one.genotype.len());
-            if start != end {
-                break;
-            }
-        }
-
-        // Use the OX crossover
-        self.crossover_ox(ctx, parent_one, parent_two, start, end)
-    }
-
-    fn crossover_ox(
-        &self,
-        ctx: &Context,
-        parent_one: &Individual,
-        parent_two: &Individual,
-        start: usize,
-        end: usize,
-    ) -> Individual {
-        // Helper function to wrap indices in the vector around
-        #[inline]
-        fn wrap_index(index: usize, length: usize) -> usize {
-            if index == length {
-                0
-            } else {
-                index
-            }
-        }
-        // Clone the genotype of the first parent
-        let mut genotype = parent_one.genotype.clone();
-
-        // Set with all the genes added to the genotype
-        let mut added_genes =
-            HashSet::with_capacity_and_hasher(genotype.len(), ctx.random.random_state());
-
-        // Index into the genotype
-        let mut index = start;
-
-        loop {
-            // Add the gene from parent one into the set of genes
-            added_genes.insert(genotype[index]);
-
-            // Done with parent one
-            if index == end {
-                index = wrap_index(index + 1, genotype.len());
-                break;
-            }
-
-            // Move index one to the right, possibly wrapping around
-            index = wrap_index(index + 1, genotype.len());
-        }
-
-        // Add genes from parent_two until all genes have been added
-        for &parent_two_gene in parent_two.genotype.iter() {
-            // Check to see if the gene is already added
-            if !added_genes.contains(&parent_two_gene) {
-                // Add the parent two gene to the set of added genes
-                added_genes.insert(parent_two_gene);
-                // Update the childs genotype with the gene from parent two
-                genotype[index] = parent_two_gene;
-                // Update the index into the childs genotype
-                index = wrap_index(index + 1, genotype.len());
-            }
-        }
-        // Return the child
-        Individual::new(genotype, self.population.total_individuals_count)
-    }
-
     fn update_penalty(&mut self, ctx: &Context) {
         // Set the next iteration in which the penalty should be updated
         self.next_penalty_update += ctx.config.borrow().penalty_update_interval;
@@ -195,6 +126,7 @@ impl GeneticAlgorithm {
                 self.ls.run(ctx, &mut self.child, 10.0);
             }
             if self.child.is_feasible() {
+                self.collect_routes(ctx);
                 self.update_best(ctx);
                 self.population
                     .add_individual(ctx, self.child.clone(), false);
@@ -203,63 +135,152 @@ impl GeneticAlgorithm {
         }
 
         // Update best solution
+        self.collect_routes(ctx);
         self.update_best(ctx);
     }
 
+    /// Add the routes of the child to the route pool
+    fn collect_routes(&mut self, ctx: &Context) {
+        if ctx.config.borrow().set_partitioning {
+            self.route_pool.add_individual(ctx, &self.child);
+        }
+    }
+
+    fn recombine_routes(&mut self, ctx: &Context) {
+        // Set the next iteration in which the routes should be recombined
+        self.next_set_partitioning += ctx.config.borrow().set_partitioning_interval;
+
+        let best = match self.population.feasible.get_best() {
+            Some(best) => best.clone(),
+            None => return,
+        };
+
+        let number = self.population.total_individuals_count;
+        if let Some(individual) = self.route_pool.recombine(ctx, &best, number) {
+            let message = format!(
+                "Set partitioning: {} routes in pool, cost {:.2}",
+                self.route_pool.len(),
+                individual.penalized_cost()
+            );
+            log::info!("Time: {:?}, {}", ctx.elapsed(), message);
+            ctx.search_history.borrow_mut().add_message(message);
+            self.child = individual;
+            self.update_best(ctx);
+            self.add_individual(ctx, self.child.clone());
+        }
+    }
+
     fn log(&mut self, ctx: &Context) {
         self.next_log_interval += ctx.config.borrow().log_interval;
-        let mut log_text = String::new();
-        log_text.push_str(&format!("T(s): {:.2} | ", ctx.elapsed_as_secs_f64()));
-        log_text.push_str(&format!(
-            "Iter: {:6} {:4} | ",
-            self.iterations,
-            self.iterations - self.best_iteration
-        ));
-        log_text.push_str(&format!(
-            "Feas {} {:.2} {:.2} | ",
-            self.population.feasible.size(),
-            self.population.feasible.get_best_cost(),
-            self.population.feasible.get_average_cost(ctx)
-        ));
-        log_text.push_str(&format!(
-            "Inf {} {:.2} {:.2} | ",
-            self.population.infeasible.size(),
-            self.population.infeasible.get_best_cost(),
-            self.population.infeasible.get_average_cost(ctx)
-        ));
         self.diversity =
             self.population.feasible.get_diversity(ctx) / (ctx.problem.dim() - 1) as f64;
-        log_text.push_str(&format!(
-            "Div {:.2} {:.2} | ",
-            self.diversity,
-            self.population.infeasible.get_diversity(ctx) / (ctx.problem.dim() - 1) as f64
-        ));
-        log_text.push_str(&format!(
-            "Feas {:.2} | ",
-            self.population.history_fraction()
-        ));
-        log_text.push_str(&format!("Pen {:.2}", ctx.config.borrow().penalty_capacity));
-        log::debug!("{}", log_text);
+        let crossover_weights = match ctx.config.borrow().adaptive_crossover {
+            true => self
+                .crossover
+                .operators
+                .iter()
+                .zip(&self.crossover.weights)
+                .map(|(operator, &weight)| (operator.name().to_owned(), weight))
+                .collect(),
+            false => Vec::new(),
+        };
+        let trace_point = TracePoint {
+            time: ctx.elapsed_as_secs_f64(),
+            iteration: self.iterations,
+            iterations_without_improvement: self.iterations - self.best_iteration,
+            feasible_size: self.population.feasible.size(),
+            feasible_best: self.population.feasible.get_best_cost(),
+            feasible_average: self.population.feasible.get_average_cost(ctx),
+            infeasible_size: self.population.infeasible.size(),
+            infeasible_best: self.population.infeasible.get_best_cost(),
+            infeasible_average: self.population.infeasible.get_average_cost(ctx),
+            feasible_diversity: self.diversity,
+            infeasible_diversity: self.population.infeasible.get_diversity(ctx)
+                / (ctx.problem.dim() - 1) as f64,
+            feasible_fraction: self.population.history_fraction(),
+            penalty: ctx.config.borrow().penalty_capacity,
+            crossover_weights,
+        };
+        log::debug!("{}", trace_point);
+        ctx.search_history.borrow_mut().trace.push(trace_point);
+    }
+
+    fn eliminate_route(&mut self, ctx: &Context) {
+        // Set the next iteration in which route elimination should be attempted
+        self.next_route_elimination += ctx.config.borrow().route_elimination_interval;
+
+        // With fleet minimization the best solution so far has the fewest routes, while the best
+        // of the population may have more
+        let best = match ctx.config.borrow().fleet_minimization {
+            true => self.best_solution.clone(),
+            false => self.population.feasible.get_best().cloned(),
+        };
+        let best = match best {
+            Some(best) => best,
+            None => return,
+        };
+
+        let number = self.population.total_individuals_count;
+        if let Some(mut individual) = self.re.run(ctx, &best, number) {
+            // Educate the individual, unless the local search opens new routes
+            let num_routes = individual.num_nonempty_routes();
+            let mut educated = individual.clone();
+            self.ls.run(ctx, &mut educated, 1.0);
+            if educated.is_feasible() && educated.num_nonempty_routes() <= num_routes {
+                individual = educated;
+            }
+
+            let message = format!(
+                "Route elimination: {} routes, cost {:.2}",
+                individual.num_nonempty_routes(),
+                individual.penalized_cost()
+            );
+            log::info!("Time: {:?}, {}", ctx.elapsed(), message);
+            ctx.search_history.borrow_mut().add_message(message);
+            self.child = individual;
+            self.update_best(ctx);
+            self.add_individual(ctx, self.child.clone());
+        }
     }
 
     pub fn update_best(&mut self, ctx: &Context) {
+        let fleet_minimization = ctx.config.borrow().fleet_minimization;
+        let num_routes = self.child.num_nonempty_routes();
         if self.child.is_feasible()
-            && self
-                .child
-                .penalized_cost()
-                .approx_lt(self.current_best_solution_cost)
+            && is_improvement(
+                fleet_minimization,
+                (num_routes, self.child.penalized_cost()),
+                (
+                    self.current_best_num_routes,
+                    self.current_best_solution_cost,
+                ),
+            )
         {
             self.best_iteration = self.iterations;
             self.current_best_solution_cost = self.child.penalized_cost();
-            let mut search_history = ctx.search_history.borrow_mut();
-            if self
-                .current_best_solution_cost
-                .approx_lt(search_history.best_cost)
+            self.current_best_num_routes = num_routes;
+            if !ctx.search_history.borrow().is_new_best(
+                fleet_minimization,
+                num_routes,
+                self.current_best_solution_cost,
+            ) {
+                return;
+            }
+
+            // Optimize the order within the routes of the new best solution, and add the
+            // improved individual to the population next to the child
+            let mut best = self.child.clone();
+            if ctx.config.borrow().tsp_optimize_best
+                && ctx.route_optimizer.borrow_mut().run(ctx, &mut best)
             {
-                self.best_solution = Some(self.child.clone());
-                search_history.add_message(format!("New best: {:.2}", self.child.penalized_cost()));
-                search_history.add(ctx, &self.child);
+                self.current_best_solution_cost = best.penalized_cost();
+                self.add_individual(ctx, best.clone());
             }
+
+            let mut search_history = ctx.search_history.borrow_mut();
+            search_history.add_message(format!("New best: {:.2}", best.penalized_cost()));
+            search_history.add(ctx, &best);
+            self.best_solution = Some(best);
         }
     }
 
@@ -267,10 +288,11 @@ impl GeneticAlgorithm {
         ctx.search_history
             .borrow_mut()
             .add_message(format!("Resetting"));
-        self.population = Population::new(ctx);
+        self.population = self.population.renew(ctx);
         self.next_penalty_update = self.iterations;
         self.next_log_interval = self.iterations;
         self.current_best_solution_cost = f64::INFINITY;
+        self.current_best_num_routes = usize::MAX;
         self.best_iteration = self.iterations;
         self.num_initialized = 0;
         self.state = State::Created;
@@ -325,6 +347,7 @@ impl Metaheuristic for GeneticAlgorithm {
                 } else {
                     self.state = State::Initialization;
                     self.rr.get_best_solution(&mut self.child);
+                    self.collect_routes(ctx);
                     self.update_best(ctx);
                     self.population
                         .add_individual(ctx, self.child.clone(), false);
@@ -347,17 +370,42 @@ impl Metaheuristic for GeneticAlgorithm {
                 ctx.next_iteration();
 
                 // Select two parents and perform crossover
-                let parent_one = self.population.get_parent(ctx);
-                let parent_two = self.population.get_parent(ctx);
-                self.child = self.crossover(ctx, parent_one, parent_two);
+                let (parent_one, parent_two) = self.population.select_parents(ctx);
+                let operator = self.crossover.select(ctx);
+                self.child = self.crossover.run(
+                    ctx,
+                    operator,
+                    parent_one,
+                    parent_two,
+                    self.population.total_individuals_count,
+                );
+                let parents_cost = parent_one.penalized_cost().min(parent_two.penalized_cost());
 
                 // Max number of routes the child is allowed to get
                 let max_routes = parent_one.num_nonempty_routes();
-                self.split.run(ctx, &mut self.child, max_routes as u64);
+
+                // Children without routes, or with too many routes, are split from the genotype
+                let num_vehicles = ctx.config.borrow().num_vehicles as usize;
+                if self.child.phenotype.is_empty() || self.child.phenotype.len() > num_vehicles {
+                    self.split.run(ctx, &mut self.child, max_routes as u64);
+                } else {
+                    self.child.phenotype.resize(num_vehicles, Vec::new());
+                    self.child.sort_routes(ctx);
+                    self.child.evaluate(ctx);
+                }
 
                 // Educate child
+                let best_cost_before = self.current_best_solution_cost;
                 self.educate(ctx);
 
+                // Reward the crossover operator based on the quality of the child
+                self.crossover.reward(
+                    operator,
+                    self.current_best_solution_cost.approx_lt(best_cost_before),
+                    self.child.penalized_cost().approx_lt(parents_cost),
+                );
+                self.crossover.update(self.iterations);
+
                 // Add child to population
                 self.population
                     .add_individual(ctx, self.child.clone(), true);
@@ -372,6 +420,20 @@ impl Metaheuristic for GeneticAlgorithm {
                     self.log(ctx);
                 }
 
+                // Try to eliminate a route from the best solution at interval
+                if ctx.config.borrow().route_elimination
+                    && self.iterations >= self.next_route_elimination
+                {
+                    self.eliminate_route(ctx);
+                }
+
+                // Recombine the routes in the pool at interval
+                if ctx.config.borrow().set_partitioning
+                    && self.iterations >= self.next_set_partitioning
+                {
+                    self.recombine_routes(ctx);
+                }
+
                 // Possible reset of population
                 if self.iterations - self.best_iteration
                     > ctx.config.borrow().max_iterations_without_improvement
@@ -389,3 +451,36 @@ impl Metaheuristic for GeneticAlgorithm {
         self.state == State::Terminated
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::test_context;
+
+    // Four customers on a line, served by a single vehicle
+    fn context() -> Context {
+        let points: Vec<(f64, f64)> = (0..5).map(|id| (id as f64, 0.0)).collect();
+        test_context(&points, &[1.0; 4], 4.0, Config::default())
+    }
+
+    #[test]
+    fn optimized_best_joins_population() {
+        let ctx = context();
+        let mut genetic = GeneticAlgorithm::new(&ctx);
+        let initial = Individual::from_routes(&ctx, vec![vec![2, 4, 1, 3]], 0);
+        assert_eq!(12.0, initial.penalized_cost());
+        genetic.add_initial(&ctx, initial);
+
+        assert_eq!(
+            8.0,
+            genetic.best_solution.as_ref().unwrap().penalized_cost()
+        );
+        assert_eq!(8.0, genetic.current_best_solution_cost);
+        assert_eq!(8.0, ctx.search_history.borrow().best_cost);
+
+        // The initial individual and its optimized order
+        assert_eq!(2, genetic.population.feasible.size());
+        assert_eq!(8.0, genetic.population.feasible.get_best_cost());
+    }
+}
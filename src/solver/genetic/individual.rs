@@ -39,6 +39,24 @@ impl Individual {
         }
     }
 
+    /// Individual of a given solution, with empty routes up to the number of vehicles
+    pub fn from_routes(ctx: &Context, mut routes: Vec<Vec<usize>>, number: u64) -> Self {
+        let genotype = routes.iter().flatten().copied().collect();
+        let num_vehicles = ctx.config.borrow().num_vehicles as usize;
+        while routes.len() < num_vehicles {
+            routes.push(Vec::new());
+        }
+        let mut individual = Self {
+            number,
+            genotype,
+            phenotype: routes,
+            fitness: f64::INFINITY,
+            evaluation: SolutionEvaluation::new(),
+        };
+        individual.evaluate(ctx);
+        individual
+    }
+
     pub fn new_random(ctx: &Context, number: u64) -> Self {
         let mut genotype: Vec<usize> = (1usize..ctx.problem.nodes.len()).collect();
 
@@ -1,3 +1,5 @@
+mod crossover;
+pub use self::crossover::*;
 mod decomposition;
 pub use self::decomposition::*;
 mod individual;
@@ -6,5 +8,9 @@ mod genetic;
 pub use self::genetic::*;
 mod population;
 pub use self::population::*;
+mod route_pool;
+pub use self::route_pool::*;
+mod selection;
+pub use self::selection::*;
 mod split;
 pub use self::split::*;
@@ -1,9 +1,10 @@
 use std::cmp::{min, Ordering};
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use ahash::RandomState;
 
-use crate::solver::genetic::Individual;
+use crate::solver::genetic::{Individual, ParentSelection, SurvivorSelection};
 use crate::solver::Context;
 
 #[derive(Debug)]
@@ -52,10 +53,13 @@ pub struct SubPopulation {
 
     // Maximum allowed individuals in the population
     max_individuals: usize,
+
+    // Strategy selecting which individual to remove during natural selection
+    survivor_selection: Rc<dyn SurvivorSelection>,
 }
 
 impl SubPopulation {
-    pub fn new(ctx: &Context) -> Self {
+    pub fn new(ctx: &Context, survivor_selection: Rc<dyn SurvivorSelection>) -> Self {
         let config = ctx.config.borrow();
         let max_individuals = (config.min_population_size + config.population_lambda) as usize;
         Self {
@@ -66,6 +70,7 @@ impl SubPopulation {
             ),
             focus_diversity: false,
             max_individuals,
+            survivor_selection,
         }
     }
 
@@ -186,39 +191,11 @@ impl SubPopulation {
     }
 
     pub fn natural_selection(&mut self, ctx: &Context) {
-        // Initialize the worst to the first individual
-        let mut worst_index = 1usize;
-        let mut worst_is_clone = false;
-        let mut worst_fitness = -1.0;
-
-        for index in 1..self.population.len() {
-            let is_clone = self.individual_is_clone(&self.population[index]);
-
-            // The worst should be updated if the worst is not a clone, but the individual is
-            let mut update_worst = is_clone && !worst_is_clone;
-
-            // Update worst based on fitness if either both are clones or none of them are clones
-            update_worst = update_worst
-                || (worst_is_clone == is_clone && self.population[index].fitness >= worst_fitness);
-
-            // Update which individual is the worst
-            if update_worst {
-                worst_index = index;
-                worst_is_clone = is_clone;
-                worst_fitness = self.population[index].fitness;
-            }
-        }
-
-        // let num_elites = ctx.config.borrow().num_elites as usize;
-
-        // if !worst_is_clone && ctx.random.real() < 0.1 && self.population.len() >= num_elites {
-        //     worst_index = ctx.random.range_usize(num_elites, self.population.len())
-        // }
-
+        let worst_index = self.survivor_selection.select_removal(ctx, self);
         self.remove_individual(ctx, worst_index);
     }
 
-    fn individual_is_clone(&self, individual: &Individual) -> bool {
+    pub fn individual_is_clone(&self, individual: &Individual) -> bool {
         // Check if the distance to the closest in the population is zero, then the individual
         // is considered to be a clone
         if let Some(diversity_vec) = self.diversity.get(&individual.number) {
@@ -229,6 +206,17 @@ impl SubPopulation {
         }
     }
 
+    /// The broken pairs distance from the individual to the closest in the population
+    pub fn closest_distance(&self, individual: &Individual) -> i64 {
+        if let Some(diversity_vec) = self.diversity.get(&individual.number) {
+            diversity_vec
+                .first()
+                .map_or(i64::MAX, |diversity| diversity.distance)
+        } else {
+            panic!("No diversity vector for indivdual");
+        }
+    }
+
     fn update_diversity(&mut self, index: usize) {
         // Calculate the diversity against all the other in the population
         for other_index in 0..self.population.len() {
@@ -376,18 +364,59 @@ pub struct Population {
 
     // History of the feasibility of the individuals added to the population
     pub feasible_history: Vec<bool>,
+
+    // Strategies for parent and survivor selection
+    parent_selection: Rc<dyn ParentSelection>,
+    survivor_selection: Rc<dyn SurvivorSelection>,
 }
 
 impl Population {
     pub fn new(ctx: &Context) -> Self {
+        let (parent_selection, survivor_selection) = {
+            let config = ctx.config.borrow();
+            (
+                config.parent_selection.build(),
+                config.survivor_selection.build(),
+            )
+        };
+        Self::with_selection(ctx, parent_selection, survivor_selection)
+    }
+
+    /// Create an empty population with the given selection strategies
+    pub fn with_selection(
+        ctx: &Context,
+        parent_selection: Rc<dyn ParentSelection>,
+        survivor_selection: Rc<dyn SurvivorSelection>,
+    ) -> Self {
         Self {
             total_individuals_count: 0,
-            feasible: SubPopulation::new(ctx),
-            infeasible: SubPopulation::new(ctx),
+            feasible: SubPopulation::new(ctx, survivor_selection.clone()),
+            infeasible: SubPopulation::new(ctx, survivor_selection.clone()),
             feasible_history: vec![true; 100],
+            parent_selection,
+            survivor_selection,
         }
     }
 
+    /// Create an empty population with the same selection strategies
+    pub fn renew(&self, ctx: &Context) -> Self {
+        Self::with_selection(
+            ctx,
+            self.parent_selection.clone(),
+            self.survivor_selection.clone(),
+        )
+    }
+
+    pub fn set_parent_selection(&mut self, parent_selection: Rc<dyn ParentSelection>) {
+        self.parent_selection = parent_selection;
+    }
+
+    pub fn set_survivor_selection(&mut self, survivor_selection: Rc<dyn SurvivorSelection>) {
+        self.feasible.survivor_selection = survivor_selection.clone();
+        self.infeasible.survivor_selection = survivor_selection.clone();
+        self.survivor_selection = survivor_selection;
+    }
+
     pub fn size(&self) -> usize {
         self.feasible.size() + self.infeasible.size()
     }
@@ -413,45 +442,103 @@ impl Population {
         self.total_individuals_count += 1;
     }
 
-    pub fn get_parent(&self, ctx: &Context) -> &Individual {
-        self.tournament(ctx, ctx.config.borrow().tournament_size as usize)
+    /// Select two parents for the crossover
+    pub fn select_parents(&self, ctx: &Context) -> (&Individual, &Individual) {
+        let parent_one = self.parent_selection.select(ctx, self, None);
+        let parent_two = self.parent_selection.select(ctx, self, Some(parent_one));
+        (parent_one, parent_two)
+    }
+
+    /// Get an individual by index, where the feasible individuals are indexed before the infeasible
+    pub fn get(&self, index: usize) -> &Individual {
+        if index < self.feasible.size() {
+            &self.feasible.population[index]
+        } else {
+            &self.infeasible.population[index - self.feasible.size()]
+        }
     }
 
     pub fn history_fraction(&self) -> f64 {
         self.feasible_history.iter().filter(|&&x| x).count() as f64
             / self.feasible_history.len() as f64
     }
+}
 
-    fn tournament(&self, ctx: &Context, num_contestants: usize) -> &Individual {
-        // let lower = if ctx.random.real() < 0.1 { 0 } else { 1 };
-        let lower = 0;
-        // Sample `k` individuals from the two subpopulations
-        let indicies: Vec<usize> = (0..num_contestants)
-            .into_iter()
-            .map(|_| ctx.random.range_usize(lower, self.size()))
-            .collect();
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
 
-        // Select the winner which has the lowest cost
-        let mut winner: Option<&Individual> = None;
-        for index in indicies {
-            // Select the correct subpopulation and offset the index
-            let (sub_population, population_index) = if index < self.feasible.size() {
-                (&self.feasible, index)
-            } else {
-                (&self.infeasible, index - self.feasible.size())
-            };
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::genetic::{AgeSurvivor, ParentSelectionKind, SurvivorSelectionKind};
+    use crate::solver::testing::test_context;
 
-            // Update the winner if the individual is better
-            let individual = &sub_population.population[population_index];
-            if let Some(current_winner) = winner {
-                if individual.fitness < current_winner.fitness {
-                    winner = Some(individual);
-                }
-            } else {
-                winner = Some(individual);
-            }
+    // Selects the first individual and counts the selections
+    struct FirstSelection {
+        count: Cell<usize>,
+    }
+
+    impl ParentSelection for FirstSelection {
+        fn select<'a>(
+            &self,
+            _ctx: &Context,
+            population: &'a Population,
+            _mate: Option<&Individual>,
+        ) -> &'a Individual {
+            self.count.set(self.count.get() + 1);
+            population.get(0)
         }
+    }
+
+    fn context(config: Config) -> Context {
+        let points: Vec<(f64, f64)> = (0..5).map(|id| (id as f64, (id % 2) as f64)).collect();
+        test_context(&points, &[1.0; 4], 2.0, config)
+    }
 
-        winner.expect("No winner found")
+    #[test]
+    fn renew_keeps_selection() {
+        let mut config = Config::default();
+        config.parent_selection = ParentSelectionKind::Rank;
+        config.survivor_selection = SurvivorSelectionKind::Crowding;
+        let ctx = context(config);
+
+        // The strategies of the config
+        let population = Population::new(&ctx);
+        let renewed = population.renew(&ctx);
+        assert!(Rc::ptr_eq(
+            &population.parent_selection,
+            &renewed.parent_selection
+        ));
+        assert!(Rc::ptr_eq(
+            &population.survivor_selection,
+            &renewed.survivor_selection
+        ));
+        assert!(Rc::ptr_eq(
+            &population.survivor_selection,
+            &renewed.feasible.survivor_selection
+        ));
+        assert!(Rc::ptr_eq(
+            &population.survivor_selection,
+            &renewed.infeasible.survivor_selection
+        ));
+
+        // Strategies set after the population is created
+        let mut population = Population::new(&ctx);
+        let parent_selection = Rc::new(FirstSelection {
+            count: Cell::new(0),
+        });
+        let survivor_selection: Rc<dyn SurvivorSelection> = Rc::new(AgeSurvivor);
+        population.set_parent_selection(parent_selection.clone());
+        population.set_survivor_selection(survivor_selection.clone());
+
+        let mut renewed = population.renew(&ctx);
+        assert!(Rc::ptr_eq(
+            &survivor_selection,
+            &renewed.feasible.survivor_selection
+        ));
+        let individual = Individual::from_routes(&ctx, vec![vec![1, 2], vec![3, 4]], 0);
+        renewed.add_individual(&ctx, individual, false);
+        renewed.select_parents(&ctx);
+        assert_eq!(2, parent_selection.count.get());
     }
 }
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use ahash::RandomState;
+use instant::{Duration, Instant};
+
+use crate::solver::genetic::Individual;
+use crate::solver::improvement::route_distance;
+use crate::solver::Context;
+use crate::utils::FloatCompare;
+
+// Fraction of the pool which is kept when the pool exceeds its maximum size
+const POOL_KEEP_FRACTION: f64 = 0.8;
+
+// Number of branch-and-bound nodes between the checks of the time limit
+const TIME_CHECK_INTERVAL: u64 = 1000;
+
+struct PoolRoute {
+    route: Vec<usize>,
+    distance: f64,
+    // Last time the route was added to the pool
+    last_seen: u64,
+}
+
+/// Pool of the feasible routes found during the search.
+///
+/// Routes are identified by their set of customers, keeping the shortest order seen. When the
+/// pool exceeds `route_pool_size`, the routes which have not been seen for the longest time are
+/// removed.
+pub struct RoutePool {
+    routes: HashMap<Vec<usize>, PoolRoute, RandomState>,
+    max_size: usize,
+    counter: u64,
+}
+
+impl RoutePool {
+    pub fn new(ctx: &Context) -> Self {
+        let max_size = ctx.config.borrow().route_pool_size as usize;
+        Self {
+            routes: HashMap::with_capacity_and_hasher(max_size, ctx.random.random_state()),
+            max_size,
+            counter: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Add the feasible routes of the individual to the pool
+    pub fn add_individual(&mut self, ctx: &Context, individual: &Individual) {
+        for route in individual.phenotype.iter() {
+            self.add_route(ctx, route);
+        }
+    }
+
+    /// Add the route to the pool if it is nonempty and feasible
+    pub fn add_route(&mut self, ctx: &Context, route: &[usize]) {
+        if route.is_empty() {
+            return;
+        }
+        let load: f64 = route.iter().map(|&c| ctx.problem.nodes[c].demand).sum();
+        if load.approx_gt(ctx.problem.vehicle.cap) {
+            return;
+        }
+
+        self.counter += 1;
+        let distance = route_distance(&ctx.matrix_provider.distance, route);
+        let mut key = route.to_vec();
+        key.sort_unstable();
+
+        let last_seen = self.counter;
+        let entry = self.routes.entry(key).or_insert_with(|| PoolRoute {
+            route: route.to_vec(),
+            distance,
+            last_seen,
+        });
+        entry.last_seen = last_seen;
+        if distance.approx_lt(entry.distance) {
+            entry.route = route.to_vec();
+            entry.distance = distance;
+        }
+
+        if self.routes.len() > self.max_size {
+            self.shrink();
+        }
+    }
+
+    /// Remove the routes which have not been seen for the longest time
+    fn shrink(&mut self) {
+        let mut last_seen: Vec<u64> = self.routes.values().map(|route| route.last_seen).collect();
+        last_seen.sort_unstable();
+        let keep = (self.max_size as f64 * POOL_KEEP_FRACTION) as usize;
+        let threshold = last_seen[last_seen.len() - keep.min(last_seen.len())];
+        self.routes.retain(|_, route| route.last_seen >= threshold);
+    }
+
+    /// Improve the best individual by recombining routes from the pool.
+    ///
+    /// A random route and its `set_partitioning_neighborhood - 1` closest routes are selected,
+    /// and the cheapest set of pool routes covering exactly their customers replaces them. This
+    /// is repeated once per route or until the time limit. Returns the new individual if it is
+    /// cheaper.
+    pub fn recombine(
+        &mut self,
+        ctx: &Context,
+        best: &Individual,
+        number: u64,
+    ) -> Option<Individual> {
+        // Make sure the routes of the best solution are in the pool
+        self.add_individual(ctx, best);
+
+        let (num_vehicles, time_limit, neighborhood) = {
+            let config = ctx.config.borrow();
+            (
+                config.num_vehicles as usize,
+                config.set_partitioning_time_limit,
+                config.set_partitioning_neighborhood as usize,
+            )
+        };
+        let distance = &ctx.matrix_provider.distance;
+        let dim = ctx.problem.dim();
+        let deadline = Instant::now() + Duration::from_secs_f64(time_limit);
+
+        let columns: Vec<(&[usize], f64)> = self
+            .routes
+            .values()
+            .map(|route| (route.route.as_slice(), route.distance))
+            .collect();
+        let mut routes: Vec<Vec<usize>> = best
+            .phenotype
+            .iter()
+            .filter(|route| !route.is_empty())
+            .cloned()
+            .collect();
+
+        let mut improved = false;
+        let mut in_subset = vec![false; dim];
+        for _ in 0..routes.len() {
+            if Instant::now() >= deadline {
+                break;
+            }
+
+            // Select the routes closest to a random route
+            let centroids: Vec<(f64, f64)> =
+                routes.iter().map(|route| centroid(ctx, route)).collect();
+            let seed = centroids[ctx.random.range_usize(0, routes.len())];
+            let mut selected: Vec<usize> = (0..routes.len()).collect();
+            selected.sort_by(|&a, &b| {
+                let distance_a = (centroids[a].0 - seed.0).hypot(centroids[a].1 - seed.1);
+                let distance_b = (centroids[b].0 - seed.0).hypot(centroids[b].1 - seed.1);
+                distance_a.partial_cmp(&distance_b).unwrap()
+            });
+            selected.truncate(neighborhood.max(2));
+
+            let customers: Vec<usize> = selected
+                .iter()
+                .flat_map(|&route| routes[route].iter().copied())
+                .collect();
+            for &customer in customers.iter() {
+                in_subset[customer] = true;
+            }
+
+            // Solve the set partitioning over the pool routes within the selected customers
+            let subset_columns: Vec<(&[usize], f64)> = columns
+                .iter()
+                .filter(|(route, _)| route.iter().all(|&customer| in_subset[customer]))
+                .copied()
+                .collect();
+            let upper_bound = selected
+                .iter()
+                .map(|&route| route_distance(distance, &routes[route]))
+                .sum();
+            let max_columns = selected.len() + num_vehicles.saturating_sub(routes.len());
+            let solution = SetPartitioning::new(&subset_columns, &customers, dim, max_columns)
+                .solve(upper_bound, deadline);
+
+            for &customer in customers.iter() {
+                in_subset[customer] = false;
+            }
+
+            // Replace the selected routes with the new routes
+            if let Some(solution) = solution {
+                selected.sort_unstable_by(|a, b| b.cmp(a));
+                for route in selected {
+                    routes.swap_remove(route);
+                }
+                routes.extend(
+                    solution
+                        .iter()
+                        .map(|&column| subset_columns[column].0.to_vec()),
+                );
+                improved = true;
+            }
+        }
+
+        if !improved {
+            return None;
+        }
+
+        let genotype = routes.iter().flatten().copied().collect();
+        routes.resize(num_vehicles, Vec::new());
+        let mut individual = Individual::new(genotype, number);
+        individual.phenotype = routes;
+        individual.sort_routes(ctx);
+        individual.evaluate(ctx);
+        Some(individual)
+    }
+}
+
+/// Mean coordinate of the customers in the route
+fn centroid(ctx: &Context, route: &[usize]) -> (f64, f64) {
+    let (x, y) = route.iter().fold((0.0, 0.0), |(x, y), &customer| {
+        let coord = &ctx.problem.nodes[customer].coord;
+        (x + coord.lng, y + coord.lat)
+    });
+    (x / route.len() as f64, y / route.len() as f64)
+}
+
+/// Branch-and-bound for the set partitioning problem over the routes.
+///
+/// Branches on the uncovered customer with the fewest routes, trying its routes in order of
+/// increasing cost. The lower bound distributes the cost of each route evenly over its
+/// customers, and every uncovered customer contributes its cheapest share.
+pub struct SetPartitioning<'a> {
+    columns: &'a [(&'a [usize], f64)],
+    max_columns: usize,
+
+    // Columns covering each customer, sorted by cost
+    cover: Vec<Vec<usize>>,
+
+    // Lower bound on the cost of covering each customer
+    share: Vec<f64>,
+
+    // Search state
+    covered: Vec<bool>,
+    chosen: Vec<usize>,
+    best: Option<Vec<usize>>,
+    best_cost: f64,
+    nodes: u64,
+    deadline: Option<Instant>,
+    timed_out: bool,
+}
+
+impl<'a> SetPartitioning<'a> {
+    /// Set partitioning of the `customers` with the `columns`, using at most `max_columns`.
+    /// The columns can only contain customers from `customers`.
+    pub fn new(
+        columns: &'a [(&'a [usize], f64)],
+        customers: &[usize],
+        dim: usize,
+        max_columns: usize,
+    ) -> Self {
+        let mut cover = vec![Vec::new(); dim];
+        let mut share = vec![0.0; dim];
+        let mut covered = vec![true; dim];
+        for &customer in customers.iter() {
+            covered[customer] = false;
+            share[customer] = f64::INFINITY;
+        }
+        for (index, &(column_customers, cost)) in columns.iter().enumerate() {
+            for &customer in column_customers.iter() {
+                cover[customer].push(index);
+                share[customer] = share[customer].min(cost / column_customers.len() as f64);
+            }
+        }
+        for column_indices in cover.iter_mut() {
+            column_indices.sort_by(|&a, &b| columns[a].1.partial_cmp(&columns[b].1).unwrap());
+        }
+
+        Self {
+            columns,
+            max_columns,
+            cover,
+            share,
+            covered,
+            chosen: Vec::new(),
+            best: None,
+            best_cost: f64::INFINITY,
+            nodes: 0,
+            deadline: None,
+            timed_out: false,
+        }
+    }
+
+    /// Find the cheapest partitioning with a cost below `upper_bound`, searching until the
+    /// deadline. Returns the indices of the selected columns.
+    pub fn solve(mut self, upper_bound: f64, deadline: Instant) -> Option<Vec<usize>> {
+        // Every customer must be covered by at least one column
+        if self.share.iter().any(|share| share.is_infinite()) {
+            return None;
+        }
+        self.best_cost = upper_bound;
+        self.deadline = Some(deadline);
+        let lower_bound = self.share.iter().sum();
+        self.branch(0.0, lower_bound);
+        self.best
+    }
+
+    fn branch(&mut self, cost: f64, lower_bound: f64) {
+        self.nodes += 1;
+        if self.nodes % TIME_CHECK_INTERVAL == 0
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out = true;
+        }
+        if self.timed_out || !(cost + lower_bound).approx_lt(self.best_cost) {
+            return;
+        }
+
+        // Select the uncovered customer with the fewest columns
+        let customer = (0..self.covered.len())
+            .filter(|&customer| !self.covered[customer])
+            .min_by_key(|&customer| self.cover[customer].len());
+        let customer = match customer {
+            Some(customer) => customer,
+            None => {
+                self.best_cost = cost;
+                self.best = Some(self.chosen.clone());
+                return;
+            }
+        };
+        if self.chosen.len() >= self.max_columns {
+            return;
+        }
+
+        for index in 0..self.cover[customer].len() {
+            let column = self.cover[customer][index];
+            let (customers, column_cost) = self.columns[column];
+            if customers.iter().any(|&c| self.covered[c]) {
+                continue;
+            }
+
+            let mut new_lower_bound = lower_bound;
+            for &c in customers.iter() {
+                self.covered[c] = true;
+                new_lower_bound -= self.share[c];
+            }
+            self.chosen.push(column);
+
+            self.branch(cost + column_cost, new_lower_bound);
+
+            self.chosen.pop();
+            for &c in customers.iter() {
+                self.covered[c] = false;
+            }
+            if self.timed_out {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_partitioning_finds_cheapest_cover() {
+        let routes: Vec<Vec<usize>> = vec![
+            vec![1, 2],
+            vec![3, 4],
+            vec![1, 3],
+            vec![2, 4],
+            vec![1, 2, 3],
+            vec![4],
+        ];
+        let costs = [10.0, 10.0, 6.0, 6.0, 13.0, 5.0];
+        let columns: Vec<(&[usize], f64)> = routes
+            .iter()
+            .zip(costs.iter())
+            .map(|(route, &cost)| (route.as_slice(), cost))
+            .collect();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut selected = SetPartitioning::new(&columns, &[1, 2, 3, 4], 5, 10)
+            .solve(f64::INFINITY, deadline)
+            .expect("No solution");
+        selected.sort();
+        assert_eq!(vec![2, 3], selected);
+
+        // No solution cheaper than the upper bound
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(SetPartitioning::new(&columns, &[1, 2, 3, 4], 5, 10)
+            .solve(12.0, deadline)
+            .is_none());
+
+        // No single route covers all the customers
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(SetPartitioning::new(&columns, &[1, 2, 3, 4], 5, 1)
+            .solve(f64::INFINITY, deadline)
+            .is_none());
+    }
+}
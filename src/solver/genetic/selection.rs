@@ -0,0 +1,469 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::solver::genetic::{Individual, Population, SubPopulation};
+use crate::solver::Context;
+
+/// Strategy for selecting the parents used in the crossover.
+///
+/// Implement this trait and set it with `Population::set_parent_selection` to use a custom
+/// strategy. The `mate` is the already selected first parent when selecting the second parent.
+pub trait ParentSelection {
+    fn select<'a>(
+        &self,
+        ctx: &Context,
+        population: &'a Population,
+        mate: Option<&Individual>,
+    ) -> &'a Individual;
+}
+
+/// Strategy for selecting which individual to remove from a subpopulation during natural
+/// selection.
+///
+/// Implement this trait and set it with `Population::set_survivor_selection` to use a custom
+/// strategy. The returned index is into the subpopulation, which is sorted on penalized cost.
+pub trait SurvivorSelection {
+    fn select_removal(&self, ctx: &Context, population: &SubPopulation) -> usize;
+}
+
+/// The built-in parent selection strategies, selected with `parent_selection` in the config
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParentSelectionKind {
+    Tournament,
+    Rank,
+    Roulette,
+    RestrictedMating,
+}
+
+/// The built-in survivor selection strategies, selected with `survivor_selection` in the config
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurvivorSelectionKind {
+    BiasedFitness,
+    Crowding,
+    Age,
+}
+
+impl ParentSelectionKind {
+    pub fn build(&self) -> Rc<dyn ParentSelection> {
+        match self {
+            Self::Tournament => Rc::new(TournamentSelection),
+            Self::Rank => Rc::new(RankSelection),
+            Self::Roulette => Rc::new(RouletteSelection),
+            Self::RestrictedMating => Rc::new(RestrictedMating),
+        }
+    }
+}
+
+impl SurvivorSelectionKind {
+    pub fn build(&self) -> Rc<dyn SurvivorSelection> {
+        match self {
+            Self::BiasedFitness => Rc::new(BiasedFitnessSurvivor),
+            Self::Crowding => Rc::new(CrowdingSurvivor),
+            Self::Age => Rc::new(AgeSurvivor),
+        }
+    }
+}
+
+// Weight given to the worst individual in the roulette selection
+const MIN_ROULETTE_WEIGHT: f64 = 0.01;
+
+/// Select the index of an individual with probability proportional to the weights
+fn roulette(ctx: &Context, weights: &[f64]) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut value = ctx.random.real() * total;
+    for (index, &weight) in weights.iter().enumerate() {
+        if value < weight {
+            return index;
+        }
+        value -= weight;
+    }
+    weights.len() - 1
+}
+
+/// Binary tournament (or `tournament_size` contestants) on biased fitness
+pub struct TournamentSelection;
+
+impl ParentSelection for TournamentSelection {
+    fn select<'a>(
+        &self,
+        ctx: &Context,
+        population: &'a Population,
+        _mate: Option<&Individual>,
+    ) -> &'a Individual {
+        let num_contestants = ctx.config.borrow().tournament_size as usize;
+        tournament(ctx, population, num_contestants)
+    }
+}
+
+fn tournament<'a>(
+    ctx: &Context,
+    population: &'a Population,
+    num_contestants: usize,
+) -> &'a Individual {
+    // Sample `k` individuals from the two subpopulations and select the one with the lowest fitness
+    let mut winner: Option<&Individual> = None;
+    for _ in 0..num_contestants {
+        let individual = population.get(ctx.random.range_usize(0, population.size()));
+        if let Some(current_winner) = winner {
+            if individual.fitness < current_winner.fitness {
+                winner = Some(individual);
+            }
+        } else {
+            winner = Some(individual);
+        }
+    }
+
+    winner.expect("No winner found")
+}
+
+/// Linear ranking on biased fitness, where `selection_pressure` in [1, 2] is the expected
+/// number of times the best individual is selected relative to the average individual
+pub struct RankSelection;
+
+impl ParentSelection for RankSelection {
+    fn select<'a>(
+        &self,
+        ctx: &Context,
+        population: &'a Population,
+        _mate: Option<&Individual>,
+    ) -> &'a Individual {
+        let size = population.size();
+        if size == 1 {
+            return population.get(0);
+        }
+        let pressure = ctx.config.borrow().selection_pressure.clamp(1.0, 2.0);
+
+        // Sort the individuals on fitness, where the best gets the highest rank
+        let mut ranked: Vec<usize> = (0..size).collect();
+        ranked.sort_by(|&a, &b| {
+            population
+                .get(b)
+                .fitness
+                .partial_cmp(&population.get(a).fitness)
+                .unwrap()
+        });
+        let weights: Vec<f64> = (0..size)
+            .map(|rank| 2.0 - pressure + 2.0 * (pressure - 1.0) * rank as f64 / (size - 1) as f64)
+            .collect();
+
+        population.get(ranked[roulette(ctx, &weights)])
+    }
+}
+
+/// Roulette wheel on biased fitness, where lower fitness gives a larger share of the wheel
+pub struct RouletteSelection;
+
+impl ParentSelection for RouletteSelection {
+    fn select<'a>(
+        &self,
+        ctx: &Context,
+        population: &'a Population,
+        _mate: Option<&Individual>,
+    ) -> &'a Individual {
+        let size = population.size();
+        let max_fitness = (0..size)
+            .map(|index| population.get(index).fitness)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = (0..size)
+            .map(|index| max_fitness - population.get(index).fitness + MIN_ROULETTE_WEIGHT)
+            .collect();
+
+        population.get(roulette(ctx, &weights))
+    }
+}
+
+/// Tournament selection where the second parent must have a broken pairs distance to the
+/// first parent within [`mating_min_distance`, `mating_max_distance`], given as fractions of
+/// the number of customers. Falls back to the tournament winner if no candidate is within range.
+pub struct RestrictedMating;
+
+impl ParentSelection for RestrictedMating {
+    fn select<'a>(
+        &self,
+        ctx: &Context,
+        population: &'a Population,
+        mate: Option<&Individual>,
+    ) -> &'a Individual {
+        let config = ctx.config.borrow();
+        let mate = match mate {
+            Some(mate) => mate,
+            None => return tournament(ctx, population, config.tournament_size as usize),
+        };
+
+        let num_customers = ctx.problem.num_customers() as f64;
+        let min_distance = config.mating_min_distance * num_customers;
+        let max_distance = config.mating_max_distance * num_customers;
+
+        let mut fallback: Option<&Individual> = None;
+        let mut winner: Option<&Individual> = None;
+        for _ in 0..config.mating_candidates.max(1) {
+            let candidate = tournament(ctx, population, config.tournament_size as usize);
+            if fallback.map_or(true, |f| candidate.fitness < f.fitness) {
+                fallback = Some(candidate);
+            }
+            let distance = candidate.calculate_broken_pairs_distance(mate) as f64;
+            if distance >= min_distance
+                && distance <= max_distance
+                && winner.map_or(true, |w| candidate.fitness < w.fitness)
+            {
+                winner = Some(candidate);
+            }
+        }
+
+        winner.or(fallback).expect("No candidates found")
+    }
+}
+
+/// Remove clones first, then the individual with the worst biased fitness
+pub struct BiasedFitnessSurvivor;
+
+impl SurvivorSelection for BiasedFitnessSurvivor {
+    fn select_removal(&self, _ctx: &Context, population: &SubPopulation) -> usize {
+        // Initialize the worst to the first individual
+        let mut worst_index = 1usize;
+        let mut worst_is_clone = false;
+        let mut worst_fitness = -1.0;
+
+        for index in 1..population.size() {
+            let individual = &population.population[index];
+            let is_clone = population.individual_is_clone(individual);
+
+            // The worst should be updated if the worst is not a clone, but the individual is
+            let mut update_worst = is_clone && !worst_is_clone;
+
+            // Update worst based on fitness if either both are clones or none of them are clones
+            update_worst =
+                update_worst || (worst_is_clone == is_clone && individual.fitness >= worst_fitness);
+
+            // Update which individual is the worst
+            if update_worst {
+                worst_index = index;
+                worst_is_clone = is_clone;
+                worst_fitness = individual.fitness;
+            }
+        }
+
+        worst_index
+    }
+}
+
+/// Remove the individual in the most crowded region, i.e. the one with the smallest broken
+/// pairs distance to its closest neighbour. Ties are broken by removing the one with the
+/// highest cost, such that the worst of two close individuals is removed.
+pub struct CrowdingSurvivor;
+
+impl SurvivorSelection for CrowdingSurvivor {
+    fn select_removal(&self, _ctx: &Context, population: &SubPopulation) -> usize {
+        let mut worst_index = 1usize;
+        let mut worst_distance = i64::MAX;
+
+        for index in 1..population.size() {
+            let distance = population.closest_distance(&population.population[index]);
+            if distance <= worst_distance {
+                worst_index = index;
+                worst_distance = distance;
+            }
+        }
+
+        worst_index
+    }
+}
+
+/// Remove clones first, then the oldest individual. The `num_elites` best individuals are
+/// never removed.
+pub struct AgeSurvivor;
+
+impl SurvivorSelection for AgeSurvivor {
+    fn select_removal(&self, ctx: &Context, population: &SubPopulation) -> usize {
+        let num_elites = ctx.config.borrow().num_elites as usize;
+        let first = if population.size() > num_elites {
+            num_elites.max(1)
+        } else {
+            1
+        };
+
+        let mut oldest_index = first;
+        let mut oldest_number = u64::MAX;
+        for index in 1..population.size() {
+            let individual = &population.population[index];
+            if population.individual_is_clone(individual) {
+                return index;
+            }
+            if index >= first && individual.number < oldest_number {
+                oldest_index = index;
+                oldest_number = individual.number;
+            }
+        }
+
+        oldest_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::{random_individuals, spread_context};
+
+    fn population(ctx: &Context) -> Population {
+        let mut population = Population::new(ctx);
+        for individual in random_individuals(ctx, 10) {
+            population.add_individual(ctx, individual, false);
+        }
+        population
+    }
+
+    // Number of times each individual of the population is selected as the first parent
+    fn selection_counts(
+        ctx: &Context,
+        population: &Population,
+        selection: &dyn ParentSelection,
+    ) -> Vec<usize> {
+        let mut counts = vec![0; population.size()];
+        for _ in 0..2000 {
+            let selected = selection.select(ctx, population, None);
+            let index = (0..population.size())
+                .position(|index| std::ptr::eq(population.get(index), selected))
+                .expect("Selected individual not in population");
+            counts[index] += 1;
+        }
+        counts
+    }
+
+    // Total count of the individuals with the lowest and the highest fitness
+    fn best_and_worst_counts(population: &Population, counts: &[usize]) -> (usize, usize) {
+        let fitness: Vec<f64> = (0..population.size())
+            .map(|index| population.get(index).fitness)
+            .collect();
+        let best = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+        let worst = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let count = |value: f64| -> usize {
+            (0..fitness.len())
+                .filter(|&index| fitness[index] == value)
+                .map(|index| counts[index])
+                .sum()
+        };
+        (count(best), count(worst))
+    }
+
+    #[test]
+    fn rank_selection_favors_low_fitness() {
+        let mut config = Config::default();
+        config.selection_pressure = 2.0;
+        let ctx = spread_context(config);
+        let population = population(&ctx);
+        let counts = selection_counts(&ctx, &population, &RankSelection);
+
+        // With the maximum pressure the worst ranked individual is never selected
+        let (best, worst) = best_and_worst_counts(&population, &counts);
+        assert!(best > worst);
+        assert_eq!(1, counts.iter().filter(|&&count| count == 0).count());
+    }
+
+    #[test]
+    fn roulette_selection_favors_low_fitness() {
+        let ctx = spread_context(Config::default());
+        let population = population(&ctx);
+        let counts = selection_counts(&ctx, &population, &RouletteSelection);
+
+        let (best, worst) = best_and_worst_counts(&population, &counts);
+        assert!(best > worst);
+        assert_eq!(2000, counts.iter().sum::<usize>());
+    }
+
+    #[test]
+    fn restricted_mating_avoids_close_mates() {
+        let mut config = Config::default();
+        config.mating_min_distance = 0.01;
+        config.mating_max_distance = 1.0;
+        config.mating_candidates = 20;
+        let ctx = spread_context(config);
+        let population = population(&ctx);
+
+        // The mate itself is at distance zero, so it is only selected as a fallback
+        let mate = population.get(0);
+        for _ in 0..100 {
+            let selected = RestrictedMating.select(&ctx, &population, Some(mate));
+            assert!(!std::ptr::eq(mate, selected));
+        }
+
+        // Without a candidate in range, the best candidate of the tournaments is selected, which
+        // is found by replaying the tournaments from the same state of the random generator
+        ctx.config.borrow_mut().mating_min_distance = 2.0;
+        ctx.config.borrow_mut().mating_max_distance = 2.0;
+        let tournament_size = ctx.config.borrow().tournament_size as usize;
+        for seed in 0..20 {
+            ctx.random.rng.borrow().seed(seed);
+            let best = (0..20)
+                .map(|_| tournament(&ctx, &population, tournament_size).fitness)
+                .fold(f64::INFINITY, f64::min);
+            ctx.random.rng.borrow().seed(seed);
+            let selected = RestrictedMating.select(&ctx, &population, Some(mate));
+            assert_eq!(best, selected.fitness);
+        }
+    }
+
+    #[test]
+    fn crowding_removes_closest_with_highest_cost() {
+        let ctx = spread_context(Config::default());
+        let mut population = SubPopulation::new(&ctx, Rc::new(CrowdingSurvivor));
+        let mut individuals = random_individuals(&ctx, 6);
+
+        // A copy of the first individual with two customers of a route swapped is its closest
+        let mut close = individuals[0].clone();
+        close.number = 6;
+        let route = close
+            .phenotype
+            .iter_mut()
+            .find(|route| route.len() >= 3)
+            .expect("No route with three customers");
+        route.swap(0, 1);
+        close.evaluate(&ctx);
+        individuals.push(close);
+        for individual in individuals {
+            population.add_individual(&ctx, individual);
+        }
+
+        let removed = &population.population[CrowdingSurvivor.select_removal(&ctx, &population)];
+        let (first, close) = {
+            let find = |number: u64| {
+                population
+                    .population
+                    .iter()
+                    .find(|individual| individual.number == number)
+                    .unwrap()
+            };
+            (find(0), find(6))
+        };
+        let expected = if close.penalized_cost() >= first.penalized_cost() {
+            close
+        } else {
+            first
+        };
+        assert_eq!(expected.number, removed.number);
+    }
+
+    #[test]
+    fn age_removes_oldest_except_elites() {
+        let mut config = Config::default();
+        config.num_elites = 2;
+        let ctx = spread_context(config);
+        let mut population = SubPopulation::new(&ctx, Rc::new(AgeSurvivor));
+        for individual in random_individuals(&ctx, 8) {
+            population.add_individual(&ctx, individual);
+        }
+
+        // The two best individuals are kept regardless of their age
+        let index = AgeSurvivor.select_removal(&ctx, &population);
+        assert!(index >= 2);
+        let oldest = population.population[2..]
+            .iter()
+            .map(|individual| individual.number)
+            .min()
+            .unwrap();
+        assert_eq!(oldest, population.population[index].number);
+    }
+}
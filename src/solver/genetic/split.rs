@@ -139,14 +139,14 @@ impl Split {
         if limited_fleet {
             // Reset all
             self.path_cost.set(0, 0, 0.0);
-            for row in 0..self.path_cost.rows {
-                for col in 1..self.path_cost.cols {
+            for row in 0..self.path_cost.rows() {
+                for col in 1..self.path_cost.cols() {
                     self.path_cost.set(row, col, 1e30);
                 }
             }
         } else {
             self.path_cost.set(0, 0, 0.0);
-            for col in 1..self.path_cost.cols {
+            for col in 1..self.path_cost.cols() {
                 self.path_cost.set(0, col, 1e30);
             }
         }
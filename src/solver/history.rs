@@ -4,8 +4,11 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
+use crate::plot;
+use crate::solver::dimacs;
 use crate::solver::genetic::Individual;
 use crate::solver::Context;
+use crate::utils::FloatCompare;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HistoricSolution {
@@ -25,6 +28,12 @@ impl From<&Individual> for HistoricSolution {
     }
 }
 
+impl HistoricSolution {
+    pub fn num_routes(&self) -> usize {
+        self.routes.iter().filter(|route| !route.is_empty()).count()
+    }
+}
+
 impl fmt::Display for HistoricSolution {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut route_number = 1;
@@ -49,6 +58,8 @@ pub struct HistoryEntry {
     pub solution: HistoricSolution,
     // Timestamp in duration since solver started
     pub timestamp: Duration,
+    // Iteration of the search at which the solution was found
+    pub iteration: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -63,6 +74,63 @@ impl fmt::Display for HistoryMessage {
     }
 }
 
+/// Statistics of the search at a log interval of the genetic algorithm
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TracePoint {
+    // Seconds since the start
+    pub time: f64,
+    pub iteration: u64,
+    pub iterations_without_improvement: u64,
+    pub feasible_size: usize,
+    pub feasible_best: f64,
+    pub feasible_average: f64,
+    pub infeasible_size: usize,
+    pub infeasible_best: f64,
+    pub infeasible_average: f64,
+    pub feasible_diversity: f64,
+    pub infeasible_diversity: f64,
+    // Fraction of the recent offspring which are feasible
+    pub feasible_fraction: f64,
+    pub penalty: f64,
+    // Weights of the crossover operators, which are only adapted with adaptive crossover
+    pub crossover_weights: Vec<(String, f64)>,
+}
+
+impl fmt::Display for TracePoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "T(s): {:.2} | ", self.time)?;
+        write!(
+            f,
+            "Iter: {:6} {:4} | ",
+            self.iteration, self.iterations_without_improvement
+        )?;
+        write!(
+            f,
+            "Feas {} {:.2} {:.2} | ",
+            self.feasible_size, self.feasible_best, self.feasible_average
+        )?;
+        write!(
+            f,
+            "Inf {} {:.2} {:.2} | ",
+            self.infeasible_size, self.infeasible_best, self.infeasible_average
+        )?;
+        write!(
+            f,
+            "Div {:.2} {:.2} | ",
+            self.feasible_diversity, self.infeasible_diversity
+        )?;
+        write!(f, "Feas {:.2} | ", self.feasible_fraction)?;
+        write!(f, "Pen {:.2}", self.penalty)?;
+        if !self.crossover_weights.is_empty() {
+            write!(f, " | Cross")?;
+            for (operator, weight) in self.crossover_weights.iter() {
+                write!(f, " {} {:.2}", operator, weight)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub struct SearchHistory {
     // History of solutions
@@ -75,7 +143,16 @@ pub struct SearchHistory {
 
     pub messages: Vec<HistoryMessage>,
 
+    // Statistics of the search at each log interval
+    pub trace: Vec<TracePoint>,
+
+    // Lower bound on the cost of the solutions
+    pub lower_bound: Option<f64>,
+
     log_new_best: bool,
+
+    // Whether the best solution was added without running the search
+    external_best: bool,
 }
 
 impl SearchHistory {
@@ -85,7 +162,10 @@ impl SearchHistory {
             best_cost: f64::INFINITY,
             start_time,
             messages: Vec::new(),
+            trace: Vec::new(),
+            lower_bound: None,
             log_new_best: true,
+            external_best: false,
         }
     }
 
@@ -95,19 +175,48 @@ impl SearchHistory {
         }
     }
 
-    pub fn add(&mut self, _ctx: &Context, individual: &Individual) {
+    /// Add a solution which is not found by the search, such as one read from a file
+    pub fn add_solution(&mut self, solution: HistoricSolution) {
+        self.best_cost = solution.cost;
+        self.external_best = true;
+        self.remove_previous_data();
+        self.history.push(HistoryEntry {
+            solution,
+            timestamp: self.start_time.elapsed(),
+            iteration: 0,
+        });
+    }
+
+    pub fn add(&mut self, ctx: &Context, individual: &Individual) {
         self.best_cost = individual.penalized_cost();
+        self.external_best = false;
         let timestamp = self.start_time.elapsed();
         let history_entry = HistoryEntry {
             solution: HistoricSolution::from(individual),
             timestamp,
+            iteration: *ctx.iteration.borrow(),
         };
 
-        #[cfg(feature = "dimacs")]
-        {
-            if self.log_new_best {
-                println!("{}", history_entry.solution);
+        // Subproblems of the decomposition do not log new best solutions, and are not plotted
+        if self.log_new_best {
+            let (dimacs, rounded) = {
+                let config = ctx.config.borrow();
+                (config.dimacs, config.round_distances)
+            };
+            if dimacs {
+                println!(
+                    "{}",
+                    dimacs::format_solution(&history_entry.solution, timestamp, rounded)
+                );
             }
+
+            plot::write_frame(
+                ctx,
+                self.history.len(),
+                &history_entry.solution.routes,
+                self.best_cost,
+                history_entry.timestamp.as_secs_f64(),
+            );
         }
 
         let new_best_message = HistoryMessage {
@@ -130,6 +239,18 @@ impl SearchHistory {
         self.messages.push(history_message);
     }
 
+    /// Whether a feasible solution with the number of routes and cost improves on the last entry
+    pub fn is_new_best(&self, fleet_minimization: bool, num_routes: usize, cost: f64) -> bool {
+        let best_num_routes = self
+            .last_entry()
+            .map_or(usize::MAX, |entry| entry.solution.num_routes());
+        is_improvement(
+            fleet_minimization,
+            (num_routes, cost),
+            (best_num_routes, self.best_cost),
+        )
+    }
+
     pub fn entries(&self) -> &Vec<HistoryEntry> {
         &self.history
     }
@@ -138,7 +259,41 @@ impl SearchHistory {
         self.history.last()
     }
 
+    /// Whether the best solution was found by the search, and not added with `add_solution`
+    pub fn is_searched(&self) -> bool {
+        !self.external_best
+    }
+
     pub fn log_new_best(&mut self, log_new_best: bool) {
         self.log_new_best = log_new_best;
     }
 }
+
+/// Whether a solution with the number of routes and cost improves on the best, where fleet
+/// minimization compares the number of routes before the cost
+pub fn is_improvement(
+    fleet_minimization: bool,
+    (num_routes, cost): (usize, f64),
+    (best_num_routes, best_cost): (usize, f64),
+) -> bool {
+    match fleet_minimization {
+        true if num_routes != best_num_routes => num_routes < best_num_routes,
+        _ => cost.approx_lt(best_cost),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fleet_minimization_compares_routes_first() {
+        assert!(is_improvement(false, (5, 90.0), (4, 100.0)));
+        assert!(!is_improvement(false, (3, 110.0), (4, 100.0)));
+
+        assert!(!is_improvement(true, (5, 90.0), (4, 100.0)));
+        assert!(is_improvement(true, (3, 110.0), (4, 100.0)));
+        assert!(is_improvement(true, (4, 90.0), (4, 100.0)));
+        assert!(!is_improvement(true, (4, 100.0), (4, 100.0)));
+    }
+}
@@ -1,15 +1,61 @@
-use std::fmt;
-use std::ptr;
+use std::ops::{Index, IndexMut};
 
 use crate::models::CircleSector;
 
+/// Id of a node in the `LinkArena`, which is the index of the node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    /// Marks a missing node, such as the predecessor of a start depot
+    pub const NONE: NodeId = NodeId(u32::MAX);
+
+    // Ids are only created by the arena, such that they always index within it
+    #[inline]
+    fn new(index: usize) -> Self {
+        debug_assert!(index < u32::MAX as usize);
+        Self(index as u32)
+    }
+
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline]
+    pub fn is_none(self) -> bool {
+        self == Self::NONE
+    }
+}
+
+/// Id of a route in the `LinkArena`, which is the index of the route
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RouteId(u32);
+
+impl RouteId {
+    /// Marks a node which is not in a route
+    pub const NONE: RouteId = RouteId(u32::MAX);
+
+    // Ids are only created by the arena, such that they always index within it
+    #[inline]
+    fn new(index: usize) -> Self {
+        debug_assert!(index < u32::MAX as usize);
+        Self(index as u32)
+    }
+
+    #[inline]
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct LinkNode {
     pub number: usize,
     pub angle: i32,
-    pub successor: *mut LinkNode,
-    pub predecessor: *mut LinkNode,
-    pub route: *mut LinkRoute,
+    pub successor: NodeId,
+    pub predecessor: NodeId,
+    pub route: RouteId,
     pub position: usize,
     pub last_tested: i32,
     pub cum_distance: f64,
@@ -19,13 +65,13 @@ pub struct LinkNode {
 }
 
 impl LinkNode {
-    pub unsafe fn new(number: usize, angle: i32) -> Self {
+    pub fn new(number: usize, angle: i32) -> Self {
         Self {
             number,
             angle,
-            successor: ptr::null_mut(),
-            predecessor: ptr::null_mut(),
-            route: ptr::null_mut(),
+            successor: NodeId::NONE,
+            predecessor: NodeId::NONE,
+            route: RouteId::NONE,
             position: 0,
             last_tested: 0,
             cum_distance: f64::default(),
@@ -37,98 +83,15 @@ impl LinkNode {
     pub fn is_depot(&self) -> bool {
         self.number == 0
     }
-
-    /// Links together two nodes
-    #[inline]
-    pub unsafe fn link_nodes(node_one: *mut LinkNode, node_two: *mut LinkNode) {
-        (*node_one).successor = node_two;
-        (*node_two).predecessor = node_one;
-    }
-
-    /// Insert `node_one` directly after `node_two`
-    #[inline]
-    pub unsafe fn insert_node(node_one: *mut LinkNode, node_two: *mut LinkNode) {
-        let node_one_prev = (*node_one).predecessor;
-        let node_one_next = (*node_one).successor;
-        let node_two_next = (*node_two).successor;
-        Self::link_nodes(node_one_prev, node_one_next);
-        Self::link_nodes(node_two, node_one);
-        Self::link_nodes(node_one, node_two_next);
-    }
-
-    /// Reverse the sequence going forward from `from_node`
-    pub unsafe fn forward_reverse(
-        mut from_node: *mut LinkNode,
-        to_node: *mut LinkNode,
-        new_first_node: *mut LinkNode,
-    ) {
-        let mut node = (*from_node).successor;
-        while !node.is_null() {
-            let next_node = (*node).successor;
-
-            // If at first node and we have a new first node
-            if next_node.is_null() && !new_first_node.is_null() {
-                Self::link_nodes(new_first_node, from_node);
-            } else {
-                Self::link_nodes(node, from_node);
-            }
-            if !to_node.is_null() {
-                if (*node).number == (*to_node).number {
-                    break;
-                }
-            }
-            from_node = node;
-            node = next_node;
-        }
-    }
-
-    /// Reverse the sequence going backward from `from_node`
-    pub unsafe fn backward_reverse(
-        mut from_node: *mut LinkNode,
-        to_node: *mut LinkNode,
-        new_last_node: *mut LinkNode,
-    ) {
-        let mut node = (*from_node).predecessor;
-        while !node.is_null() {
-            let next_node = (*node).predecessor;
-
-            // If at last node and we have a new last node
-            if next_node.is_null() && !new_last_node.is_null() {
-                Self::link_nodes(from_node, new_last_node);
-            } else {
-                Self::link_nodes(from_node, node);
-            }
-            if !to_node.is_null() {
-                if (*node).number == (*to_node).number {
-                    break;
-                }
-            }
-            from_node = node;
-            node = next_node;
-        }
-    }
-
-    #[inline]
-    pub unsafe fn replace_end_depot(mut from_node: *mut LinkNode, end_depot: *mut LinkNode) {
-        let mut next_node = (*from_node).successor;
-        while !next_node.is_null() {
-            // Update last depot when at last node
-            if (*next_node).successor.is_null() {
-                Self::link_nodes(from_node, end_depot);
-            }
-            from_node = next_node;
-            next_node = (*next_node).successor;
-        }
-    }
 }
 
 #[derive(Clone, Debug)]
 pub struct LinkRoute {
     pub index: usize,
 
-    // Reference to the depot nodes
-    pub start_depot: *mut LinkNode,
-    pub end_depot: *mut LinkNode,
+    // Ids of the depot nodes
+    pub start_depot: NodeId,
+    pub end_depot: NodeId,
 
     // Number of customers visisted in the route
     pub num_customers: usize,
@@ -156,7 +119,7 @@ pub struct LinkRoute {
 }
 
 impl LinkRoute {
-    pub fn new(index: usize, start_depot: *mut LinkNode, end_depot: *mut LinkNode) -> Self {
+    pub fn new(index: usize, start_depot: NodeId, end_depot: NodeId) -> Self {
         Self {
             index,
             start_depot,
@@ -177,19 +140,258 @@ impl LinkRoute {
     }
 }
 
-impl fmt::Display for LinkRoute {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut customers: Vec<usize> = Vec::new();
-        let mut next_node_ptr = self.start_depot;
-        while !next_node_ptr.is_null() {
-            unsafe {
-                let node = &*next_node_ptr;
-                if !node.is_depot() {
-                    customers.push(node.number);
-                }
-                next_node_ptr = node.successor;
+/// Owns the nodes and routes of the local search, which refer to each other by ids.
+///
+/// The node of a customer has the customer number as id, and is followed by a start and an end
+/// depot node for each route. Nodes and routes are never added or removed once the arena is
+/// created, and ids are only handed out by the arena, so they always index within it. Indexing
+/// is checked, such that following a missing id, as after a user-defined move breaks the links,
+/// panics instead of reading out of bounds.
+#[derive(Clone, Debug)]
+pub(crate) struct LinkArena {
+    nodes: Vec<LinkNode>,
+    routes: Vec<LinkRoute>,
+}
+
+impl LinkArena {
+    /// Create the nodes with the given angles, where the first is the depot, and the routes
+    pub fn new(angles: impl Iterator<Item = i32>, num_routes: usize) -> Self {
+        let mut nodes: Vec<LinkNode> = angles
+            .enumerate()
+            .map(|(number, angle)| LinkNode::new(number, angle))
+            .collect();
+        let mut routes = Vec::with_capacity(num_routes);
+        for index in 0..num_routes {
+            let start_depot = NodeId::new(nodes.len());
+            nodes.push(LinkNode::new(0, 0));
+            let end_depot = NodeId::new(nodes.len());
+            nodes.push(LinkNode::new(0, 0));
+            routes.push(LinkRoute::new(index, start_depot, end_depot));
+        }
+        Self { nodes, routes }
+    }
+
+    /// Whether the id refers to a node of this arena
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        !id.is_none() && id.index() < self.nodes.len()
+    }
+
+    /// Whether the id refers to a route of this arena
+    pub fn contains_route(&self, id: RouteId) -> bool {
+        id != RouteId::NONE && id.index() < self.routes.len()
+    }
+
+    /// Id of the node of a customer
+    #[inline]
+    pub fn customer(&self, number: usize) -> NodeId {
+        assert!(
+            number > 0 && number < self.nodes.len() && !self.nodes[number].is_depot(),
+            "Unknown customer {}",
+            number
+        );
+        NodeId::new(number)
+    }
+
+    /// Id of the route with the given index
+    #[inline]
+    pub fn route_id(&self, index: usize) -> RouteId {
+        assert!(index < self.routes.len(), "Unknown route {}", index);
+        RouteId::new(index)
+    }
+
+    /// All the nodes, customers and depots
+    pub fn nodes_mut(&mut self) -> impl Iterator<Item = &mut LinkNode> {
+        self.nodes.iter_mut()
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn num_routes(&self) -> usize {
+        self.routes.len()
+    }
+
+    /// Ids of all the routes
+    pub fn route_ids(&self) -> impl Iterator<Item = RouteId> {
+        (0..self.routes.len()).map(RouteId::new)
+    }
+
+    /// Customers visited by the route in order
+    pub fn route_customers(&self, route: RouteId) -> Vec<usize> {
+        let mut customers = Vec::with_capacity(self[route].num_customers);
+        let mut node = self[self[route].start_depot].successor;
+        while !node.is_none() && !self[node].is_depot() {
+            customers.push(self[node].number);
+            node = self[node].successor;
+        }
+        customers
+    }
+
+    /// Links together two nodes
+    #[inline]
+    pub fn link(&mut self, node_one: NodeId, node_two: NodeId) {
+        self[node_one].successor = node_two;
+        self[node_two].predecessor = node_one;
+    }
+
+    /// Insert `node_one` directly after `node_two`
+    #[inline]
+    pub fn insert_after(&mut self, node_one: NodeId, node_two: NodeId) {
+        let node_one_prev = self[node_one].predecessor;
+        let node_one_next = self[node_one].successor;
+        let node_two_next = self[node_two].successor;
+        self.link(node_one_prev, node_one_next);
+        self.link(node_two, node_one);
+        self.link(node_one, node_two_next);
+    }
+
+    /// Reverse the sequence going forward from `from_node`
+    pub fn forward_reverse(
+        &mut self,
+        mut from_node: NodeId,
+        to_node: NodeId,
+        new_first_node: NodeId,
+    ) {
+        let mut node = self[from_node].successor;
+        while !node.is_none() {
+            let next_node = self[node].successor;
+
+            // If at first node and we have a new first node
+            if next_node.is_none() && !new_first_node.is_none() {
+                self.link(new_first_node, from_node);
+            } else {
+                self.link(node, from_node);
+            }
+            if !to_node.is_none() && self[node].number == self[to_node].number {
+                break;
+            }
+            from_node = node;
+            node = next_node;
+        }
+    }
+
+    /// Reverse the sequence going backward from `from_node`
+    pub fn backward_reverse(
+        &mut self,
+        mut from_node: NodeId,
+        to_node: NodeId,
+        new_last_node: NodeId,
+    ) {
+        let mut node = self[from_node].predecessor;
+        while !node.is_none() {
+            let next_node = self[node].predecessor;
+
+            // If at last node and we have a new last node
+            if next_node.is_none() && !new_last_node.is_none() {
+                self.link(from_node, new_last_node);
+            } else {
+                self.link(from_node, node);
+            }
+            if !to_node.is_none() && self[node].number == self[to_node].number {
+                break;
+            }
+            from_node = node;
+            node = next_node;
+        }
+    }
+
+    #[inline]
+    pub fn replace_end_depot(&mut self, mut from_node: NodeId, end_depot: NodeId) {
+        let mut next_node = self[from_node].successor;
+        while !next_node.is_none() {
+            // Update last depot when at last node
+            if self[next_node].successor.is_none() {
+                self.link(from_node, end_depot);
             }
+            from_node = next_node;
+            next_node = self[next_node].successor;
+        }
+    }
+}
+
+impl Index<NodeId> for LinkArena {
+    type Output = LinkNode;
+
+    #[inline]
+    fn index(&self, id: NodeId) -> &LinkNode {
+        &self.nodes[id.index()]
+    }
+}
+
+impl IndexMut<NodeId> for LinkArena {
+    #[inline]
+    fn index_mut(&mut self, id: NodeId) -> &mut LinkNode {
+        &mut self.nodes[id.index()]
+    }
+}
+
+impl Index<RouteId> for LinkArena {
+    type Output = LinkRoute;
+
+    #[inline]
+    fn index(&self, id: RouteId) -> &LinkRoute {
+        &self.routes[id.index()]
+    }
+}
+
+impl IndexMut<RouteId> for LinkArena {
+    #[inline]
+    fn index_mut(&mut self, id: RouteId) -> &mut LinkRoute {
+        &mut self.routes[id.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arena_with_route(customers: &[usize]) -> LinkArena {
+        let mut arena = LinkArena::new(std::iter::repeat(0).take(8), 1);
+        let route = &arena[arena.route_id(0)];
+        let (start_depot, end_depot) = (route.start_depot, route.end_depot);
+        let mut prev = start_depot;
+        for &customer in customers {
+            let node = arena.customer(customer);
+            arena.link(prev, node);
+            prev = node;
         }
-        write!(f, "{:?}", customers)
+        arena.link(prev, end_depot);
+        arena
+    }
+
+    #[test]
+    fn insert_and_reverse() {
+        let mut arena = arena_with_route(&[1, 2, 3, 4, 5]);
+        let route = arena.route_id(0);
+
+        arena.insert_after(arena.customer(1), arena.customer(3));
+        assert_eq!(vec![2, 3, 1, 4, 5], arena.route_customers(route));
+
+        // Reverse (3, 1, 4) into (4, 1, 3)
+        let (two, three, four, five) = (
+            arena.customer(2),
+            arena.customer(3),
+            arena.customer(4),
+            arena.customer(5),
+        );
+        arena.backward_reverse(four, three, NodeId::NONE);
+        arena.link(two, four);
+        arena.link(three, five);
+        assert_eq!(vec![2, 4, 1, 3, 5], arena.route_customers(route));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unknown_customer() {
+        let arena = arena_with_route(&[1, 2]);
+        arena.customer(8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn missing_node_is_out_of_bounds() {
+        let arena = arena_with_route(&[1, 2]);
+        let _ = &arena[NodeId::NONE];
     }
 }
@@ -1,21 +1,21 @@
 use std::cmp::max;
 use std::collections::HashSet;
-use std::ptr;
 
 use ahash::RandomState;
 
-use crate::models::{CorrelationMatrix, Matrix};
+use crate::models::{CircleSector, CorrelationMatrix, Matrix};
 use crate::solver::evaluate::route_cost;
 use crate::solver::genetic::Individual;
-use crate::solver::improvement::moves::{Moves, SwapStar};
-use crate::solver::improvement::{InsertLocation, LinkNode, LinkRoute, ThreeBestInserts};
+use crate::solver::improvement::moves::{Move, Moves, SwapStar};
+use crate::solver::improvement::{
+    InsertLocation, LinkArena, NodeId, NodeView, RouteId, RouteView, ThreeBestInserts,
+};
 use crate::solver::Context;
 use crate::utils::FloatCompare;
 
 pub struct LocalSearch {
-    pub ctx: &'static Context,
-
-    pub routes: Vec<LinkRoute>,
+    // Nodes and routes used in the local search
+    pub(crate) arena: LinkArena,
     pub customers: Vec<usize>,
 
     pub correlation: CorrelationMatrix,
@@ -27,11 +27,6 @@ pub struct LocalSearch {
     // Store the three best insert locations for each node and route. Used by swap start move.
     pub best_inserts: Matrix<ThreeBestInserts>,
 
-    // Nodes used in the local search
-    pub nodes: Vec<LinkNode>,
-    pub start_depots: Vec<LinkNode>,
-    pub end_depots: Vec<LinkNode>,
-
     // Indices to empty routes
     pub empty_routes: HashSet<usize, RandomState>,
 
@@ -39,156 +34,212 @@ pub struct LocalSearch {
     pub penalty_capacity: f64,
 }
 
+/// Builder for the `LocalSearch`, which can add user-defined moves to the moves enabled in the
+/// config.
+///
+/// To use the moves in the solver, replace the local search of the genetic algorithm:
+///
+/// ```ignore
+/// let mut ga = GeneticAlgorithm::new(&ctx);
+/// ga.ls = LocalSearch::builder(&ctx)
+///     .neighbor_move(Box::new(MyMove))
+///     .build();
+/// ```
+pub struct LocalSearchBuilder<'a> {
+    ctx: &'a Context,
+    penalty_multiplier: f64,
+    config_moves: bool,
+    moves: Moves,
+}
+
+impl<'a> LocalSearchBuilder<'a> {
+    pub fn new(ctx: &'a Context) -> Self {
+        Self {
+            ctx,
+            penalty_multiplier: 1.0,
+            config_moves: true,
+            moves: Moves::default(),
+        }
+    }
+
+    pub fn penalty_multiplier(mut self, penalty_multiplier: f64) -> Self {
+        self.penalty_multiplier = penalty_multiplier;
+        self
+    }
+
+    /// Whether to include the moves enabled in the config, which are tried before the
+    /// user-defined moves
+    pub fn config_moves(mut self, config_moves: bool) -> Self {
+        self.config_moves = config_moves;
+        self
+    }
+
+    /// Move tried for each customer `u` and each `v` among its correlated customers
+    pub fn neighbor_move(mut self, m: Box<dyn Move>) -> Self {
+        self.moves.neighbor.push(m);
+        self
+    }
+
+    /// Move tried when `v` is the first customer of its route, after the neighbor moves failed.
+    /// The move gets the customer `v`, whose predecessor is the start depot of its route.
+    pub fn depot_move(mut self, m: Box<dyn Move>) -> Self {
+        self.moves.depot.push(m);
+        self
+    }
+
+    /// Move tried with `v` as the start depot of an empty route
+    pub fn empty_route_move(mut self, m: Box<dyn Move>) -> Self {
+        self.moves.empty_route.push(m);
+        self
+    }
+
+    pub fn build(self) -> LocalSearch {
+        let ctx = self.ctx;
+        let num_vehicles = ctx.config.borrow().num_vehicles as usize;
+
+        let mut moves = match self.config_moves {
+            true => Moves::new(ctx),
+            false => Moves::default(),
+        };
+        moves.neighbor.extend(self.moves.neighbor);
+        moves.depot.extend(self.moves.depot);
+        moves.empty_route.extend(self.moves.empty_route);
+
+        // Create all the nodes and the routes
+        let arena = LinkArena::new(
+            (0..ctx.problem.dim()).map(|index| ctx.problem.get_angle(index)),
+            num_vehicles,
+        );
+        let customers: Vec<usize> = (1..ctx.problem.dim()).collect();
+
+        LocalSearch {
+            moves,
+            arena,
+            customers,
+            best_inserts: Matrix::init(ThreeBestInserts::new(), num_vehicles, ctx.problem.dim()),
+            granularity: ctx.config.borrow().local_search_granularity as usize,
+            correlation: ctx.matrix_provider.correlation.clone(),
+            move_count: 0,
+            empty_routes: HashSet::with_capacity_and_hasher(
+                num_vehicles,
+                ctx.random.random_state(),
+            ),
+            penalty_capacity: ctx.config.borrow().penalty_capacity * self.penalty_multiplier,
+        }
+    }
+}
+
 impl LocalSearch {
     pub fn new(ctx: &Context, penalty_multiplier: f64) -> Self {
-        unsafe {
-            // Create all the nodes
-            let nodes: Vec<LinkNode> = ctx
-                .problem
-                .nodes
-                .iter()
-                .enumerate()
-                .map(|(index, _)| LinkNode::new(index, ctx.problem.get_angle(index)))
-                .collect();
-
-            let customers: Vec<usize> = (1..ctx.problem.dim()).collect();
-
-            // Create nodes for the depots
-            let num_vehicles = ctx.config.borrow().num_vehicles as usize;
-
-            // Create depots and routes
-            let mut start_depots = Vec::with_capacity(num_vehicles);
-            let mut end_depots = Vec::with_capacity(num_vehicles);
-            let mut routes = Vec::with_capacity(num_vehicles);
-            for route_number in 0..num_vehicles {
-                let start_depot = LinkNode::new(0, 0);
-                let end_depot = LinkNode::new(0, 0);
-                start_depots.push(start_depot);
-                end_depots.push(end_depot);
-                let start_depot_ptr = start_depots.get_unchecked_mut(route_number) as *mut LinkNode;
-                let end_depot_ptr = end_depots.get_unchecked_mut(route_number) as *mut LinkNode;
-                routes.push(LinkRoute::new(route_number, start_depot_ptr, end_depot_ptr));
-            }
+        Self::builder(ctx)
+            .penalty_multiplier(penalty_multiplier)
+            .build()
+    }
 
-            Self {
-                ctx: &*(ctx as *const Context),
-                moves: Moves::new(ctx),
-                nodes,
-                customers,
-                best_inserts: Matrix::init(
-                    ThreeBestInserts::new(),
-                    num_vehicles,
-                    ctx.problem.dim(),
-                ),
-                granularity: ctx.config.borrow().local_search_granularity as usize,
-                correlation: ctx.matrix_provider.correlation.clone(),
-                routes: routes,
-                move_count: 0,
-                empty_routes: HashSet::with_capacity_and_hasher(
-                    num_vehicles,
-                    ctx.random.random_state(),
-                ),
-                start_depots: start_depots,
-                end_depots: end_depots,
-                penalty_capacity: ctx.config.borrow().penalty_capacity * penalty_multiplier,
-            }
-        }
+    pub fn builder(ctx: &Context) -> LocalSearchBuilder<'_> {
+        LocalSearchBuilder::new(ctx)
     }
 
-    pub fn update_penalty(&mut self, penalty_multiplier: f64) {
-        self.penalty_capacity = self.ctx.config.borrow().penalty_capacity * penalty_multiplier;
+    pub fn update_penalty(&mut self, ctx: &Context, penalty_multiplier: f64) {
+        let penalty_capacity = ctx.config.borrow().penalty_capacity;
+        self.penalty_capacity = penalty_capacity * penalty_multiplier;
     }
 
-    pub fn load_individual(&mut self, individual: &Individual) {
-        unsafe {
-            for (route_index, route) in individual.phenotype.iter().enumerate() {
-                // Start with the depot as the prev node
-                let mut prev_node = &mut self.start_depots[route_index] as *mut LinkNode;
+    pub fn load_individual(&mut self, ctx: &Context, individual: &Individual) {
+        for (route_index, route) in individual.phenotype.iter().enumerate() {
+            let route_id = self.arena.route_id(route_index);
 
-                // Link up all nodes
-                for &node_index in route.iter() {
-                    let node = &mut self.nodes[node_index] as *mut LinkNode;
-                    LinkNode::link_nodes(prev_node, node);
-                    prev_node = node;
-                }
+            // Start with the depot as the prev node
+            let mut prev_node = self.arena[route_id].start_depot;
+
+            // Link up all nodes
+            for &node_index in route.iter() {
+                let node = self.arena.customer(node_index);
+                self.arena.link(prev_node, node);
+                prev_node = node;
+            }
 
-                // Link the prev node to the end depot
-                let depot_end = &mut self.end_depots[route_index] as *mut LinkNode;
-                LinkNode::link_nodes(prev_node, depot_end);
+            // Link the prev node to the end depot
+            let depot_end = self.arena[route_id].end_depot;
+            self.arena.link(prev_node, depot_end);
 
-                let route = &mut self.routes[route_index] as *mut LinkRoute;
-                (*route).last_tested_swap_star = -1;
-                for node_number in 0..self.nodes.len() {
-                    let best_insert = self.best_inserts.get_mut(route_index, node_number);
-                    best_insert.reset();
-                    best_insert.last_calculated = -1;
-                }
-                self.update_route(route);
+            self.arena[route_id].last_tested_swap_star = -1;
+            for node_number in 0..self.best_inserts.cols() {
+                let best_insert = self.best_inserts.get_mut(route_index, node_number);
+                best_insert.reset();
+                best_insert.last_calculated = -1;
             }
+            self.update_route(ctx, route_id);
         }
     }
 
     pub fn reset(&mut self) {
         self.move_count = 0;
-        for node in self.nodes.iter_mut() {
+        for node in self.arena.nodes_mut() {
             node.last_tested = -1;
         }
     }
 
     pub fn run(&mut self, ctx: &Context, individual: &mut Individual, penalty_multiplier: f64) {
-        unsafe {
-            self.ctx = &*(ctx as *const Context);
-            self.reset();
-            self.update_penalty(penalty_multiplier);
-            self.load_individual(individual);
-            self.search();
+        self.reset();
+        self.update_penalty(ctx, penalty_multiplier);
+        self.load_individual(ctx, individual);
+        self.search(ctx);
+        self.update_individual(ctx, individual);
+
+        // Optimize the order within each route
+        if ctx.config.borrow().tsp_after_local_search {
+            ctx.route_optimizer.borrow_mut().run(ctx, individual);
         }
-        self.update_individual(individual);
     }
 
-    unsafe fn search(&mut self) {
+    fn search(&mut self, ctx: &Context) {
+        // The moves borrow the whole local search, so the moves themselves are moved out during
+        // the search. The customers and the correlated customers are read by index, such that
+        // they stay readable by the moves.
+        let moves = std::mem::take(&mut self.moves);
+
         let mut loop_count = 0;
         let mut improvement = true;
-        let moves = &*{ &self.moves as *const Moves };
         while improvement {
             improvement = false;
             // Loop over all customers in random order
-            self.ctx.random.shuffle(self.customers.as_mut_slice());
-            let customers = &*{ &self.customers as *const Vec<usize> };
-            for u_index in customers {
+            ctx.random.shuffle(self.customers.as_mut_slice());
+            for customer in 0..self.customers.len() {
+                let u_index = self.customers[customer];
                 // Get all correlated customers in random order
-                let cor = &mut *{
-                    self.correlation.top_slice_mut(*u_index, self.granularity) as *mut [usize]
-                };
-                if self.ctx.random.range_usize(0, self.granularity) == 0 {
-                    self.ctx
-                        .random
-                        .shuffle(self.correlation.top_slice_mut(*u_index, self.granularity));
+                if ctx.random.range_usize(0, self.granularity) == 0 {
+                    ctx.random
+                        .shuffle(self.correlation.top_slice_mut(u_index, self.granularity));
                 }
 
-                let u = &mut self.nodes[*u_index] as *mut LinkNode;
-                let mut route_u = (*u).route;
+                let u = self.arena.customer(u_index);
+                let mut route_u = self.arena[u].route;
 
                 // Update timestamp for node u
-                let last_test_u = (*u).last_tested;
-                (*u).last_tested = self.move_count;
+                let last_test_u = self.arena[u].last_tested;
+                self.arena[u].last_tested = self.move_count;
 
                 // Iterate over correlated nodes
-                'v_loop: for &v_index in cor.iter() {
-                    let v = &mut self.nodes[v_index] as *mut LinkNode;
-                    let route_v = (*v).route;
+                'v_loop: for correlated in 0..self.granularity {
+                    let v_index = self.correlation.top_slice(u_index, self.granularity)[correlated];
+                    let v = self.arena.customer(v_index);
+                    let route_v = self.arena[v].route;
 
                     // Only try moves if one of the routes is modified since last time
                     if loop_count == 0
-                        || max((*route_u).last_modified, (*route_v).last_modified) > last_test_u
+                        || max(
+                            self.arena[route_u].last_modified,
+                            self.arena[route_v].last_modified,
+                        ) > last_test_u
                     {
                         // First, all the moves for the pair of customers are attempted
                         for m in moves.neighbor.iter() {
-                            let delta = m.delta(&self, u, v);
+                            let delta = m.delta(ctx, self, u, v);
                             if delta.approx_lt(0.0) {
                                 self.move_count += 1;
-                                m.perform(self, u, v);
-                                route_u = (*u).route;
+                                m.perform(ctx, self, u, v);
+                                route_u = self.arena[u].route;
                                 improvement = true;
                                 continue 'v_loop;
                             }
@@ -196,14 +247,14 @@ impl LocalSearch {
 
                         // If none of the moves above are successful, we attempt moves where
                         // `u` is located directly after a depot
-                        let v_pred = (*v).predecessor;
-                        if (*v_pred).is_depot() {
+                        let v_pred = self.arena[v].predecessor;
+                        if self.arena[v_pred].is_depot() {
                             for m in moves.depot.iter() {
-                                let delta = m.delta(&self, u, v);
+                                let delta = m.delta(ctx, self, u, v);
                                 if delta.approx_lt(0.0) {
                                     self.move_count += 1;
-                                    m.perform(self, u, v);
-                                    route_u = (*u).route;
+                                    m.perform(ctx, self, u, v);
+                                    route_u = self.arena[u].route;
                                     improvement = true;
                                     continue 'v_loop;
                                 }
@@ -219,13 +270,12 @@ impl LocalSearch {
                 if loop_count > 0 && !self.empty_routes.is_empty() {
                     let empty_route_index =
                         *self.empty_routes.iter().next().expect("No empty route");
-                    let route_v = &mut self.routes[empty_route_index] as *mut LinkRoute;
-                    let v = (*route_v).start_depot;
+                    let v = self.arena[self.arena.route_id(empty_route_index)].start_depot;
                     for m in moves.empty_route.iter() {
-                        let delta = m.delta(&self, u, v);
+                        let delta = m.delta(ctx, self, u, v);
                         if delta.approx_lt(0.0) {
                             self.move_count += 1;
-                            m.perform(self, u, v);
+                            m.perform(ctx, self, u, v);
                             improvement = true;
                             break;
                         }
@@ -234,237 +284,307 @@ impl LocalSearch {
             }
 
             // Finally the SWAP* move is performed for all pairs of routes with overlapping circle sectors
-            if self.ctx.config.borrow().swap_star {
-                for r1_num in 0..self.routes.len() {
-                    let r1_ptr = &mut self.routes[r1_num] as *mut LinkRoute;
-                    let last_tested_u = (*r1_ptr).last_tested_swap_star;
-                    (*r1_ptr).last_tested_swap_star = self.move_count;
-                    for r2_num in (r1_num + 1)..self.routes.len() {
-                        let r2_ptr = &mut self.routes[r2_num] as *mut LinkRoute;
-                        if !(*r1_ptr).is_empty()
-                            && !(*r2_ptr).is_empty()
-                            && r1_num < r2_num
+            if ctx.config.borrow().swap_star {
+                for r1_num in 0..self.arena.num_routes() {
+                    let r1 = self.arena.route_id(r1_num);
+                    let last_tested_u = self.arena[r1].last_tested_swap_star;
+                    self.arena[r1].last_tested_swap_star = self.move_count;
+                    for r2_num in (r1_num + 1)..self.arena.num_routes() {
+                        let r2 = self.arena.route_id(r2_num);
+                        let (route_one, route_two) = (&self.arena[r1], &self.arena[r2]);
+                        if !route_one.is_empty()
+                            && !route_two.is_empty()
                             && (loop_count == 0 || {
-                                (*r1_ptr)
+                                route_one
                                     .last_tested_swap_star
-                                    .max((*r2_ptr).last_tested_swap_star)
+                                    .max(route_two.last_tested_swap_star)
                                     > last_tested_u
                             })
+                            && route_one.sector.overlaps(&route_two.sector)
+                            && SwapStar::run(ctx, self, r1, r2)
                         {
-                            if (*r1_ptr).sector.overlaps(&(*r2_ptr).sector) {
-                                if SwapStar::run(self, r1_ptr, r2_ptr) {
-                                    improvement = true;
-                                }
-                            }
+                            improvement = true;
                         }
                     }
                 }
                 loop_count += 1;
             }
         }
+
+        self.moves = moves;
     }
 
-    fn update_individual(&self, individual: &mut Individual) {
+    fn update_individual(&self, ctx: &Context, individual: &mut Individual) {
         // Clear the genotype
         individual.genotype.clear();
 
-        unsafe {
-            // Loop over the routes and update the genotype and the phenotype
-            for (route_number, route) in self.routes.iter().enumerate() {
-                let mut phenotype_nodes: Vec<usize> = Vec::with_capacity(route.num_customers);
-                let mut next_node = route.start_depot;
-                while !next_node.is_null() {
-                    let node = &*next_node;
-                    if !node.is_depot() {
-                        phenotype_nodes.push(node.number);
-                    }
-                    next_node = node.successor;
-                }
-                individual.genotype.extend(phenotype_nodes.iter());
-                individual.phenotype[route_number] = phenotype_nodes;
-            }
-
-            // Reevaluate the individual
-            individual.sort_routes(self.ctx);
-            individual.evaluate(self.ctx);
+        // Loop over the routes and update the genotype and the phenotype
+        for route in self.arena.route_ids() {
+            let phenotype_nodes = self.arena.route_customers(route);
+            individual.genotype.extend(phenotype_nodes.iter());
+            individual.phenotype[route.index()] = phenotype_nodes;
         }
+
+        // Reevaluate the individual
+        individual.sort_routes(ctx);
+        individual.evaluate(ctx);
     }
 
-    // Used to update the route after a move is performed
-    pub fn update_route(&mut self, route_ptr: *mut LinkRoute) {
-        let problem = &self.ctx.problem;
-        let distance_matrix = &self.ctx.matrix_provider.distance;
-        unsafe {
-            // Variables to be calculated for the route
-            let mut distance = 0.0;
-            let mut load = 0.0;
-            let mut num_customers = 0;
-
-            // Start with the depot as the first node
-            let mut prev_node_ptr = (*route_ptr).start_depot;
-
-            // Update information for the start depot
-            (*prev_node_ptr).route = route_ptr;
-            (*prev_node_ptr).position = 0;
-
-            // Reset the route circle sector
-            (*route_ptr).sector.reset();
-
-            // Go to the next node
-            let mut node_ptr = (*prev_node_ptr).successor;
-            let mut position = 1;
-
-            // Loop through all nodes in route
-            while !node_ptr.is_null() {
-                // Add distance and load for the node
-                distance += distance_matrix.get((*prev_node_ptr).number, (*node_ptr).number);
-                load += problem.nodes[(*node_ptr).number].demand;
-
-                // Update circle sector for customers
-                if !(*node_ptr).is_depot() {
-                    (*route_ptr).sector.extend((*node_ptr).angle);
-                    num_customers += 1;
-                }
+    /// View of a node for computing the delta of a move
+    pub fn node(&self, id: NodeId) -> NodeView<'_> {
+        assert!(self.arena.contains_node(id), "Unknown node {:?}", id);
+        NodeView::new(&self.arena, id)
+    }
 
-                // Update information on the node
-                (*node_ptr).cum_distance = distance;
-                (*node_ptr).cum_load = load;
-                (*node_ptr).route = route_ptr;
-                (*node_ptr).position = position;
+    /// View of a route for computing the delta of a move
+    pub fn route(&self, id: RouteId) -> RouteView<'_> {
+        assert!(self.arena.contains_route(id), "Unknown route {:?}", id);
+        RouteView::new(&self.arena, id)
+    }
 
-                // Increment position and pointers
-                position += 1;
-                prev_node_ptr = node_ptr;
-                node_ptr = (*node_ptr).successor;
-            }
+    /// Distance between the customers or depots of two nodes
+    pub fn distance(&self, ctx: &Context, from: NodeId, to: NodeId) -> f64 {
+        ctx.matrix_provider
+            .distance
+            .get(self.node(from).number(), self.node(to).number())
+    }
 
-            // Update information on the route
-            (*route_ptr).distance = distance;
-            (*route_ptr).load = load;
-            (*route_ptr).overload = load - problem.vehicle.cap;
-            (*route_ptr).last_modified = self.move_count;
-            (*route_ptr).num_customers = num_customers;
-
-            // Ensure predecessor of start_depot and successor of end_depot are null
-            self.start_depots[(*route_ptr).index].predecessor = ptr::null_mut();
-            self.end_depots[(*route_ptr).index].successor = ptr::null_mut();
-
-            // Update route cost
-            (*route_ptr).cost = route_cost(
-                (*route_ptr).distance,
-                (*route_ptr).overload,
-                self.penalty_capacity,
+    /// Demand of the customer of a node
+    pub fn demand(&self, ctx: &Context, id: NodeId) -> f64 {
+        ctx.problem.nodes[self.node(id).number()].demand
+    }
+
+    /// Cost of a route with the given distance and overload, penalized like the routes of the
+    /// local search
+    pub fn penalized_cost(&self, distance: f64, overload: f64) -> f64 {
+        route_cost(distance, overload, self.penalty_capacity)
+    }
+
+    /// Make `to` the successor of `from`. The routes must be updated with `update_route` once the
+    /// move is complete.
+    pub fn link(&mut self, from: NodeId, to: NodeId) {
+        assert!(
+            self.arena.contains_node(from) && self.arena.contains_node(to),
+            "Unknown nodes {:?}, {:?}",
+            from,
+            to
+        );
+        self.arena.link(from, to);
+    }
+
+    /// Move `node` directly after `after`. The routes must be updated with `update_route` once the
+    /// move is complete.
+    pub fn insert_after(&mut self, node: NodeId, after: NodeId) {
+        assert!(
+            self.arena.contains_node(node) && self.arena.contains_node(after),
+            "Unknown nodes {:?}, {:?}",
+            node,
+            after
+        );
+        assert!(
+            !self.arena[node].predecessor.is_none() && !self.arena[node].successor.is_none(),
+            "Cannot move depot {:?}",
+            node
+        );
+        self.arena.insert_after(node, after);
+    }
+
+    /// Update the cumulative data, the cost and the sector of a route after a move is performed
+    pub fn update_route(&mut self, ctx: &Context, route_id: RouteId) {
+        assert!(
+            self.arena.contains_route(route_id),
+            "Unknown route {:?}",
+            route_id
+        );
+        let problem = &ctx.problem;
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &mut self.arena;
+
+        // Variables to be calculated for the route
+        let mut distance = 0.0;
+        let mut load = 0.0;
+        let mut num_customers = 0;
+        let mut sector = CircleSector::new();
+
+        // Start with the depot as the first node
+        let start_depot = arena[route_id].start_depot;
+        let end_depot = arena[route_id].end_depot;
+
+        // Update information for the start depot
+        let depot = &mut arena[start_depot];
+        depot.route = route_id;
+        depot.position = 0;
+        depot.predecessor = NodeId::NONE;
+        let mut prev_number = depot.number;
+
+        // Go to the next node
+        let mut node_id = depot.successor;
+        let mut position = 1;
+
+        // Loop through all nodes in route, which must lead to the end depot of the route without
+        // passing another depot or visiting a node twice
+        loop {
+            assert!(
+                !node_id.is_none() && position < arena.num_nodes(),
+                "Route {:?} does not lead to its end depot",
+                route_id
+            );
+            let node = &mut arena[node_id];
+            assert!(
+                !node.is_depot() || node_id == end_depot,
+                "Route {:?} runs into another route",
+                route_id
             );
 
-            // Update set of empty routes
-            if (*route_ptr).is_empty() {
-                self.empty_routes.insert((*route_ptr).index);
-            } else {
-                self.empty_routes.remove(&(*route_ptr).index);
+            // Add distance and load for the node
+            distance += distance_matrix.get(prev_number, node.number);
+            load += problem.nodes[node.number].demand;
+
+            // Update circle sector for customers
+            if !node.is_depot() {
+                sector.extend(node.angle);
+                num_customers += 1;
+            }
+
+            // Update information on the node
+            node.cum_distance = distance;
+            node.cum_load = load;
+            node.route = route_id;
+            node.position = position;
+
+            // Increment position and go to the next node, where the end depot has no successor
+            if node_id == end_depot {
+                break;
             }
+            position += 1;
+            prev_number = node.number;
+            node_id = node.successor;
+        }
+        arena[end_depot].successor = NodeId::NONE;
+
+        // Update information on the route
+        let route = &mut arena[route_id];
+        route.sector = sector;
+        route.distance = distance;
+        route.load = load;
+        route.overload = load - problem.vehicle.cap;
+        route.last_modified = self.move_count;
+        route.num_customers = num_customers;
+
+        // Update route cost
+        route.cost = route_cost(route.distance, route.overload, self.penalty_capacity);
+
+        // Update set of empty routes
+        if route.is_empty() {
+            self.empty_routes.insert(route.index);
+        } else {
+            self.empty_routes.remove(&route.index);
         }
     }
 
     /// Used to preprocess the three best insertion costs for all nodes in a pair of routes
-    pub unsafe fn preprocess_insertions(&mut self, r1_ptr: *mut LinkRoute, r2_ptr: *mut LinkRoute) {
-        let distance_matrix = &self.ctx.matrix_provider.distance;
-        let r1 = &*r1_ptr;
-        let r2 = &*r2_ptr;
+    pub(crate) fn preprocess_insertions(&mut self, ctx: &Context, r1: RouteId, r2: RouteId) {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &mut self.arena;
+        let r2_index = arena[r2].index;
+        let r2_start_depot = arena[r2].start_depot;
+        let r2_last_modified = arena[r2].last_modified;
 
         // Start with the first customer in route 1
-        let mut u_ptr = (*r1.start_depot).successor;
+        let mut u_id = arena[arena[r1].start_depot].successor;
 
         // Loop over all customers in route 1
-        while !(*u_ptr).is_depot() {
-            // Derefence pointers
-            let u = &*u_ptr;
-            let u_prev = &*u.predecessor;
-            let x = &*u.successor;
+        while !arena[u_id].is_depot() {
+            let u = &arena[u_id];
+            let u_number = u.number;
+            let u_prev = &arena[u.predecessor];
+            let x = &arena[u.successor];
+            let u_successor = u.successor;
 
             // Calculate and set change in objective when removing u
             let delta_removal = distance_matrix.get(u_prev.number, x.number)
-                - distance_matrix.get(u_prev.number, u.number)
-                - distance_matrix.get(u.number, x.number);
-            (*u_ptr).delta_removal = delta_removal;
+                - distance_matrix.get(u_prev.number, u_number)
+                - distance_matrix.get(u_number, x.number);
+            arena[u_id].delta_removal = delta_removal;
 
             // Only recalculate insertion cost into route 2 if the route has changed since last calculation
-            if r2.last_modified > self.best_inserts.get(r2.index, u.number).last_calculated {
+            if r2_last_modified > self.best_inserts.get(r2_index, u_number).last_calculated {
+                let best_insert = self.best_inserts.get_mut(r2_index, u_number);
+
                 // Reset best inserts of u into route 2
-                self.best_inserts.get_mut(r2.index, u.number).reset();
-                self.best_inserts
-                    .get_mut(r2.index, u.number)
-                    .last_calculated = self.move_count;
+                best_insert.reset();
+                best_insert.last_calculated = self.move_count;
 
                 // Start with first customer in the second route as v
-                let mut v_ptr = (*r2.start_depot).successor;
+                let mut v_id = arena[r2_start_depot].successor;
 
                 // Check cost of inserting node u between the start depot and the first node in route 2
-                let cost = distance_matrix.get(0, u.number)
-                    + distance_matrix.get(u.number, (*v_ptr).number)
-                    - distance_matrix.get(0, (*v_ptr).number);
-                self.best_inserts
-                    .get_mut(r2.index, u.number)
-                    .add(InsertLocation {
-                        cost,
-                        node: r2.start_depot,
-                    });
+                let cost = distance_matrix.get(0, u_number)
+                    + distance_matrix.get(u_number, arena[v_id].number)
+                    - distance_matrix.get(0, arena[v_id].number);
+                best_insert.add(InsertLocation {
+                    cost,
+                    node: r2_start_depot,
+                });
 
                 // Calculate insertion cost of u for the remaining positions in route 2
-                while !(*v_ptr).is_depot() {
-                    let v = &*v_ptr;
-                    let y = &*v.successor;
-                    let delta_insert = distance_matrix.get(v.number, u.number)
-                        + distance_matrix.get(u.number, y.number)
+                while !arena[v_id].is_depot() {
+                    let v = &arena[v_id];
+                    let y = &arena[v.successor];
+                    let delta_insert = distance_matrix.get(v.number, u_number)
+                        + distance_matrix.get(u_number, y.number)
                         - distance_matrix.get(v.number, y.number);
                     let cost = delta_insert;
 
-                    self.best_inserts
-                        .get_mut(r2.index, u.number)
-                        .add(InsertLocation { cost, node: v_ptr });
+                    best_insert.add(InsertLocation { cost, node: v_id });
 
-                    v_ptr = v.successor;
+                    v_id = v.successor;
                 }
             }
-            u_ptr = u.successor;
+            u_id = u_successor;
         }
     }
 
     /// Finds the cheapest insert location of u into the route of v,
     /// while v is removed at the same time
-    pub unsafe fn cheapest_insert_and_removal(
-        &mut self,
-        u_ptr: *mut LinkNode,
-        v_ptr: *mut LinkNode,
-    ) -> (*mut LinkNode, f64) {
-        // Derefence pointers and setup local variables
-        let u = &*u_ptr;
-        let v = &*v_ptr;
-        let r2 = &(*v.route);
-        let distance_matrix = &self.ctx.matrix_provider.distance;
+    pub(crate) fn cheapest_insert_and_removal(
+        &self,
+        ctx: &Context,
+        u_id: NodeId,
+        v_id: NodeId,
+    ) -> (NodeId, f64) {
+        // Setup local variables
+        let arena = &self.arena;
+        let u = &arena[u_id];
+        let v = &arena[v_id];
+        let r2 = &arena[v.route];
+        let distance_matrix = &ctx.matrix_provider.distance;
 
         // Start with the best insertion into route v.
-        let best_insertion = self.best_inserts.get_mut(r2.index, u.number);
+        let best_insertion = self.best_inserts.get(r2.index, u.number);
         let mut best_node = best_insertion.locations[0].node;
         let mut best_cost = best_insertion.locations[0].cost;
 
         // Found is true if the best insert position is neither directly before or after v.
         // If the best insert position involves v, the position is illegal when v is removed,
         // and thus we must use the second or third best insert position.
-        let mut found =
-            (*best_node).number != v.number && (*(*best_node).successor).number != v.number;
-        if !found && !best_insertion.locations[1].node.is_null() {
+        let is_away_from_v = |node: NodeId| {
+            arena[node].number != v.number && arena[arena[node].successor].number != v.number
+        };
+        let mut found = is_away_from_v(best_node);
+        if !found && !best_insertion.locations[1].node.is_none() {
             best_node = best_insertion.locations[1].node;
             best_cost = best_insertion.locations[1].cost;
-            found = (*best_node).number != v.number && (*(*best_node).successor).number != v.number;
-            if !found && !best_insertion.locations[2].node.is_null() {
+            found = is_away_from_v(best_node);
+            if !found && !best_insertion.locations[2].node.is_none() {
                 best_node = best_insertion.locations[2].node;
                 best_cost = best_insertion.locations[2].cost;
                 found = true;
             }
         }
 
-        let v_prev = &*(v.predecessor);
-        let y = &*(v.successor);
+        let v_prev = &arena[v.predecessor];
+        let y = &arena[v.successor];
 
         // Calculate the cost of inserting u in place of v, as
         // the best position already found is into route 2 while v is still present
@@ -483,22 +603,187 @@ impl LocalSearch {
     }
 }
 
-impl Drop for LocalSearch {
-    fn drop(&mut self) {
-        for node in self.nodes.iter_mut() {
-            node.route = ptr::null_mut();
-            node.predecessor = ptr::null_mut();
-            node.successor = ptr::null_mut();
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::test_context;
+
+    // Relocates `u` after `v`, using only the public interface of the local search
+    struct PublicRelocate;
+
+    impl Move for PublicRelocate {
+        fn move_name(&self) -> &'static str {
+            "PublicRelocate"
+        }
+
+        fn delta(&self, ctx: &Context, ls: &LocalSearch, u: NodeId, v: NodeId) -> f64 {
+            let (u_view, v_view) = (ls.node(u), ls.node(v));
+            let (u_prev, x) = (u_view.predecessor().unwrap(), u_view.successor().unwrap());
+            let y = v_view.successor().unwrap();
+            if y.id() == u {
+                return 0.0;
+            }
+            let (r1, r2) = (u_view.route().unwrap(), v_view.route().unwrap());
+            if r1.id() == r2.id() {
+                return 0.0;
+            }
+
+            let distance_one =
+                r1.distance() - ls.distance(ctx, u_prev.id(), u) - ls.distance(ctx, u, x.id())
+                    + ls.distance(ctx, u_prev.id(), x.id());
+            let distance_two = r2.distance() - ls.distance(ctx, v, y.id())
+                + ls.distance(ctx, v, u)
+                + ls.distance(ctx, u, y.id());
+            ls.penalized_cost(distance_one, r1.overload() - ls.demand(ctx, u))
+                + ls.penalized_cost(distance_two, r2.overload() + ls.demand(ctx, u))
+                - r1.cost()
+                - r2.cost()
         }
-        for node in self.start_depots.iter_mut() {
-            node.route = ptr::null_mut();
-            node.predecessor = ptr::null_mut();
-            node.successor = ptr::null_mut();
+
+        fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u: NodeId, v: NodeId) {
+            let r1 = ls.node(u).route().unwrap().id();
+            let r2 = ls.node(v).route().unwrap().id();
+            ls.insert_after(u, v);
+            ls.update_route(ctx, r1);
+            ls.update_route(ctx, r2);
         }
-        for node in self.end_depots.iter_mut() {
-            node.route = ptr::null_mut();
-            node.predecessor = ptr::null_mut();
-            node.successor = ptr::null_mut();
+    }
+
+    // Links `u` back to its predecessor, which turns the route into a cycle
+    struct CyclicLink;
+
+    impl Move for CyclicLink {
+        fn move_name(&self) -> &'static str {
+            "CyclicLink"
         }
+
+        fn delta(&self, _ctx: &Context, ls: &LocalSearch, u: NodeId, _v: NodeId) -> f64 {
+            match ls.node(u).predecessor() {
+                Some(prev) if !prev.is_depot() => -1.0,
+                _ => 0.0,
+            }
+        }
+
+        fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u: NodeId, _v: NodeId) {
+            let prev = ls.node(u).predecessor().unwrap().id();
+            let route = ls.node(u).route().unwrap().id();
+            ls.link(u, prev);
+            ls.update_route(ctx, route);
+        }
+    }
+
+    // Checks the nodes given to a depot move, and counts the calls
+    struct DepotCheck(Rc<Cell<usize>>);
+
+    impl Move for DepotCheck {
+        fn move_name(&self) -> &'static str {
+            "DepotCheck"
+        }
+
+        fn delta(&self, _ctx: &Context, ls: &LocalSearch, u: NodeId, v: NodeId) -> f64 {
+            let v_view = ls.node(v);
+            assert!(!ls.node(u).is_depot());
+            assert!(!v_view.is_depot());
+            assert_eq!(
+                v_view.route().unwrap().start_depot().id(),
+                v_view.predecessor().unwrap().id()
+            );
+            // The customers and correlated customers stay readable during the search
+            assert_eq!(6, ls.customers.len());
+            assert_eq!(5, ls.correlation.width());
+            self.0.set(self.0.get() + 1);
+            0.0
+        }
+
+        fn perform(&self, _ctx: &Context, _ls: &mut LocalSearch, _u: NodeId, _v: NodeId) {
+            unreachable!()
+        }
+    }
+
+    fn context() -> Context {
+        let coords = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (11.0, 0.0),
+            (0.0, 10.0),
+            (0.0, 11.0),
+            (-10.0, 0.0),
+            (-11.0, 0.0),
+        ];
+        let mut config = Config::default();
+        config.swap_star = false;
+        config.tsp_after_local_search = false;
+        test_context(&coords, &[1.0; 6], 3.0, config)
+    }
+
+    #[test]
+    fn user_defined_move() {
+        let ctx = context();
+        let mut ls = LocalSearch::builder(&ctx)
+            .config_moves(false)
+            .neighbor_move(Box::new(PublicRelocate))
+            .build();
+        assert_eq!(1, ls.moves.neighbor.len());
+        assert!(ls.moves.depot.is_empty());
+
+        let mut individual = Individual::new_random(&ctx, 0);
+        individual.phenotype[0] = vec![1, 3];
+        individual.phenotype[1] = vec![2, 5];
+        individual.phenotype[2] = vec![4, 6];
+        individual.evaluate(&ctx);
+        let initial_cost = individual.penalized_cost();
+
+        ls.run(&ctx, &mut individual, 1.0);
+        assert!(ls.move_count > 0);
+        assert!(individual.penalized_cost() < initial_cost);
+
+        let mut customers = individual.genotype.clone();
+        customers.sort_unstable();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], customers);
+        for route in individual.phenotype.iter() {
+            assert!(route.len() <= 3);
+        }
+    }
+
+    #[test]
+    fn depot_move_gets_first_customer() {
+        let ctx = context();
+        let calls = Rc::new(Cell::new(0));
+        let mut ls = LocalSearch::builder(&ctx)
+            .config_moves(false)
+            .depot_move(Box::new(DepotCheck(calls.clone())))
+            .build();
+
+        let mut individual = Individual::new_random(&ctx, 0);
+        individual.phenotype[0] = vec![1, 3];
+        individual.phenotype[1] = vec![2, 5];
+        individual.phenotype[2] = vec![4, 6];
+        individual.evaluate(&ctx);
+        ls.run(&ctx, &mut individual, 1.0);
+        assert!(calls.get() > 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not lead to its end depot")]
+    fn cyclic_route_is_rejected() {
+        let ctx = context();
+        let mut ls = LocalSearch::builder(&ctx)
+            .config_moves(false)
+            .neighbor_move(Box::new(CyclicLink))
+            .build();
+
+        let mut individual = Individual::new_random(&ctx, 0);
+        individual
+            .phenotype
+            .iter_mut()
+            .for_each(|route| route.clear());
+        individual.phenotype[0] = vec![1, 2, 3];
+        individual.phenotype[1] = vec![4, 5, 6];
+        individual.evaluate(&ctx);
+        ls.run(&ctx, &mut individual, 1.0);
     }
 }
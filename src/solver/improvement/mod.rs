@@ -7,8 +7,17 @@ pub use self::moves::*;
 mod linked_list;
 pub use self::linked_list::*;
 
+mod view;
+pub use self::view::*;
+
 mod preprocess;
 pub use self::preprocess::*;
 
+mod route_optimizer;
+pub use self::route_optimizer::*;
+
+mod route_elimination;
+pub use self::route_elimination::*;
+
 mod ruin_recreate;
 pub use self::ruin_recreate::*;
@@ -0,0 +1,284 @@
+use std::cell::Cell;
+
+use crate::solver::evaluate::route_cost;
+use crate::solver::improvement::{LocalSearch, Move, NodeId};
+use crate::solver::Context;
+
+/// Exchange of the segment (x, ..., a) starting after `u` with the segment (v, ..., b) between
+/// two routes, such that `u` is followed by `v`. Both segments have at most
+/// `cross_exchange_max_length` customers, and the best combination of lengths is used.
+#[derive(Default)]
+pub struct CrossExchange {
+    cache: ExchangeCache,
+}
+
+impl Move for CrossExchange {
+    fn move_name(&self) -> &'static str {
+        "CrossExchange"
+    }
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        self.cache.delta(ctx, ls, u_id, v_id, false)
+    }
+
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        self.cache.perform(ctx, ls, u_id, v_id, false);
+    }
+}
+
+/// Same as `CrossExchange`, but both segments are reversed when inserted (I-CROSS), such that
+/// `u` is followed by `b`, and `v_prev` is followed by `a`. Disabled for asymmetric distances.
+#[derive(Default)]
+pub struct CrossExchangeReverse {
+    cache: ExchangeCache,
+}
+
+impl Move for CrossExchangeReverse {
+    fn move_name(&self) -> &'static str {
+        "CrossExchangeReverse"
+    }
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        self.cache.delta(ctx, ls, u_id, v_id, true)
+    }
+
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        self.cache.perform(ctx, ls, u_id, v_id, true);
+    }
+}
+
+/// Segment ends found by the last evaluation, such that performing the move does not repeat the
+/// search over all combinations of segment lengths
+#[derive(Default)]
+struct ExchangeCache(Cell<Option<CachedExchange>>);
+
+#[derive(Clone, Copy, PartialEq)]
+struct ExchangeKey {
+    u_id: NodeId,
+    v_id: NodeId,
+    // Last modification of the routes of `u` and `v`
+    last_modified: (i32, i32),
+}
+
+#[derive(Clone, Copy)]
+struct CachedExchange {
+    key: ExchangeKey,
+    a_id: NodeId,
+    b_id: NodeId,
+}
+
+impl ExchangeKey {
+    fn new(ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> Self {
+        let r1 = &ls.arena[ls.arena[u_id].route];
+        let r2 = &ls.arena[ls.arena[v_id].route];
+        Self {
+            u_id,
+            v_id,
+            last_modified: (r1.last_modified, r2.last_modified),
+        }
+    }
+}
+
+impl ExchangeCache {
+    fn delta(
+        &self,
+        ctx: &Context,
+        ls: &LocalSearch,
+        u_id: NodeId,
+        v_id: NodeId,
+        reverse: bool,
+    ) -> f64 {
+        let (delta, a_id, b_id) = best_exchange(ctx, ls, u_id, v_id, reverse);
+        self.0.set(Some(CachedExchange {
+            key: ExchangeKey::new(ls, u_id, v_id),
+            a_id,
+            b_id,
+        }));
+        delta
+    }
+
+    fn perform(
+        &self,
+        ctx: &Context,
+        ls: &mut LocalSearch,
+        u_id: NodeId,
+        v_id: NodeId,
+        reverse: bool,
+    ) {
+        let (a_id, b_id) = match self.0.take() {
+            Some(cached) if cached.key == ExchangeKey::new(ls, u_id, v_id) => {
+                (cached.a_id, cached.b_id)
+            }
+            _ => {
+                let (_, a_id, b_id) = best_exchange(ctx, ls, u_id, v_id, reverse);
+                (a_id, b_id)
+            }
+        };
+        perform_exchange(ctx, ls, u_id, v_id, a_id, b_id, reverse);
+    }
+}
+
+/// Finds the segment lengths with the lowest delta cost. Returns the delta cost together with
+/// the last node `a` of the first segment and the last node `b` of the second segment.
+fn best_exchange(
+    ctx: &Context,
+    ls: &LocalSearch,
+    u_id: NodeId,
+    v_id: NodeId,
+    reverse: bool,
+) -> (f64, NodeId, NodeId) {
+    let distance_matrix = &ctx.matrix_provider.distance;
+    let arena = &ls.arena;
+    let max_length = ctx.config.borrow().cross_exchange_max_length as usize;
+
+    let mut best = (0.0, NodeId::NONE, NodeId::NONE);
+
+    let u = &arena[u_id];
+    let v = &arena[v_id];
+    let r1 = &arena[u.route];
+    let r2 = &arena[v.route];
+
+    // Only exchange segments between different routes
+    if r1.index == r2.index {
+        return best;
+    }
+
+    // The reversed segments are evaluated with their forward distances
+    if reverse && !distance_matrix.is_symmetric() {
+        return best;
+    }
+
+    let x = &arena[u.successor];
+    let v_prev = &arena[v.predecessor];
+    if x.is_depot() {
+        return best;
+    }
+
+    let old_cost = r1.cost + r2.cost;
+
+    // Extend the second segment (v, ..., b) one customer at a time
+    let mut b_id = v_id;
+    for length_two in 1..=max_length {
+        let b = &arena[b_id];
+        let b_next = &arena[b.successor];
+
+        // Distance and load of the second segment
+        let distance_segment_two = b.cum_distance - v.cum_distance;
+        let load_two = b.cum_load - v_prev.cum_load;
+
+        // Extend the first segment (x, ..., a) one customer at a time
+        let mut a_id = u.successor;
+        for length_one in 1..=max_length {
+            let a = &arena[a_id];
+            let a_next = &arena[a.successor];
+
+            // Reversing two single customers is the same as not reversing
+            if !(reverse && length_one == 1 && length_two == 1) {
+                // Distance and load of the first segment
+                let distance_segment_one = a.cum_distance - x.cum_distance;
+                let load_one = a.cum_load - u.cum_load;
+
+                let (distance_one, distance_two) = if reverse {
+                    (
+                        r1.distance
+                            - (a.cum_distance - u.cum_distance)
+                            - distance_matrix.get(a.number, a_next.number)
+                            + distance_matrix.get(u.number, b.number)
+                            + distance_segment_two
+                            + distance_matrix.get(v.number, a_next.number),
+                        r2.distance
+                            - (b.cum_distance - v_prev.cum_distance)
+                            - distance_matrix.get(b.number, b_next.number)
+                            + distance_matrix.get(v_prev.number, a.number)
+                            + distance_segment_one
+                            + distance_matrix.get(x.number, b_next.number),
+                    )
+                } else {
+                    (
+                        r1.distance
+                            - (a.cum_distance - u.cum_distance)
+                            - distance_matrix.get(a.number, a_next.number)
+                            + distance_matrix.get(u.number, v.number)
+                            + distance_segment_two
+                            + distance_matrix.get(b.number, a_next.number),
+                        r2.distance
+                            - (b.cum_distance - v_prev.cum_distance)
+                            - distance_matrix.get(b.number, b_next.number)
+                            + distance_matrix.get(v_prev.number, x.number)
+                            + distance_segment_one
+                            + distance_matrix.get(a.number, b_next.number),
+                    )
+                };
+
+                let overload_one = r1.overload - load_one + load_two;
+                let overload_two = r2.overload + load_one - load_two;
+
+                let new_cost = route_cost(distance_one, overload_one, ls.penalty_capacity)
+                    + route_cost(distance_two, overload_two, ls.penalty_capacity);
+
+                if new_cost - old_cost < best.0 {
+                    best = (new_cost - old_cost, a_id, b_id);
+                }
+            }
+
+            if a_next.is_depot() {
+                break;
+            }
+            a_id = a.successor;
+        }
+
+        if b_next.is_depot() {
+            break;
+        }
+        b_id = b.successor;
+    }
+
+    best
+}
+
+/// Performs the exchange of the segments (x, ..., a) and (v, ..., b)
+fn perform_exchange(
+    ctx: &Context,
+    ls: &mut LocalSearch,
+    u_id: NodeId,
+    v_id: NodeId,
+    a_id: NodeId,
+    b_id: NodeId,
+    reverse: bool,
+) {
+    let r1 = ls.arena[u_id].route;
+    let r2 = ls.arena[v_id].route;
+
+    let x_id = ls.arena[u_id].successor;
+    let a_next_id = ls.arena[a_id].successor;
+    let v_prev_id = ls.arena[v_id].predecessor;
+    let b_next_id = ls.arena[b_id].successor;
+
+    if reverse {
+        // Reverse the segments in place, such that (a, ..., x) and (b, ..., v)
+        if a_id != x_id {
+            ls.arena.backward_reverse(a_id, x_id, NodeId::NONE);
+        }
+        if b_id != v_id {
+            ls.arena.backward_reverse(b_id, v_id, NodeId::NONE);
+        }
+
+        // Link (u) -> (b, ..., v) -> (a_next)
+        ls.arena.link(u_id, b_id);
+        ls.arena.link(v_id, a_next_id);
+
+        // Link (v_prev) -> (a, ..., x) -> (b_next)
+        ls.arena.link(v_prev_id, a_id);
+        ls.arena.link(x_id, b_next_id);
+    } else {
+        // Link (u) -> (v, ..., b) -> (a_next)
+        ls.arena.link(u_id, v_id);
+        ls.arena.link(b_id, a_next_id);
+
+        // Link (v_prev) -> (x, ..., a) -> (b_next)
+        ls.arena.link(v_prev_id, x_id);
+        ls.arena.link(a_id, b_next_id);
+    }
+
+    // Update routes
+    ls.update_route(ctx, r1);
+    ls.update_route(ctx, r2);
+}
@@ -1,3 +1,6 @@
+mod cross_exchange;
+pub use self::cross_exchange::*;
+
 mod relocation;
 pub use self::relocation::*;
 
@@ -10,16 +13,26 @@ pub use self::swap_star::*;
 mod two_opt;
 pub use self::two_opt::*;
 
-use crate::solver::improvement::linked_list::LinkNode;
-use crate::solver::improvement::LocalSearch;
+use crate::solver::improvement::{LocalSearch, NodeId};
 use crate::solver::Context;
 
+/// Move of the local search between the node `u` and a node `v` among its correlated nodes.
+///
+/// User-defined moves are added with `LocalSearchBuilder`. They compute the delta from the views
+/// returned by `LocalSearch::node` and `LocalSearch::route`, and perform the move with
+/// `LocalSearch::link` or `LocalSearch::insert_after` followed by `LocalSearch::update_route` for
+/// every modified route. Both are given the context of the current run of the local search.
 pub trait Move {
     fn move_name(&self) -> &'static str;
-    unsafe fn delta(&self, ls: &LocalSearch, node_u: *mut LinkNode, node_v: *mut LinkNode) -> f64;
-    unsafe fn perform(&self, ls: &mut LocalSearch, node_u: *mut LinkNode, node_v: *mut LinkNode);
+
+    /// Change in penalized cost when performing the move
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u: NodeId, v: NodeId) -> f64;
+
+    /// Perform the move and update the modified routes
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u: NodeId, v: NodeId);
 }
 
+#[derive(Default)]
 pub struct Moves {
     pub neighbor: Vec<Box<dyn Move>>,
     pub depot: Vec<Box<dyn Move>>,
@@ -46,6 +59,12 @@ impl Moves {
         if ctx.config.borrow().relocate_double_reverse {
             moves.push(Box::new(RelocateDoubleReverse));
         }
+        if ctx.config.borrow().relocate_triple {
+            moves.push(Box::new(RelocateTriple));
+        }
+        if ctx.config.borrow().relocate_triple_reverse {
+            moves.push(Box::new(RelocateTripleReverse));
+        }
         if ctx.config.borrow().swap_one_with_one {
             moves.push(Box::new(SwapOneWithOne));
         }
@@ -64,6 +83,12 @@ impl Moves {
         if ctx.config.borrow().two_opt_inter {
             moves.push(Box::new(TwoOptInter));
         }
+        if ctx.config.borrow().cross_exchange {
+            moves.push(Box::new(CrossExchange::default()));
+        }
+        if ctx.config.borrow().cross_exchange_reverse {
+            moves.push(Box::new(CrossExchangeReverse::default()));
+        }
         moves
     }
 
@@ -78,6 +103,12 @@ impl Moves {
         if ctx.config.borrow().relocate_double_reverse {
             moves.push(Box::new(RelocateDoubleReverse));
         }
+        if ctx.config.borrow().relocate_triple {
+            moves.push(Box::new(RelocateTriple));
+        }
+        if ctx.config.borrow().relocate_triple_reverse {
+            moves.push(Box::new(RelocateTripleReverse));
+        }
         if ctx.config.borrow().two_opt_inter_reverse {
             moves.push(Box::new(TwoOptInterReverse));
         }
@@ -98,9 +129,145 @@ impl Moves {
         if ctx.config.borrow().relocate_double_reverse {
             moves.push(Box::new(RelocateDoubleReverse));
         }
+        if ctx.config.borrow().relocate_triple {
+            moves.push(Box::new(RelocateTriple));
+        }
+        if ctx.config.borrow().relocate_triple_reverse {
+            moves.push(Box::new(RelocateTripleReverse));
+        }
         if ctx.config.borrow().two_opt_inter {
             moves.push(Box::new(TwoOptInter));
         }
         moves
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::models::{DistanceMatrix, Matrix};
+    use crate::solver::genetic::{Individual, Split};
+    use crate::solver::testing::test_context;
+    use crate::utils::FloatCompare;
+
+    // Delegates to `inner` and asserts that its delta equals the change in cost of the routes of
+    // `u` and `v` when the move is performed
+    struct CheckedMove {
+        inner: Box<dyn Move>,
+        delta: Cell<f64>,
+        performed: Rc<Cell<usize>>,
+    }
+
+    impl Move for CheckedMove {
+        fn move_name(&self) -> &'static str {
+            self.inner.move_name()
+        }
+
+        fn delta(&self, ctx: &Context, ls: &LocalSearch, u: NodeId, v: NodeId) -> f64 {
+            let delta = self.inner.delta(ctx, ls, u, v);
+            self.delta.set(delta);
+            delta
+        }
+
+        fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u: NodeId, v: NodeId) {
+            let r1 = ls.node(u).route().unwrap().id();
+            let r2 = ls.node(v).route().unwrap().id();
+            let routes_cost = |ls: &LocalSearch| match r1 == r2 {
+                true => ls.route(r1).cost(),
+                false => ls.route(r1).cost() + ls.route(r2).cost(),
+            };
+
+            let old_cost = routes_cost(ls);
+            self.inner.perform(ctx, ls, u, v);
+            let new_cost = routes_cost(ls);
+            assert!(
+                (new_cost - old_cost).approx_eq(self.delta.get()),
+                "{} has delta {} but changed the cost by {}",
+                self.inner.move_name(),
+                self.delta.get(),
+                new_cost - old_cost
+            );
+            self.performed.set(self.performed.get() + 1);
+        }
+    }
+
+    // Twenty customers around the depot, where a vehicle serves four to six of them
+    fn context() -> Context {
+        let points: Vec<(f64, f64)> = (0..=20)
+            .map(|id| (((id * 7) % 17) as f64 - 8.0, ((id * 11) % 13) as f64 - 6.0))
+            .collect();
+        let demands: Vec<f64> = (1..=20).map(|id| (id % 3 + 1) as f64).collect();
+        let mut config = Config::default();
+        config.tsp_after_local_search = false;
+        test_context(&points, &demands, 10.0, config)
+    }
+
+    // Euclidean distances, where the distance increases when going to a customer with a lower
+    // number
+    fn asymmetric_context() -> Context {
+        let mut ctx = context();
+        let n = ctx.problem.nodes.len();
+        let mut storage = Matrix::new(n, n);
+        for i in 0..n {
+            for j in 0..n {
+                let distance = ctx.matrix_provider.distance.get(i, j);
+                storage.set(i, j, if j < i { 2.0 * distance } else { distance });
+            }
+        }
+        ctx.matrix_provider.distance = DistanceMatrix::from_input(storage, false, None);
+        assert!(!ctx.matrix_provider.distance.is_symmetric());
+        ctx
+    }
+
+    // Runs the local search with only the given move on random individuals, and returns the
+    // number of performed moves
+    fn performed_moves(ctx: &Context, m: Box<dyn Move>) -> usize {
+        let performed = Rc::new(Cell::new(0));
+        let mut ls = LocalSearch::builder(ctx)
+            .config_moves(false)
+            .neighbor_move(Box::new(CheckedMove {
+                inner: m,
+                delta: Cell::new(0.0),
+                performed: performed.clone(),
+            }))
+            .build();
+        let mut split = Split::new(ctx);
+        let num_vehicles = ctx.config.borrow().num_vehicles;
+        for number in 0..5 {
+            let mut individual = Individual::new_random(ctx, number);
+            split.run(ctx, &mut individual, num_vehicles);
+            ls.run(ctx, &mut individual, 1.0);
+        }
+        performed.get()
+    }
+
+    #[test]
+    fn relocate_triple_delta_is_exact() {
+        let ctx = context();
+        assert!(performed_moves(&ctx, Box::new(RelocateTriple)) > 0);
+        assert!(performed_moves(&ctx, Box::new(RelocateTripleReverse)) > 0);
+    }
+
+    #[test]
+    fn cross_exchange_delta_is_exact() {
+        let ctx = context();
+        assert!(performed_moves(&ctx, Box::new(CrossExchange::default())) > 0);
+        assert!(performed_moves(&ctx, Box::new(CrossExchangeReverse::default())) > 0);
+    }
+
+    #[test]
+    fn asymmetric_delta_is_exact() {
+        let ctx = asymmetric_context();
+        assert!(performed_moves(&ctx, Box::new(RelocateTriple)) > 0);
+        assert!(performed_moves(&ctx, Box::new(RelocateTripleReverse)) > 0);
+        assert!(performed_moves(&ctx, Box::new(CrossExchange::default())) > 0);
+        assert_eq!(
+            0,
+            performed_moves(&ctx, Box::new(CrossExchangeReverse::default()))
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::solver::evaluate::route_cost;
-use crate::solver::improvement::{LinkNode, LocalSearch, Move};
+use crate::solver::improvement::{LocalSearch, Move, NodeId};
+use crate::solver::Context;
 
 pub struct RelocateSingle;
 
@@ -7,18 +8,19 @@ impl Move for RelocateSingle {
     fn move_name(&self) -> &'static str {
         "RelocateSingle"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_pred = &*u.predecessor;
-        let x = &*u.successor;
-        let v = &*v_rc;
-        let y = &*v.successor;
+        let u = &arena[u_id];
+        let u_pred = &arena[u.predecessor];
+        let x = &arena[u.successor];
+        let v = &arena[v_id];
+        let y = &arena[v.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if y.number == u.number {
@@ -51,25 +53,25 @@ impl Move for RelocateSingle {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_pred_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let y_rc = (*v_rc).successor;
+        let u_pred_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let y_id = ls.arena[v_id].successor;
 
         // Update (up, u, x) -> (up, x)
-        LinkNode::link_nodes(u_pred_rc, x_rc);
+        ls.arena.link(u_pred_id, x_id);
 
         // Update (v, y) -> (v, u, y)
-        LinkNode::link_nodes(v_rc, u_rc);
-        LinkNode::link_nodes(u_rc, y_rc);
+        ls.arena.link(v_id, u_id);
+        ls.arena.link(u_id, y_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
@@ -80,25 +82,26 @@ impl Move for RelocateDouble {
     fn move_name(&self) -> &'static str {
         "RelocateDouble"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_pred = &*u.predecessor;
-        let x = &*u.successor;
+        let u = &arena[u_id];
+        let u_pred = &arena[u.predecessor];
+        let x = &arena[u.successor];
 
         // Return if x is a depot.
         if x.is_depot() {
             return 0.0;
         }
-        let x_next = &*x.successor;
+        let x_next = &arena[x.successor];
 
-        let v = &*v_rc;
-        let y = &*v.successor;
+        let v = &arena[v_id];
+        let y = &arena[v.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if u.number == y.number || v.number == x.number {
@@ -134,26 +137,26 @@ impl Move for RelocateDouble {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_prev_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let x_next_rc = (*x_rc).successor;
-        let y_rc = (*v_rc).successor;
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x_next_id = ls.arena[x_id].successor;
+        let y_id = ls.arena[v_id].successor;
 
         // Update (up, u, x, xn) -> (up, xn)
-        LinkNode::link_nodes(u_prev_rc, x_next_rc);
+        ls.arena.link(u_prev_id, x_next_id);
 
         // Update (v, y) -> (v, u, x, y)
-        LinkNode::link_nodes(v_rc, u_rc);
-        LinkNode::link_nodes(x_rc, y_rc);
+        ls.arena.link(v_id, u_id);
+        ls.arena.link(x_id, y_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
@@ -164,25 +167,26 @@ impl Move for RelocateDoubleReverse {
     fn move_name(&self) -> &'static str {
         "RelocateDoubleReverse"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_prev = &*u.predecessor;
-        let x = &*u.successor;
+        let u = &arena[u_id];
+        let u_prev = &arena[u.predecessor];
+        let x = &arena[u.successor];
 
         // Return if x is a depot.
         if x.is_depot() {
             return 0.0;
         }
-        let x_next = &*x.successor;
+        let x_next = &arena[x.successor];
 
-        let v = &*v_rc;
-        let y = &*v.successor;
+        let v = &arena[v_id];
+        let y = &arena[v.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if u.number == y.number || v.number == x.number {
@@ -218,31 +222,178 @@ impl Move for RelocateDoubleReverse {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_prev_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let x_next_rc = (*x_rc).successor;
-        let y_rc = (*v_rc).successor;
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x_next_id = ls.arena[x_id].successor;
+        let y_id = ls.arena[v_id].successor;
 
         // Link (up) -> (xn)
-        LinkNode::link_nodes(u_prev_rc, x_next_rc);
+        ls.arena.link(u_prev_id, x_next_id);
 
         // Link (v) -> (x)
-        LinkNode::link_nodes(v_rc, x_rc);
+        ls.arena.link(v_id, x_id);
 
         // Link (x) -> (u)
-        LinkNode::link_nodes(x_rc, u_rc);
+        ls.arena.link(x_id, u_id);
 
         // Link (u) -> (y)
-        LinkNode::link_nodes(u_rc, y_rc);
+        ls.arena.link(u_id, y_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
+
+pub struct RelocateTriple;
+
+impl Move for RelocateTriple {
+    fn move_name(&self) -> &'static str {
+        "RelocateTriple"
+    }
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        relocate_triple_delta(ctx, ls, u_id, v_id, false)
+    }
+
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
+
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x2_id = ls.arena[x_id].successor;
+        let x2_next_id = ls.arena[x2_id].successor;
+        let y_id = ls.arena[v_id].successor;
+
+        // Update (up, u, x, x2, x2n) -> (up, x2n)
+        ls.arena.link(u_prev_id, x2_next_id);
+
+        // Update (v, y) -> (v, u, x, x2, y)
+        ls.arena.link(v_id, u_id);
+        ls.arena.link(x2_id, y_id);
+
+        // Update routes
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
+        }
+    }
+}
+
+pub struct RelocateTripleReverse;
+
+impl Move for RelocateTripleReverse {
+    fn move_name(&self) -> &'static str {
+        "RelocateTripleReverse"
+    }
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        relocate_triple_delta(ctx, ls, u_id, v_id, true)
+    }
+
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
+
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x2_id = ls.arena[x_id].successor;
+        let x2_next_id = ls.arena[x2_id].successor;
+        let y_id = ls.arena[v_id].successor;
+
+        // Link (up) -> (x2n)
+        ls.arena.link(u_prev_id, x2_next_id);
+
+        // Link (v) -> (x2) -> (x) -> (u) -> (y)
+        ls.arena.link(v_id, x2_id);
+        ls.arena.link(x2_id, x_id);
+        ls.arena.link(x_id, u_id);
+        ls.arena.link(u_id, y_id);
+
+        // Update routes
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
+        }
+    }
+}
+
+/// Delta cost of moving (u, x, x2) after v, possibly inserted in reverse order
+fn relocate_triple_delta(
+    ctx: &Context,
+    ls: &LocalSearch,
+    u_id: NodeId,
+    v_id: NodeId,
+    reverse: bool,
+) -> f64 {
+    let distance_matrix = &ctx.matrix_provider.distance;
+    let arena = &ls.arena;
+    let nodes = &ctx.problem.nodes;
+
+    let u = &arena[u_id];
+    let u_prev = &arena[u.predecessor];
+    let x = &arena[u.successor];
+
+    // Return if x or x2 is a depot
+    if x.is_depot() {
+        return 0.0;
+    }
+    let x2 = &arena[x.successor];
+    if x2.is_depot() {
+        return 0.0;
+    }
+    let x2_next = &arena[x2.successor];
+
+    let v = &arena[v_id];
+    let y = &arena[v.successor];
+
+    let r1 = &arena[u.route];
+    let r2 = &arena[v.route];
+
+    // Nothing happens or v is inside the moved sequence
+    if u.number == y.number || v.number == x.number || v.number == x2.number {
+        return 0.0;
+    }
+
+    let distance_one = r1.distance
+        - distance_matrix.get(u_prev.number, u.number)
+        - distance_matrix.get(u.number, x.number)
+        - distance_matrix.get(x.number, x2.number)
+        - distance_matrix.get(x2.number, x2_next.number)
+        + distance_matrix.get(u_prev.number, x2_next.number);
+
+    let distance_two = if reverse {
+        r2.distance - distance_matrix.get(v.number, y.number)
+            + distance_matrix.get(v.number, x2.number)
+            + distance_matrix.get(x2.number, x.number)
+            + distance_matrix.get(x.number, u.number)
+            + distance_matrix.get(u.number, y.number)
+    } else {
+        r2.distance - distance_matrix.get(v.number, y.number)
+            + distance_matrix.get(v.number, u.number)
+            + distance_matrix.get(u.number, x.number)
+            + distance_matrix.get(x.number, x2.number)
+            + distance_matrix.get(x2.number, y.number)
+    };
+
+    let mut overload_one = r1.overload;
+    let mut overload_two = r2.overload;
+
+    if r1.index != r2.index {
+        let demand = nodes[u.number].demand + nodes[x.number].demand + nodes[x2.number].demand;
+        overload_one += -demand;
+        overload_two += demand;
+    }
+
+    let old_cost = r1.cost + r2.cost;
+    let new_cost = route_cost(distance_one, overload_one, ls.penalty_capacity)
+        + route_cost(distance_two, overload_two, ls.penalty_capacity);
+
+    // Return delta cost
+    new_cost - old_cost
+}
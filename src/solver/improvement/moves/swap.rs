@@ -1,5 +1,6 @@
 use crate::solver::evaluate::route_cost;
-use crate::solver::improvement::{LinkNode, LocalSearch, Move};
+use crate::solver::improvement::{LocalSearch, Move, NodeId};
+use crate::solver::Context;
 
 pub struct SwapOneWithOne;
 
@@ -7,20 +8,21 @@ impl Move for SwapOneWithOne {
     fn move_name(&self) -> &'static str {
         "SwapOneWithOne"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_prev = &*u.predecessor;
-        let x = &*u.successor;
+        let u = &arena[u_id];
+        let u_prev = &arena[u.predecessor];
+        let x = &arena[u.successor];
 
-        let v = &*v_rc;
-        let v_prev = &*v.predecessor;
-        let y = &*v.successor;
+        let v = &arena[v_id];
+        let v_prev = &arena[v.predecessor];
+        let y = &arena[v.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if u.number == y.number || u.number == v_prev.number {
@@ -57,31 +59,31 @@ impl Move for SwapOneWithOne {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_prev_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let v_prev_rc = (*v_rc).predecessor;
-        let y_rc = (*v_rc).successor;
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let v_prev_id = ls.arena[v_id].predecessor;
+        let y_id = ls.arena[v_id].successor;
 
         // Link (u_prev) -> (v)
-        LinkNode::link_nodes(u_prev_rc, v_rc);
+        ls.arena.link(u_prev_id, v_id);
 
         // Link (v) -> (x)
-        LinkNode::link_nodes(v_rc, x_rc);
+        ls.arena.link(v_id, x_id);
 
         // Link (v_prev) -> (u)
-        LinkNode::link_nodes(v_prev_rc, u_rc);
+        ls.arena.link(v_prev_id, u_id);
 
         // Link (u) -> (y)
-        LinkNode::link_nodes(u_rc, y_rc);
+        ls.arena.link(u_id, y_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
@@ -92,25 +94,26 @@ impl Move for SwapTwoWithOne {
     fn move_name(&self) -> &'static str {
         "SwapTwoWithOne"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_prev = &*u.predecessor;
-        let x = &*u.successor;
+        let u = &arena[u_id];
+        let u_prev = &arena[u.predecessor];
+        let x = &arena[u.successor];
 
         if x.is_depot() {
             return 0.0;
         }
-        let x_next = &*x.successor;
+        let x_next = &arena[x.successor];
 
-        let v = &*v_rc;
-        let v_prev = &*v.predecessor;
-        let y = &*v.successor;
+        let v = &arena[v_id];
+        let v_prev = &arena[v.predecessor];
+        let y = &arena[v.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if u.number == v_prev.number || x.number == v_prev.number || u.number == y.number {
@@ -150,32 +153,32 @@ impl Move for SwapTwoWithOne {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_prev_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let x_next_rc = (*x_rc).successor;
-        let v_prev_rc = (*v_rc).predecessor;
-        let y_rc = (*v_rc).successor;
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x_next_id = ls.arena[x_id].successor;
+        let v_prev_id = ls.arena[v_id].predecessor;
+        let y_id = ls.arena[v_id].successor;
 
         // Link (u_prev) -> (v)
-        LinkNode::link_nodes(u_prev_rc, v_rc);
+        ls.arena.link(u_prev_id, v_id);
 
         // Link (v) -> (x_next)
-        LinkNode::link_nodes(v_rc, x_next_rc);
+        ls.arena.link(v_id, x_next_id);
 
         // Link (v_prev) -> (u)
-        LinkNode::link_nodes(v_prev_rc, u_rc);
+        ls.arena.link(v_prev_id, u_id);
 
         // Link (x) -> (y)
-        LinkNode::link_nodes(x_rc, y_rc);
+        ls.arena.link(x_id, y_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
@@ -186,29 +189,30 @@ impl Move for SwapTwoWithTwo {
     fn move_name(&self) -> &'static str {
         "SwapTwoWithTwo"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
-        let nodes = &ls.ctx.problem.nodes;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
+        let nodes = &ctx.problem.nodes;
 
-        let u = &*u_rc;
-        let u_prev = &*u.predecessor;
-        let x = &*u.successor;
+        let u = &arena[u_id];
+        let u_prev = &arena[u.predecessor];
+        let x = &arena[u.successor];
 
         if x.is_depot() {
             return 0.0;
         }
-        let x_next = &*x.successor;
+        let x_next = &arena[x.successor];
 
-        let v = &*v_rc;
-        let v_prev = &*v.predecessor;
-        let y = &*v.successor;
+        let v = &arena[v_id];
+        let v_prev = &arena[v.predecessor];
+        let y = &arena[v.successor];
         if y.is_depot() {
             return 0.0;
         }
-        let y_next = &*y.successor;
+        let y_next = &arena[y.successor];
 
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         // Nothing happens
         if u.number == y.number
@@ -255,33 +259,33 @@ impl Move for SwapTwoWithTwo {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
 
-        let u_prev_rc = (*u_rc).predecessor;
-        let x_rc = (*u_rc).successor;
-        let x_next_rc = (*x_rc).successor;
-        let v_prev_rc = (*v_rc).predecessor;
-        let y_rc = (*v_rc).successor;
-        let y_next_rc = (*y_rc).successor;
+        let u_prev_id = ls.arena[u_id].predecessor;
+        let x_id = ls.arena[u_id].successor;
+        let x_next_id = ls.arena[x_id].successor;
+        let v_prev_id = ls.arena[v_id].predecessor;
+        let y_id = ls.arena[v_id].successor;
+        let y_next_id = ls.arena[y_id].successor;
 
         // Link (u_prev) -> (v)
-        LinkNode::link_nodes(u_prev_rc, v_rc);
+        ls.arena.link(u_prev_id, v_id);
 
         // Link (y) -> (x_next)
-        LinkNode::link_nodes(y_rc, x_next_rc);
+        ls.arena.link(y_id, x_next_id);
 
         // Link (v_prev) -> (u)
-        LinkNode::link_nodes(v_prev_rc, u_rc);
+        ls.arena.link(v_prev_id, u_id);
 
         // Link (x) -> (y_next)
-        LinkNode::link_nodes(x_rc, y_next_rc);
+        ls.arena.link(x_id, y_next_id);
 
         // Update routes
-        ls.update_route(r1);
-        if (*r1).index != (*r2).index {
-            ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        if r1 != r2 {
+            ls.update_route(ctx, r2);
         }
     }
 }
@@ -1,26 +1,25 @@
-use std::ptr;
-
-use crate::solver::improvement::{LinkNode, LinkRoute, LocalSearch};
+use crate::solver::improvement::{LocalSearch, NodeId, RouteId};
+use crate::solver::Context;
 use crate::utils::FloatCompare;
 
 pub struct BestSwapStar {
     pub cost: f64,
-    pub u: *mut LinkNode,
-    pub v: *mut LinkNode,
+    pub u: NodeId,
+    pub v: NodeId,
     // Best position to insert `u` is right after `pos_u`
-    pub pos_u: *mut LinkNode,
+    pub pos_u: NodeId,
     // Best position to insert `v` is right after `pos_v`
-    pub pos_v: *mut LinkNode,
+    pub pos_v: NodeId,
 }
 
 impl BestSwapStar {
     pub fn new() -> Self {
         Self {
             cost: f64::INFINITY,
-            u: ptr::null_mut(),
-            v: ptr::null_mut(),
-            pos_u: ptr::null_mut(),
-            pos_v: ptr::null_mut(),
+            u: NodeId::NONE,
+            v: NodeId::NONE,
+            pos_u: NodeId::NONE,
+            pos_v: NodeId::NONE,
         }
     }
 }
@@ -32,28 +31,25 @@ impl SwapStar {
         "SwapStar"
     }
 
-    pub unsafe fn run(
-        ls: &mut LocalSearch,
-        r1_ptr: *mut LinkRoute,
-        r2_ptr: *mut LinkRoute,
-    ) -> bool {
+    pub fn run(ctx: &Context, ls: &mut LocalSearch, r1_id: RouteId, r2_id: RouteId) -> bool {
+        // Preprocess the three best insertions for all the nodes in the two routes
+        ls.preprocess_insertions(ctx, r1_id, r2_id);
+        ls.preprocess_insertions(ctx, r2_id, r1_id);
+
         // Setup local variables
         let mut best_move = BestSwapStar::new();
-        let problem = &ls.ctx.problem;
-        let r1 = &*r1_ptr;
-        let r2 = &*r2_ptr;
-        let mut u_ptr = (*r1.start_depot).successor;
-
-        // Preprocess the three best insertions for all the nodes in the two routes
-        ls.preprocess_insertions(r1_ptr, r2_ptr);
-        ls.preprocess_insertions(r2_ptr, r1_ptr);
+        let problem = &ctx.problem;
+        let arena = &ls.arena;
+        let r1 = &arena[r1_id];
+        let r2 = &arena[r2_id];
+        let mut u_id = arena[r1.start_depot].successor;
 
         // Loop over pairs of nodes in the two routes
-        while !(*u_ptr).is_depot() {
-            let u = &*u_ptr;
-            let mut v_ptr = (*r2.start_depot).successor;
-            while !(*v_ptr).is_depot() {
-                let v = &*v_ptr;
+        while !arena[u_id].is_depot() {
+            let u = &arena[u_id];
+            let mut v_id = arena[r2.start_depot].successor;
+            while !arena[v_id].is_depot() {
+                let v = &arena[v_id];
 
                 // Calculate the change in penalty when u and v swap routes
                 let delta_penalty_r1 = 0f64.max(
@@ -70,13 +66,13 @@ impl SwapStar {
                     .approx_lte(0.0)
                 {
                     let mut m = BestSwapStar::new();
-                    m.u = u_ptr;
-                    m.v = v_ptr;
+                    m.u = u_id;
+                    m.v = v_id;
 
                     let (best_pos_u, delta_insertion_u) =
-                        ls.cheapest_insert_and_removal(u_ptr, v_ptr);
+                        ls.cheapest_insert_and_removal(ctx, u_id, v_id);
                     let (best_pos_v, delta_insertion_v) =
-                        ls.cheapest_insert_and_removal(v_ptr, u_ptr);
+                        ls.cheapest_insert_and_removal(ctx, v_id, u_id);
                     m.pos_u = best_pos_u;
                     m.pos_v = best_pos_v;
                     // Calculate change in cost for performing the move
@@ -93,18 +89,18 @@ impl SwapStar {
                     }
                 }
 
-                v_ptr = v.successor;
+                v_id = v.successor;
             }
-            u_ptr = u.successor;
+            u_id = u.successor;
         }
 
         // Include all relocations of u into route of v.
         // This is very cheap as we already have calculated the best insertion positions
-        let mut u_ptr = (*r1.start_depot).successor;
-        while !(*u_ptr).is_depot() {
-            let u = &*u_ptr;
+        let mut u_id = arena[r1.start_depot].successor;
+        while !arena[u_id].is_depot() {
+            let u = &arena[u_id];
             let mut m = BestSwapStar::new();
-            m.u = u_ptr;
+            m.u = u_id;
             let best_insert = &ls.best_inserts.get(r2.index, u.number).locations[0];
             m.pos_u = best_insert.node;
             let delta_penalty_r1 = 0f64.max(r1.overload - problem.nodes[u.number].demand)
@@ -119,16 +115,16 @@ impl SwapStar {
                 best_move = m;
             }
 
-            u_ptr = u.successor;
+            u_id = u.successor;
         }
 
         // Include all relocations of v into route of u.
         // This is very cheap as we already have calculated the best insertion positions
-        let mut v_ptr = (*r2.start_depot).successor;
-        while !(*v_ptr).is_depot() {
-            let v = &*v_ptr;
+        let mut v_id = arena[r2.start_depot].successor;
+        while !arena[v_id].is_depot() {
+            let v = &arena[v_id];
             let mut m = BestSwapStar::new();
-            m.v = v_ptr;
+            m.v = v_id;
             let best_insert = &ls.best_inserts.get(r1.index, v.number).locations[0];
             m.pos_v = best_insert.node;
             let delta_penalty_r1 = 0f64.max(r1.overload + problem.nodes[v.number].demand)
@@ -143,7 +139,7 @@ impl SwapStar {
                 best_move = m;
             }
 
-            v_ptr = v.successor;
+            v_id = v.successor;
         }
 
         // Return false if the move does not reduce the objective function
@@ -154,17 +150,17 @@ impl SwapStar {
         ls.move_count += 1;
 
         // Relocate u into route of v
-        if !best_move.pos_u.is_null() {
-            LinkNode::insert_node(best_move.u, best_move.pos_u);
+        if !best_move.pos_u.is_none() {
+            ls.arena.insert_after(best_move.u, best_move.pos_u);
         }
         // Relocate u into route of v
-        if !best_move.pos_v.is_null() {
-            LinkNode::insert_node(best_move.v, best_move.pos_v);
+        if !best_move.pos_v.is_none() {
+            ls.arena.insert_after(best_move.v, best_move.pos_v);
         }
 
         // Update routes
-        ls.update_route(r1_ptr);
-        ls.update_route(r2_ptr);
+        ls.update_route(ctx, r1_id);
+        ls.update_route(ctx, r2_id);
 
         true
     }
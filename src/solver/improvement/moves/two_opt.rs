@@ -1,5 +1,6 @@
 use crate::solver::evaluate::route_cost;
-use crate::solver::improvement::{LinkNode, LocalSearch, Move};
+use crate::solver::improvement::{LocalSearch, Move, NodeId};
+use crate::solver::Context;
 
 pub struct TwoOptIntraReverse;
 
@@ -7,20 +8,21 @@ impl Move for TwoOptIntraReverse {
     fn move_name(&self) -> &'static str {
         "TwoOptIntraReverse"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
 
-        let u = &*u_rc;
-        let v = &*v_rc;
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let u = &arena[u_id];
+        let v = &arena[v_id];
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         if r1.index != r2.index {
             return 0.0;
         }
 
-        let x = &*u.successor;
-        let y = &*v.successor;
+        let x = &arena[u.successor];
+        let y = &arena[v.successor];
 
         // Nothing happens
         if u.position > v.position || x.number == v.number {
@@ -36,14 +38,14 @@ impl Move for TwoOptIntraReverse {
         delta_distance as f64
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let x_rc = (*u_rc).successor;
-        let y_rc = (*v_rc).successor;
-        LinkNode::backward_reverse(v_rc, x_rc, std::ptr::null_mut());
-        LinkNode::link_nodes(u_rc, v_rc);
-        LinkNode::link_nodes(x_rc, y_rc);
-        ls.update_route(r1);
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let x_id = ls.arena[u_id].successor;
+        let y_id = ls.arena[v_id].successor;
+        ls.arena.backward_reverse(v_id, x_id, NodeId::NONE);
+        ls.arena.link(u_id, v_id);
+        ls.arena.link(x_id, y_id);
+        ls.update_route(ctx, r1);
     }
 }
 
@@ -53,22 +55,23 @@ impl Move for TwoOptInterReverse {
     fn move_name(&self) -> &'static str {
         "TwoOptInterReverse"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
 
-        let u = &*u_rc;
-        let v = &*v_rc;
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let u = &arena[u_id];
+        let v = &arena[v_id];
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         if r1.index == r2.index {
             return 0.0;
         }
 
-        let x = &*u.successor;
-        let y = &*v.successor;
+        let x = &arena[u.successor];
+        let y = &arena[v.successor];
 
-        let cap = ls.ctx.problem.vehicle.cap;
+        let cap = ctx.problem.vehicle.cap;
 
         let distance_one =
             u.cum_distance + v.cum_distance + distance_matrix.get(u.number, v.number);
@@ -85,26 +88,28 @@ impl Move for TwoOptInterReverse {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, mut v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
-        let mut x_rc = (*u_rc).successor;
-        let y_rc = (*v_rc).successor;
-        if !(*v_rc).is_depot() {
-            LinkNode::backward_reverse(v_rc, std::ptr::null_mut(), (*r1).end_depot);
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, mut v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
+        let mut x_id = ls.arena[u_id].successor;
+        let y_id = ls.arena[v_id].successor;
+        let r1_end_depot = ls.arena[r1].end_depot;
+        let r2_start_depot = ls.arena[r2].start_depot;
+        if !ls.arena[v_id].is_depot() {
+            ls.arena.backward_reverse(v_id, NodeId::NONE, r1_end_depot);
         } else {
-            v_rc = (*r1).end_depot;
+            v_id = r1_end_depot;
         }
-        LinkNode::link_nodes(u_rc, v_rc);
-        if !(*x_rc).is_depot() {
-            LinkNode::forward_reverse(x_rc, std::ptr::null_mut(), (*r2).start_depot);
+        ls.arena.link(u_id, v_id);
+        if !ls.arena[x_id].is_depot() {
+            ls.arena.forward_reverse(x_id, NodeId::NONE, r2_start_depot);
         } else {
-            x_rc = (*r2).start_depot;
+            x_id = r2_start_depot;
         }
-        LinkNode::link_nodes(x_rc, y_rc);
+        ls.arena.link(x_id, y_id);
 
-        ls.update_route(r1);
-        ls.update_route(r2);
+        ls.update_route(ctx, r1);
+        ls.update_route(ctx, r2);
     }
 }
 
@@ -114,22 +119,23 @@ impl Move for TwoOptInter {
     fn move_name(&self) -> &'static str {
         "TwoOptInter"
     }
-    unsafe fn delta(&self, ls: &LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) -> f64 {
-        let distance_matrix = &ls.ctx.matrix_provider.distance;
+    fn delta(&self, ctx: &Context, ls: &LocalSearch, u_id: NodeId, v_id: NodeId) -> f64 {
+        let distance_matrix = &ctx.matrix_provider.distance;
+        let arena = &ls.arena;
 
-        let u = &*u_rc;
-        let v = &*v_rc;
-        let r1 = &*u.route;
-        let r2 = &*v.route;
+        let u = &arena[u_id];
+        let v = &arena[v_id];
+        let r1 = &arena[u.route];
+        let r2 = &arena[v.route];
 
         if r1.index == r2.index {
             return 0.0;
         }
 
-        let x = &*u.successor;
-        let y = &*v.successor;
+        let x = &arena[u.successor];
+        let y = &arena[v.successor];
 
-        let cap = ls.ctx.problem.vehicle.cap;
+        let cap = ctx.problem.vehicle.cap;
 
         let distance_one =
             u.cum_distance + r2.distance - y.cum_distance + distance_matrix.get(u.number, y.number);
@@ -146,16 +152,16 @@ impl Move for TwoOptInter {
         new_cost - old_cost
     }
 
-    unsafe fn perform(&self, ls: &mut LocalSearch, u_rc: *mut LinkNode, v_rc: *mut LinkNode) {
-        let r1 = (*u_rc).route;
-        let r2 = (*v_rc).route;
-        let x_rc = (*u_rc).successor;
-        let y_rc = (*v_rc).successor;
-        LinkNode::link_nodes(u_rc, y_rc);
-        LinkNode::link_nodes(v_rc, x_rc);
-        LinkNode::replace_end_depot(v_rc, (*r2).end_depot);
-        LinkNode::replace_end_depot(u_rc, (*r1).end_depot);
-        ls.update_route(r1);
-        ls.update_route(r2);
+    fn perform(&self, ctx: &Context, ls: &mut LocalSearch, u_id: NodeId, v_id: NodeId) {
+        let r1 = ls.arena[u_id].route;
+        let r2 = ls.arena[v_id].route;
+        let x_id = ls.arena[u_id].successor;
+        let y_id = ls.arena[v_id].successor;
+        ls.arena.link(u_id, y_id);
+        ls.arena.link(v_id, x_id);
+        ls.arena.replace_end_depot(v_id, ls.arena[r2].end_depot);
+        ls.arena.replace_end_depot(u_id, ls.arena[r1].end_depot);
+        ls.update_route(ctx, r1);
+        ls.update_route(ctx, r2);
     }
 }
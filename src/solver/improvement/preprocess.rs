@@ -1,25 +1,23 @@
-use std::ptr;
-
-use crate::solver::improvement::LinkNode;
+use crate::solver::improvement::NodeId;
 
 /// Used to store the cost of inserting directly after `node`
 #[derive(Debug, Clone, Copy)]
 pub struct InsertLocation {
     pub cost: f64,
-    pub node: *mut LinkNode,
+    pub node: NodeId,
 }
 
 impl InsertLocation {
     pub fn new() -> Self {
         Self {
             cost: f64::INFINITY,
-            node: ptr::null_mut(),
+            node: NodeId::NONE,
         }
     }
 
     pub fn reset(&mut self) {
         self.cost = f64::INFINITY;
-        self.node = ptr::null_mut();
+        self.node = NodeId::NONE;
     }
 }
 
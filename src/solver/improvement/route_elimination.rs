@@ -0,0 +1,500 @@
+use crate::solver::genetic::Individual;
+use crate::solver::Context;
+
+// Marks customers which are in the ejection pool
+const IN_POOL: usize = usize::MAX;
+
+/// Route elimination with an ejection pool, in the spirit of Nagata and Bräysy (2009).
+///
+/// The route with the fewest customers is removed and its customers are put in the ejection
+/// pool. Customers are taken from the pool and inserted at the cheapest feasible position. If
+/// no feasible position exists, the customer is inserted anyway and up to
+/// `route_elimination_max_ejections` customers are ejected from the route to restore
+/// feasibility, choosing the customers which have been hard to insert the fewest times. Random
+/// feasible relocations diversify the search between the insertions.
+pub struct RouteElimination {
+    // Number of times each customer has failed to be inserted feasibly
+    penalties: Vec<u64>,
+
+    // Index of the route serving each customer
+    route_of: Vec<usize>,
+
+    // Load on each route
+    loads: Vec<f64>,
+}
+
+impl RouteElimination {
+    pub fn new(ctx: &Context) -> Self {
+        Self {
+            penalties: vec![1; ctx.problem.dim()],
+            route_of: vec![IN_POOL; ctx.problem.dim()],
+            loads: Vec::new(),
+        }
+    }
+
+    /// Try to remove one route from the individual while keeping it feasible. Returns the
+    /// evaluated individual with one route less if successful.
+    pub fn run(
+        &mut self,
+        ctx: &Context,
+        individual: &Individual,
+        number: u64,
+    ) -> Option<Individual> {
+        let mut routes: Vec<Vec<usize>> = individual
+            .phenotype
+            .iter()
+            .filter(|route| !route.is_empty())
+            .cloned()
+            .collect();
+
+        // The number of routes can not go below the bin packing bound
+        if routes.len() as u64 <= ctx.vehicle_lower_bound().max(1) {
+            return None;
+        }
+
+        // Remove the route with the fewest customers and put them in the ejection pool
+        let smallest = (0..routes.len())
+            .min_by_key(|&index| routes[index].len())
+            .expect("No routes");
+        let mut pool = routes.remove(smallest);
+        self.reset(ctx, &routes);
+
+        let (max_iterations, perturbation_moves) = {
+            let config = ctx.config.borrow();
+            (
+                config.route_elimination_max_iterations,
+                config.route_elimination_perturbation_moves,
+            )
+        };
+
+        for _ in 0..max_iterations {
+            let customer = match pool.pop() {
+                Some(customer) => customer,
+                None => break,
+            };
+            if ctx.terminate() {
+                pool.push(customer);
+                break;
+            }
+
+            if !self.insert_feasible(ctx, &mut routes, customer) {
+                self.penalties[customer] += 1;
+                self.insert_with_ejection(ctx, &mut routes, customer, &mut pool);
+                self.perturb(ctx, &mut routes, perturbation_moves);
+            }
+        }
+
+        if !pool.is_empty() {
+            return None;
+        }
+
+        // Create the individual from the remaining routes
+        let genotype = routes.iter().flatten().copied().collect();
+        let mut child = Individual::new(genotype, number);
+        child.phenotype = routes;
+        child
+            .phenotype
+            .resize(ctx.config.borrow().num_vehicles as usize, Vec::new());
+        child.sort_routes(ctx);
+        child.evaluate(ctx);
+        Some(child)
+    }
+
+    /// Reset the search state to the routes, where customers outside the routes are in the pool
+    fn reset(&mut self, ctx: &Context, routes: &[Vec<usize>]) {
+        for penalty in self.penalties.iter_mut() {
+            *penalty = 1;
+        }
+        for route_index in self.route_of.iter_mut() {
+            *route_index = IN_POOL;
+        }
+        self.loads = routes
+            .iter()
+            .map(|route| route.iter().map(|&c| ctx.problem.nodes[c].demand).sum())
+            .collect();
+        for route_index in 0..routes.len() {
+            self.update_route(routes, route_index);
+        }
+    }
+
+    fn update_route(&mut self, routes: &[Vec<usize>], route_index: usize) {
+        for &customer in routes[route_index].iter() {
+            self.route_of[customer] = route_index;
+        }
+    }
+
+    /// Candidate routes for the customer, which are the routes of the correlated customers
+    fn candidate_routes(&self, ctx: &Context, customer: usize) -> Vec<usize> {
+        let granularity = ctx.config.borrow().local_search_granularity as usize;
+        let mut candidates: Vec<usize> = ctx
+            .matrix_provider
+            .correlation
+            .top_slice(customer, granularity)
+            .iter()
+            .map(|&neighbor| self.route_of[neighbor])
+            .filter(|&route_index| route_index != IN_POOL)
+            .collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+
+    /// Insert the customer at the cheapest feasible position. Returns false if there is none.
+    fn insert_feasible(
+        &mut self,
+        ctx: &Context,
+        routes: &mut [Vec<usize>],
+        customer: usize,
+    ) -> bool {
+        let capacity = ctx.problem.vehicle.cap;
+        let demand = ctx.problem.nodes[customer].demand;
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        for (route_index, route) in routes.iter().enumerate() {
+            if self.loads[route_index] + demand > capacity {
+                continue;
+            }
+            let (cost, position) = cheapest_position(ctx, route, customer);
+            if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, route_index, position));
+            }
+        }
+
+        match best {
+            Some((_, route_index, position)) => {
+                routes[route_index].insert(position, customer);
+                self.loads[route_index] += demand;
+                self.update_route(routes, route_index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert the customer into a candidate route and eject customers to restore feasibility,
+    /// minimizing the sum of penalties of the ejected customers and then the distance
+    fn insert_with_ejection(
+        &mut self,
+        ctx: &Context,
+        routes: &mut [Vec<usize>],
+        customer: usize,
+        pool: &mut Vec<usize>,
+    ) {
+        let distance = &ctx.matrix_provider.distance;
+        let capacity = ctx.problem.vehicle.cap;
+        let demand = ctx.problem.nodes[customer].demand;
+        let max_ejections = ctx.config.borrow().route_elimination_max_ejections as usize;
+
+        let mut candidates = self.candidate_routes(ctx, customer);
+        if candidates.is_empty() {
+            candidates = (0..routes.len()).collect();
+        }
+
+        // (penalty sum, distance, route index, ejected positions)
+        let mut best: Option<(u64, f64, usize, Vec<usize>)> = None;
+        for &route_index in candidates.iter() {
+            let route = &routes[route_index];
+            let excess = self.loads[route_index] + demand - capacity;
+            let (insert_cost, _) = cheapest_position(ctx, route, customer);
+
+            let removal: Vec<f64> = (0..route.len())
+                .map(|position| {
+                    let prev = if position == 0 {
+                        0
+                    } else {
+                        route[position - 1]
+                    };
+                    let next = route.get(position + 1).copied().unwrap_or(0);
+                    distance.get(prev, next)
+                        - distance.get(prev, route[position])
+                        - distance.get(route[position], next)
+                })
+                .collect();
+
+            let search = EjectionSearch {
+                route,
+                removal,
+                excess,
+                insert_cost,
+                route_index,
+                max_ejections,
+            };
+            let mut ejected = Vec::with_capacity(max_ejections);
+            self.search_ejections(ctx, &search, &mut ejected, &mut best);
+        }
+
+        match best {
+            Some((_, _, route_index, ejected)) => {
+                // Eject the customers, starting from the back to preserve the positions
+                for &position in ejected.iter().rev() {
+                    let ejected_customer = routes[route_index].remove(position);
+                    self.loads[route_index] -= ctx.problem.nodes[ejected_customer].demand;
+                    self.route_of[ejected_customer] = IN_POOL;
+                    pool.push(ejected_customer);
+                }
+                let (_, position) = cheapest_position(ctx, &routes[route_index], customer);
+                routes[route_index].insert(position, customer);
+                self.loads[route_index] += demand;
+                self.update_route(routes, route_index);
+            }
+            // No ejection restores feasibility, put the customer back in the pool
+            None => pool.insert(0, customer),
+        }
+    }
+
+    /// Enumerate the sets of at most `max_ejections` customers in lexicographic order, pruning
+    /// sets with a larger penalty sum than the best found
+    fn search_ejections(
+        &self,
+        ctx: &Context,
+        search: &EjectionSearch,
+        ejected: &mut Vec<usize>,
+        best: &mut Option<(u64, f64, usize, Vec<usize>)>,
+    ) {
+        let route = search.route;
+        let start = ejected.last().map_or(0, |&position| position + 1);
+        for position in start..route.len() {
+            ejected.push(position);
+
+            let penalty: u64 = ejected.iter().map(|&p| self.penalties[route[p]]).sum();
+            let is_pruned = best
+                .as_ref()
+                .is_some_and(|(best_penalty, _, _, _)| penalty > *best_penalty);
+
+            if !is_pruned {
+                let ejected_demand: f64 = ejected
+                    .iter()
+                    .map(|&p| ctx.problem.nodes[route[p]].demand)
+                    .sum();
+                if ejected_demand >= search.excess {
+                    // Approximate the distance with independent removals
+                    let cost = search.insert_cost
+                        + ejected.iter().map(|&p| search.removal[p]).sum::<f64>();
+                    let is_better =
+                        best.as_ref()
+                            .map_or(true, |(best_penalty, best_cost, _, _)| {
+                                penalty < *best_penalty
+                                    || (penalty == *best_penalty && cost < *best_cost)
+                            });
+                    if is_better {
+                        *best = Some((penalty, cost, search.route_index, ejected.clone()));
+                    }
+                } else if ejected.len() < search.max_ejections {
+                    self.search_ejections(ctx, search, ejected, best);
+                }
+            }
+
+            ejected.pop();
+        }
+    }
+
+    /// Relocate random customers to feasible positions next to one of their correlated
+    /// customers in another route
+    fn perturb(&mut self, ctx: &Context, routes: &mut [Vec<usize>], moves: u64) {
+        let capacity = ctx.problem.vehicle.cap;
+        let granularity = ctx.config.borrow().local_search_granularity as usize;
+        let num_customers = ctx.problem.num_customers();
+        for _ in 0..moves {
+            let customer = ctx.random.range_usize(1, num_customers + 1);
+            let from = self.route_of[customer];
+            if from == IN_POOL {
+                continue;
+            }
+            let correlated = ctx
+                .matrix_provider
+                .correlation
+                .top_slice(customer, granularity);
+            let neighbor = correlated[ctx.random.range_usize(0, correlated.len())];
+            let to = self.route_of[neighbor];
+            let demand = ctx.problem.nodes[customer].demand;
+            if to == IN_POOL || to == from || self.loads[to] + demand > capacity {
+                continue;
+            }
+
+            // Move the customer directly after its correlated customer
+            let position = routes[from]
+                .iter()
+                .position(|&c| c == customer)
+                .expect("Customer not in route");
+            routes[from].remove(position);
+            let neighbor_position = routes[to]
+                .iter()
+                .position(|&c| c == neighbor)
+                .expect("Customer not in route");
+            routes[to].insert(neighbor_position + 1, customer);
+            self.loads[from] -= demand;
+            self.loads[to] += demand;
+            self.route_of[customer] = to;
+        }
+    }
+}
+
+/// A route where a customer is inserted and customers are ejected
+struct EjectionSearch<'a> {
+    route: &'a [usize],
+    // Change in distance when removing each customer from the route
+    removal: Vec<f64>,
+    // Load exceeding the capacity after the insertion
+    excess: f64,
+    insert_cost: f64,
+    route_index: usize,
+    max_ejections: usize,
+}
+
+/// Cheapest insertion cost and position of the customer in the route
+fn cheapest_position(ctx: &Context, route: &[usize], customer: usize) -> (f64, usize) {
+    let distance = &ctx.matrix_provider.distance;
+    let mut best = (f64::INFINITY, 0);
+    for position in 0..=route.len() {
+        let prev = if position == 0 {
+            0
+        } else {
+            route[position - 1]
+        };
+        let next = route.get(position).copied().unwrap_or(0);
+        let cost =
+            distance.get(prev, customer) + distance.get(customer, next) - distance.get(prev, next);
+        if cost < best.0 {
+            best = (cost, position);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::test_context;
+
+    // Seven customers with unit demand on a circle around the depot, and vehicles of capacity 3
+    fn context() -> Context {
+        let points: Vec<(f64, f64)> = (0..8)
+            .map(|id| match id {
+                0 => (0.0, 0.0),
+                _ => {
+                    let angle = id as f64 * std::f64::consts::PI / 4.0;
+                    (10.0 * angle.cos(), 10.0 * angle.sin())
+                }
+            })
+            .collect();
+        test_context(&points, &[1.0; 7], 3.0, Config::default())
+    }
+
+    // Assert that the routes and the pool hold every customer exactly once, and that the routes
+    // are within the capacity
+    fn assert_valid(ctx: &Context, routes: &[Vec<usize>], pool: &[usize]) {
+        let mut customers: Vec<usize> = routes
+            .iter()
+            .flatten()
+            .chain(pool.iter())
+            .copied()
+            .collect();
+        customers.sort_unstable();
+        assert_eq!((1..=7).collect::<Vec<usize>>(), customers);
+        for route in routes.iter() {
+            let load: f64 = route.iter().map(|&c| ctx.problem.nodes[c].demand).sum();
+            assert!(load <= ctx.problem.vehicle.cap);
+        }
+    }
+
+    #[test]
+    fn run_removes_route() {
+        let ctx = context();
+        let mut individual = Individual::new_random(&ctx, 0);
+        individual
+            .phenotype
+            .iter_mut()
+            .for_each(|route| route.clear());
+        individual.phenotype[0] = vec![1, 2];
+        individual.phenotype[1] = vec![3, 4];
+        individual.phenotype[2] = vec![5, 6];
+        individual.phenotype[3] = vec![7];
+        individual.evaluate(&ctx);
+
+        let mut re = RouteElimination::new(&ctx);
+        let child = re.run(&ctx, &individual, 1).expect("No route removed");
+        assert_eq!(3, child.num_nonempty_routes());
+        assert!(child.is_feasible());
+        assert_valid(&ctx, &child.phenotype, &[]);
+
+        // The bin packing bound of three routes is reached
+        assert!(re.run(&ctx, &child, 2).is_none());
+    }
+
+    #[test]
+    fn insert_with_ejection_restores_capacity() {
+        let ctx = context();
+        let mut routes = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let mut re = RouteElimination::new(&ctx);
+        re.reset(&ctx, &routes);
+
+        // Customer 5 is the only one which has not been hard to insert
+        for penalty in re.penalties.iter_mut() {
+            *penalty = 10;
+        }
+        re.penalties[5] = 1;
+
+        let mut pool = Vec::new();
+        re.insert_with_ejection(&ctx, &mut routes, 7, &mut pool);
+        assert_eq!(vec![5], pool);
+        assert!(routes[1].contains(&7));
+        assert_valid(&ctx, &routes, &pool);
+        assert_eq!(vec![3.0, 3.0], re.loads);
+        assert_eq!(IN_POOL, re.route_of[5]);
+        assert_eq!(1, re.route_of[7]);
+    }
+
+    #[test]
+    fn search_ejections_covers_excess() {
+        let ctx = context();
+        let routes = vec![vec![1, 2, 3]];
+        let mut re = RouteElimination::new(&ctx);
+        re.reset(&ctx, &routes);
+        re.penalties[1] = 2;
+        re.penalties[2] = 2;
+        re.penalties[3] = 3;
+
+        // Two customers must be ejected, where 1 and 2 have the smallest penalty sum
+        let search = EjectionSearch {
+            route: &routes[0],
+            removal: vec![0.0; 3],
+            excess: 2.0,
+            insert_cost: 0.0,
+            route_index: 0,
+            max_ejections: 3,
+        };
+        let mut best = None;
+        re.search_ejections(&ctx, &search, &mut Vec::new(), &mut best);
+        let (penalty, _, route_index, ejected) = best.expect("No ejection");
+        assert_eq!(4, penalty);
+        assert_eq!(0, route_index);
+        assert_eq!(vec![0, 1], ejected);
+
+        // Not enough customers can be ejected
+        let search = EjectionSearch {
+            max_ejections: 1,
+            ..search
+        };
+        let mut best = None;
+        re.search_ejections(&ctx, &search, &mut Vec::new(), &mut best);
+        assert!(best.is_none());
+    }
+
+    #[test]
+    fn perturb_keeps_customers_and_capacity() {
+        let ctx = context();
+        let mut routes = vec![vec![1, 2], vec![3, 4, 5], vec![6, 7]];
+        let mut re = RouteElimination::new(&ctx);
+        re.reset(&ctx, &routes);
+
+        re.perturb(&ctx, &mut routes, 100);
+        assert_valid(&ctx, &routes, &[]);
+        for (route_index, route) in routes.iter().enumerate() {
+            assert_eq!(route.len() as f64, re.loads[route_index]);
+            for &customer in route.iter() {
+                assert_eq!(route_index, re.route_of[customer]);
+            }
+        }
+    }
+}
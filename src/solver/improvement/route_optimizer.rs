@@ -0,0 +1,459 @@
+use crate::models::DistanceMatrix;
+use crate::solver::genetic::Individual;
+use crate::solver::Context;
+use crate::utils::FloatCompare;
+
+// Upper limit on the route size solved with Held-Karp, as the tables grow with 2^n * n
+const MAX_EXACT_SIZE: usize = 16;
+
+// Maximum length of the segments moved by Or-opt
+const OR_OPT_MAX_LENGTH: usize = 3;
+
+/// Optimizes the order of the customers within routes.
+///
+/// Routes with at most `tsp_exact_size_limit` customers are solved to optimality with the
+/// Held-Karp dynamic program. Longer routes are improved with 2-opt, Or-opt and 3-opt until none
+/// of the moves improve the route, so they are only 3-opt optimal and not necessarily optimal.
+#[derive(Debug)]
+pub struct RouteOptimizer {
+    // Held-Karp tables indexed by the set of visited customers and the last visited customer,
+    // which grow with the largest route solved
+    cost: Vec<f64>,
+    parent: Vec<u8>,
+
+    // Largest route solved with Held-Karp
+    exact_size_limit: usize,
+}
+
+impl RouteOptimizer {
+    pub fn new(ctx: &Context) -> Self {
+        Self::with_exact_size_limit(ctx.config.borrow().tsp_exact_size_limit as usize)
+    }
+
+    pub fn with_exact_size_limit(exact_size_limit: usize) -> Self {
+        Self {
+            cost: Vec::new(),
+            parent: Vec::new(),
+            exact_size_limit: exact_size_limit.min(MAX_EXACT_SIZE),
+        }
+    }
+
+    /// Optimize all the routes of the individual. Returns true if any route is improved, in
+    /// which case the individual is reevaluated.
+    pub fn run(&mut self, ctx: &Context, individual: &mut Individual) -> bool {
+        let distance = &ctx.matrix_provider.distance;
+        let mut improved = false;
+        for route in individual.phenotype.iter_mut() {
+            improved |= self.optimize_route(distance, route);
+        }
+
+        if improved {
+            individual.genotype = individual.phenotype.iter().flatten().copied().collect();
+            individual.evaluate(ctx);
+        }
+        improved
+    }
+
+    /// Optimize the order of the customers in the route. Returns true if the route is improved.
+    pub fn optimize_route(&mut self, distance: &DistanceMatrix, route: &mut Vec<usize>) -> bool {
+        if route.len() < 2 {
+            return false;
+        }
+        let old_distance = route_distance(distance, route);
+        if route.len() <= self.exact_size_limit {
+            let optimal = self.held_karp(distance, route);
+            if route_distance(distance, &optimal).approx_lt(old_distance) {
+                *route = optimal;
+                return true;
+            }
+            false
+        } else {
+            let mut tour = Vec::with_capacity(route.len() + 2);
+            tour.push(0);
+            tour.extend(route.iter());
+            tour.push(0);
+            let mut improved = false;
+            // The cheaper moves are tried first, and 3-opt only at their local optimum
+            while two_opt(distance, &mut tour)
+                || or_opt(distance, &mut tour)
+                || three_opt(distance, &mut tour)
+            {
+                improved = true;
+            }
+            if improved {
+                *route = tour[1..tour.len() - 1].to_vec();
+            }
+            improved
+        }
+    }
+
+    /// Solve the TSP over the depot and the customers in the route with dynamic programming
+    fn held_karp(&mut self, distance: &DistanceMatrix, route: &[usize]) -> Vec<usize> {
+        let size = route.len();
+        let full = (1usize << size) - 1;
+        let index = |set: usize, last: usize| set * size + last;
+
+        let table_size = (full + 1) * size;
+        if self.cost.len() < table_size {
+            self.cost.resize(table_size, f64::INFINITY);
+            self.parent.resize(table_size, 0);
+        }
+
+        // Paths from the depot visiting a single customer
+        for set in 1..=full {
+            for last in 0..size {
+                self.cost[index(set, last)] = f64::INFINITY;
+            }
+        }
+        for (last, &customer) in route.iter().enumerate() {
+            self.cost[index(1 << last, last)] = distance.get(0, customer);
+        }
+
+        // Extend the paths in order of increasing sets
+        for set in 1..=full {
+            for last in 0..size {
+                if set & (1 << last) == 0 {
+                    continue;
+                }
+                let cost = self.cost[index(set, last)];
+                if cost == f64::INFINITY {
+                    continue;
+                }
+                for next in 0..size {
+                    if set & (1 << next) != 0 {
+                        continue;
+                    }
+                    let next_set = set | (1 << next);
+                    let next_cost = cost + distance.get(route[last], route[next]);
+                    if next_cost < self.cost[index(next_set, next)] {
+                        self.cost[index(next_set, next)] = next_cost;
+                        self.parent[index(next_set, next)] = last as u8;
+                    }
+                }
+            }
+        }
+
+        // Close the tour at the depot
+        let mut last = (0..size)
+            .min_by(|&a, &b| {
+                let cost_a = self.cost[index(full, a)] + distance.get(route[a], 0);
+                let cost_b = self.cost[index(full, b)] + distance.get(route[b], 0);
+                cost_a.partial_cmp(&cost_b).unwrap()
+            })
+            .expect("Empty route");
+
+        // Backtrack the optimal order
+        let mut optimal = Vec::with_capacity(size);
+        let mut set = full;
+        loop {
+            optimal.push(route[last]);
+            let previous = self.parent[index(set, last)] as usize;
+            set &= !(1 << last);
+            if set == 0 {
+                break;
+            }
+            last = previous;
+        }
+        optimal.reverse();
+        optimal
+    }
+}
+
+/// Distance of a route starting and ending at the depot
+pub fn route_distance(distance: &DistanceMatrix, route: &[usize]) -> f64 {
+    let mut total = 0.0;
+    let mut prev = 0;
+    for &node in route.iter() {
+        total += distance.get(prev, node);
+        prev = node;
+    }
+    total + distance.get(prev, 0)
+}
+
+/// Perform the first improving 2-opt move on the tour. Returns true if a move is performed.
+///
+/// With asymmetric distances, the change in distance of the arcs within the reversed segment is
+/// included in the delta.
+fn two_opt(distance: &DistanceMatrix, tour: &mut [usize]) -> bool {
+    let symmetric = distance.is_symmetric();
+    let last = tour.len() - 1;
+    for i in 0..last - 1 {
+        // Change in distance from reversing the arcs between tour[i + 1] and tour[j]
+        let mut reversal = 0.0;
+        for j in i + 2..last {
+            if !symmetric {
+                reversal += distance.get(tour[j], tour[j - 1]) - distance.get(tour[j - 1], tour[j]);
+            }
+            let delta = distance.get(tour[i], tour[j]) + distance.get(tour[i + 1], tour[j + 1])
+                - distance.get(tour[i], tour[i + 1])
+                - distance.get(tour[j], tour[j + 1])
+                + reversal;
+            if delta.approx_lt(0.0) {
+                tour[i + 1..=j].reverse();
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Perform the first improving Or-opt move on the tour, moving a segment of at most three
+/// customers to another position, possibly reversed. Returns true if a move is performed.
+fn or_opt(distance: &DistanceMatrix, tour: &mut Vec<usize>) -> bool {
+    let last = tour.len() - 1;
+    for length in 1..=OR_OPT_MAX_LENGTH {
+        for start in 1..last.saturating_sub(length - 1) {
+            let end = start + length - 1;
+            let (prev, first, end_node, next) =
+                (tour[start - 1], tour[start], tour[end], tour[end + 1]);
+            let removal =
+                distance.get(prev, next) - distance.get(prev, first) - distance.get(end_node, next);
+            // Change in distance from reversing the arcs within the segment, which is zero for
+            // symmetric distances
+            let reversal: f64 = (start..end)
+                .map(|k| distance.get(tour[k + 1], tour[k]) - distance.get(tour[k], tour[k + 1]))
+                .sum();
+
+            // Insert between tour[position] and tour[position + 1] outside the segment
+            for position in (0..last).filter(|&p| p + 1 < start || p > end) {
+                let (a, b) = (tour[position], tour[position + 1]);
+                let forward = distance.get(a, first) + distance.get(end_node, b);
+                let reversed = distance.get(a, end_node) + distance.get(first, b) + reversal;
+                let insertion = forward.min(reversed) - distance.get(a, b);
+                if (removal + insertion).approx_lt(0.0) {
+                    let mut segment: Vec<usize> = tour.drain(start..=end).collect();
+                    if reversed < forward {
+                        segment.reverse();
+                    }
+                    let insert_at = if position < start {
+                        position + 1
+                    } else {
+                        position + 1 - length
+                    };
+                    tour.splice(insert_at..insert_at, segment);
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Perform the first improving pure 3-opt move on the tour, which replaces three arcs such that
+/// the two segments between them are exchanged, reversed, or both. Returns true if a move is
+/// performed.
+///
+/// With asymmetric distances, the change in distance of the arcs within the reversed segments is
+/// included in the delta.
+fn three_opt(distance: &DistanceMatrix, tour: &mut Vec<usize>) -> bool {
+    let symmetric = distance.is_symmetric();
+    // Change in distance from reversing the arc into tour[to]
+    let reversal = |to: usize| -> f64 {
+        match symmetric {
+            true => 0.0,
+            false => distance.get(tour[to], tour[to - 1]) - distance.get(tour[to - 1], tour[to]),
+        }
+    };
+
+    // The segments B = tour[i + 1..=j] and C = tour[j + 1..=k] lie between the removed arcs
+    // (a, b1), (b2, c1) and (c2, d)
+    let last = tour.len() - 1;
+    for i in 0..last {
+        let (a, b1) = (tour[i], tour[i + 1]);
+        let mut reversal_b = 0.0;
+        for j in i + 1..last {
+            if j > i + 1 {
+                reversal_b += reversal(j);
+            }
+            let (b2, c1) = (tour[j], tour[j + 1]);
+            let mut reversal_c = 0.0;
+            for k in j + 1..last {
+                if k > j + 1 {
+                    reversal_c += reversal(k);
+                }
+                let (c2, d) = (tour[k], tour[k + 1]);
+                let removed = distance.get(a, b1) + distance.get(b2, c1) + distance.get(c2, d);
+
+                // The reconnections A C B D, A C B' D, A C' B D and A B' C' D
+                let candidates = [
+                    distance.get(a, c1) + distance.get(c2, b1) + distance.get(b2, d),
+                    distance.get(a, c1) + distance.get(c2, b2) + distance.get(b1, d) + reversal_b,
+                    distance.get(a, c2) + distance.get(c1, b1) + distance.get(b2, d) + reversal_c,
+                    distance.get(a, b2)
+                        + distance.get(b1, c2)
+                        + distance.get(c1, d)
+                        + reversal_b
+                        + reversal_c,
+                ];
+                let (variant, added) = candidates
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .min_by(|x, y| x.1.partial_cmp(&y.1).unwrap())
+                    .unwrap();
+                if (added - removed).approx_lt(0.0) {
+                    let mut segment_b = tour[i + 1..=j].to_vec();
+                    let mut segment_c = tour[j + 1..=k].to_vec();
+                    let (first, second) = match variant {
+                        0 => (segment_c, segment_b),
+                        1 => {
+                            segment_b.reverse();
+                            (segment_c, segment_b)
+                        }
+                        2 => {
+                            segment_c.reverse();
+                            (segment_c, segment_b)
+                        }
+                        _ => {
+                            segment_b.reverse();
+                            segment_c.reverse();
+                            (segment_b, segment_c)
+                        }
+                    };
+                    tour.splice(i + 1..=k, first.into_iter().chain(second));
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Matrix;
+
+    fn distance_matrix(points: &[(f64, f64)]) -> DistanceMatrix {
+        let mut storage = Matrix::new(points.len(), points.len());
+        for (i, a) in points.iter().enumerate() {
+            for (j, b) in points.iter().enumerate() {
+                storage.set(
+                    i,
+                    j,
+                    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt().round(),
+                );
+            }
+        }
+        DistanceMatrix::from_input(storage, true, None)
+    }
+
+    fn brute_force(distance: &DistanceMatrix, route: &mut Vec<usize>, k: usize, best: &mut f64) {
+        if k == route.len() {
+            *best = best.min(route_distance(distance, route));
+            return;
+        }
+        for i in k..route.len() {
+            route.swap(k, i);
+            brute_force(distance, route, k + 1, best);
+            route.swap(k, i);
+        }
+    }
+
+    #[test]
+    fn held_karp_is_optimal() {
+        let points: Vec<(f64, f64)> = (0..9)
+            .map(|i| ((i * 37 % 23) as f64 * 10.0, (i * 53 % 29) as f64 * 10.0))
+            .collect();
+        let distance = distance_matrix(&points);
+        let mut route: Vec<usize> = (1..9).collect();
+
+        let mut optimal = f64::INFINITY;
+        brute_force(&distance, &mut route.clone(), 0, &mut optimal);
+
+        let mut optimizer = RouteOptimizer::with_exact_size_limit(12);
+        optimizer.optimize_route(&distance, &mut route);
+        assert_eq!(optimal, route_distance(&distance, &route));
+
+        let mut sorted = route.clone();
+        sorted.sort();
+        assert_eq!((1..9).collect::<Vec<usize>>(), sorted);
+    }
+
+    #[test]
+    fn local_search_keeps_customers() {
+        let points: Vec<(f64, f64)> = (0..30)
+            .map(|i| ((i * 37 % 41) as f64 * 10.0, (i * 53 % 43) as f64 * 10.0))
+            .collect();
+        let distance = distance_matrix(&points);
+        let mut route: Vec<usize> = (1..30).collect();
+        let old_distance = route_distance(&distance, &route);
+
+        let mut optimizer = RouteOptimizer::with_exact_size_limit(12);
+        assert!(optimizer.optimize_route(&distance, &mut route));
+        assert!(route_distance(&distance, &route) < old_distance);
+
+        let mut sorted = route.clone();
+        sorted.sort();
+        assert_eq!((1..30).collect::<Vec<usize>>(), sorted);
+    }
+
+    #[test]
+    fn moves_improve_asymmetric_routes() {
+        let points: Vec<(f64, f64)> = (0..30)
+            .map(|i| ((i * 37 % 41) as f64 * 10.0, (i * 53 % 43) as f64 * 10.0))
+            .collect();
+        // Going to a customer with a lower index costs more than the way back
+        let mut storage = Matrix::new(points.len(), points.len());
+        for (i, a) in points.iter().enumerate() {
+            for (j, b) in points.iter().enumerate() {
+                let euclidean = ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+                storage.set(i, j, euclidean + if j < i { 100.0 } else { 0.0 });
+            }
+        }
+        let distance = DistanceMatrix::from_input(storage, false, None);
+        assert!(!distance.is_symmetric());
+
+        // Every move performed decreases the distance of the tour
+        let mut tour: Vec<usize> = std::iter::once(0)
+            .chain((1..30).rev())
+            .chain(std::iter::once(0))
+            .collect();
+        let mut moves = 0;
+        loop {
+            let old_distance = route_distance(&distance, &tour[1..tour.len() - 1]);
+            if !(two_opt(&distance, &mut tour)
+                || or_opt(&distance, &mut tour)
+                || three_opt(&distance, &mut tour))
+            {
+                break;
+            }
+            moves += 1;
+            assert!(route_distance(&distance, &tour[1..tour.len() - 1]) < old_distance);
+        }
+        assert!(moves > 0);
+    }
+
+    #[test]
+    fn no_improving_three_opt_move_remains() {
+        let points: Vec<(f64, f64)> = (0..25)
+            .map(|i| ((i * 37 % 41) as f64 * 10.0, (i * 53 % 43) as f64 * 10.0))
+            .collect();
+        let distance = distance_matrix(&points);
+        let mut route: Vec<usize> = (1..25).collect();
+
+        let mut optimizer = RouteOptimizer::with_exact_size_limit(12);
+        assert!(optimizer.optimize_route(&distance, &mut route));
+
+        // Exchanging any two adjacent segments of the optimized route does not shorten it
+        let tour: Vec<usize> = std::iter::once(0)
+            .chain(route.iter().copied())
+            .chain(std::iter::once(0))
+            .collect();
+        let route_distance_of =
+            |tour: &[usize]| route_distance(&distance, &tour[1..tour.len() - 1]);
+        let best = route_distance_of(&tour);
+        let last = tour.len() - 1;
+        for i in 0..last {
+            for j in i + 1..last {
+                for k in j + 1..last {
+                    let mut exchanged = tour[..=i].to_vec();
+                    exchanged.extend_from_slice(&tour[j + 1..=k]);
+                    exchanged.extend_from_slice(&tour[i + 1..=j]);
+                    exchanged.extend_from_slice(&tour[k + 1..]);
+                    assert!(!route_distance_of(&exchanged).approx_lt(best));
+                }
+            }
+        }
+    }
+}
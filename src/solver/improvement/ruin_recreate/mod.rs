@@ -147,7 +147,7 @@ impl RuinRecreate {
     pub fn setup_elite_education(&mut self, ctx: &Context) {
         let config = ctx.config.borrow();
         if config.elite_education_time_based {
-            let duration_f64 = config.time_limit as f64 * config.elite_education_time_fraction;
+            let duration_f64 = config.time_limit_secs() * config.elite_education_time_fraction;
             let duration = Duration::from_secs_f64(duration_f64);
             self.acceptance = TimeSchedule::new(config.elite_education_start_temp, duration).into();
         } else {
@@ -192,11 +192,18 @@ impl RuinRecreate {
     fn update_best(&mut self) {
         self.best_solution = Some(self.current_solution.clone());
         let mut search_history = self.ctx.search_history.borrow_mut();
+        let num_routes = self
+            .current_solution
+            .routes
+            .iter()
+            .filter(|route| !route.nodes.is_empty())
+            .count();
         if self.current_solution.is_feasible()
-            && self
-                .current_solution
-                .cost
-                .approx_lt(search_history.best_cost)
+            && search_history.is_new_best(
+                self.ctx.config.borrow().fleet_minimization,
+                num_routes,
+                self.current_solution.cost,
+            )
         {
             let mut best_individual = Individual::new_random(self.ctx, 0);
             self.update_individual(&self.current_solution, &mut best_individual);
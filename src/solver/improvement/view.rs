@@ -0,0 +1,161 @@
+use crate::solver::improvement::{LinkArena, LinkNode, LinkRoute, NodeId, RouteId};
+
+/// Read-only view of a route in the local search.
+///
+/// This is the stable interface for moves computing deltas, while the fields of the underlying
+/// `LinkRoute` may change between versions.
+#[derive(Clone, Copy)]
+pub struct RouteView<'a> {
+    arena: &'a LinkArena,
+    id: RouteId,
+    route: &'a LinkRoute,
+}
+
+impl<'a> RouteView<'a> {
+    pub(crate) fn new(arena: &'a LinkArena, id: RouteId) -> Self {
+        Self {
+            arena,
+            id,
+            route: &arena[id],
+        }
+    }
+
+    pub fn id(&self) -> RouteId {
+        self.id
+    }
+
+    /// Index of the route in the solution
+    pub fn index(&self) -> usize {
+        self.route.index
+    }
+
+    /// Depot node at the start of the route
+    pub fn start_depot(&self) -> NodeView<'a> {
+        NodeView::new(self.arena, self.route.start_depot)
+    }
+
+    /// Depot node at the end of the route
+    pub fn end_depot(&self) -> NodeView<'a> {
+        NodeView::new(self.arena, self.route.end_depot)
+    }
+
+    pub fn num_customers(&self) -> usize {
+        self.route.num_customers
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.route.is_empty()
+    }
+
+    /// Total distance of the route
+    pub fn distance(&self) -> f64 {
+        self.route.distance
+    }
+
+    /// Total demand of the customers in the route
+    pub fn load(&self) -> f64 {
+        self.route.load
+    }
+
+    /// Load exceeding the vehicle capacity, which is negative for routes with spare capacity
+    pub fn overload(&self) -> f64 {
+        self.route.overload
+    }
+
+    /// Distance plus the penalty for the excess load
+    pub fn cost(&self) -> f64 {
+        self.route.cost
+    }
+
+    /// Move count when the route was last modified
+    pub fn last_modified(&self) -> i32 {
+        self.route.last_modified
+    }
+
+    /// Customers of the route in order, up to the first depot or missing node
+    pub fn customers(&self) -> impl Iterator<Item = NodeView<'a>> + 'a {
+        let arena = self.arena;
+        let mut node = arena[self.route.start_depot].successor;
+        std::iter::from_fn(move || {
+            if node.is_none() || arena[node].is_depot() {
+                return None;
+            }
+            let current = NodeView::new(arena, node);
+            node = arena[node].successor;
+            Some(current)
+        })
+    }
+}
+
+/// Read-only view of a customer or depot node in the local search.
+///
+/// The cumulative values are measured from the start depot of the route up to and including the
+/// node, and are updated whenever the route is updated.
+#[derive(Clone, Copy)]
+pub struct NodeView<'a> {
+    arena: &'a LinkArena,
+    id: NodeId,
+    node: &'a LinkNode,
+}
+
+impl<'a> NodeView<'a> {
+    pub(crate) fn new(arena: &'a LinkArena, id: NodeId) -> Self {
+        Self {
+            arena,
+            id,
+            node: &arena[id],
+        }
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Number of the customer in the problem, or zero for a depot
+    pub fn number(&self) -> usize {
+        self.node.number
+    }
+
+    pub fn is_depot(&self) -> bool {
+        self.node.is_depot()
+    }
+
+    /// The previous node in the route, which is missing for a start depot
+    pub fn predecessor(&self) -> Option<NodeView<'a>> {
+        match self.node.predecessor.is_none() {
+            true => None,
+            false => Some(NodeView::new(self.arena, self.node.predecessor)),
+        }
+    }
+
+    /// The next node in the route, which is missing for an end depot
+    pub fn successor(&self) -> Option<NodeView<'a>> {
+        match self.node.successor.is_none() {
+            true => None,
+            false => Some(NodeView::new(self.arena, self.node.successor)),
+        }
+    }
+
+    /// The route of the node, which is missing before the first solution is loaded
+    pub fn route(&self) -> Option<RouteView<'a>> {
+        match self.node.route == RouteId::NONE {
+            true => None,
+            false => Some(RouteView::new(self.arena, self.node.route)),
+        }
+    }
+
+    /// Position in the route, where the start depot has position zero
+    pub fn position(&self) -> usize {
+        self.node.position
+    }
+
+    /// Distance from the start depot to the node
+    pub fn cum_distance(&self) -> f64 {
+        self.node.cum_distance
+    }
+
+    /// Total demand from the start depot to the node
+    pub fn cum_load(&self) -> f64 {
+        self.node.cum_load
+    }
+}
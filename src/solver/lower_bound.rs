@@ -0,0 +1,284 @@
+use instant::Instant;
+
+use crate::models::DistanceMatrix;
+use crate::solver::Context;
+
+// Initial factor of the subgradient step size
+const INITIAL_STEP_FACTOR: f64 = 2.0;
+
+// Number of iterations without improvement before the step size factor is halved
+const HALVING_INTERVAL: u64 = 100;
+
+// The subgradient optimization stops when the step size factor is below this value
+const MIN_STEP_FACTOR: f64 = 1e-4;
+
+/// Lagrangian lower bound on the total distance, based on the K-tree relaxation of Fisher (1994).
+///
+/// Removing the depot from a solution with `m` routes leaves `m` paths over the customers with
+/// `n - m` edges in total, while the depot is incident to `2m` edges. Relaxing the degree
+/// constraints of the customers, the cheapest such edge sets are the `n - m` cheapest edges of a
+/// minimum spanning tree over the customers and the `2m` cheapest depot edges, where a customer
+/// can be connected twice to the depot. The number of routes is at least the bin packing bound.
+/// The degree constraints are dualized and the multipliers are set by subgradient optimization.
+pub struct LowerBound<'a> {
+    distance: &'a DistanceMatrix,
+    num_customers: usize,
+    min_routes: usize,
+
+    // Lagrangian multipliers of the degree constraints of the customers
+    multipliers: Vec<f64>,
+
+    // Degree of each customer in the last relaxed solution
+    degrees: Vec<i64>,
+}
+
+impl<'a> LowerBound<'a> {
+    pub fn new(distance: &'a DistanceMatrix, num_customers: usize, min_routes: usize) -> Self {
+        Self {
+            distance,
+            num_customers,
+            min_routes: min_routes.clamp(1, num_customers.max(1)),
+            multipliers: vec![0.0; num_customers + 1],
+            degrees: vec![0; num_customers + 1],
+        }
+    }
+
+    pub fn from_context(ctx: &'a Context) -> Self {
+        Self::new(
+            &ctx.matrix_provider.distance,
+            ctx.problem.num_customers(),
+            ctx.vehicle_lower_bound() as usize,
+        )
+    }
+
+    /// Maximize the Lagrangian bound with subgradient optimization, where the steps are scaled
+    /// by the distance to the cost of a known solution. Returns the best bound found, or None for
+    /// asymmetric distances, as the relaxation is over undirected edges.
+    pub fn run(&mut self, upper_bound: f64, max_iterations: u64, deadline: Instant) -> Option<f64> {
+        if !self.distance.is_symmetric() {
+            return None;
+        }
+        if self.num_customers == 0 {
+            return Some(0.0);
+        }
+
+        let mut best = f64::NEG_INFINITY;
+        let mut step_factor = INITIAL_STEP_FACTOR;
+        let mut iterations_without_improvement = 0;
+        for _ in 0..max_iterations {
+            let bound = self.relaxation();
+            if bound > best {
+                best = bound;
+                iterations_without_improvement = 0;
+            } else {
+                iterations_without_improvement += 1;
+                if iterations_without_improvement >= HALVING_INTERVAL {
+                    step_factor /= 2.0;
+                    iterations_without_improvement = 0;
+                }
+            }
+
+            // A relaxed solution with all degrees equal to two can not be improved
+            let norm: i64 = self.degrees[1..].iter().map(|&d| (d - 2).pow(2)).sum();
+            if norm == 0
+                || best >= upper_bound
+                || step_factor < MIN_STEP_FACTOR
+                || Instant::now() >= deadline
+            {
+                break;
+            }
+
+            let step = step_factor * (upper_bound - bound) / norm as f64;
+            for (multiplier, &degree) in self.multipliers.iter_mut().zip(self.degrees.iter()) {
+                *multiplier += step * (degree - 2) as f64;
+            }
+            self.multipliers[0] = 0.0;
+        }
+        Some(best)
+    }
+
+    /// Solve the relaxation with the current multipliers and update the degrees. Returns the
+    /// Lagrangian bound.
+    fn relaxation(&mut self) -> f64 {
+        let n = self.num_customers;
+        let distance = self.distance;
+        let multipliers = &self.multipliers;
+        let cost = |a: usize, b: usize| distance.get(a, b) + multipliers[a] + multipliers[b];
+
+        // Minimum spanning tree over the customers with Prim's algorithm
+        let mut in_tree = vec![false; n + 1];
+        let mut key: Vec<f64> = (0..=n).map(|node| cost(1, node)).collect();
+        let mut parent = vec![1; n + 1];
+        in_tree[1] = true;
+        let mut edges: Vec<(f64, usize, usize)> = Vec::with_capacity(n - 1);
+        for _ in 1..n {
+            let node = (2..=n)
+                .filter(|&node| !in_tree[node])
+                .min_by(|&a, &b| key[a].partial_cmp(&key[b]).unwrap())
+                .expect("No node left");
+            in_tree[node] = true;
+            edges.push((key[node], parent[node], node));
+            for next in 2..=n {
+                if !in_tree[next] {
+                    let next_cost = cost(node, next);
+                    if next_cost < key[next] {
+                        key[next] = next_cost;
+                        parent[next] = node;
+                    }
+                }
+            }
+        }
+        edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        // Each customer can be connected twice to the depot
+        let mut depot_edges: Vec<(f64, usize)> = (1..=n)
+            .flat_map(|customer| {
+                let edge = (distance.get(0, customer) + multipliers[customer], customer);
+                [edge, edge]
+            })
+            .collect();
+        depot_edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        // Choose the number of routes with the cheapest relaxed solution
+        let prefix_sums = |costs: &mut dyn Iterator<Item = f64>| {
+            let mut sums = vec![0.0];
+            for cost in costs {
+                sums.push(sums.last().unwrap() + cost);
+            }
+            sums
+        };
+        let forest_costs = prefix_sums(&mut edges.iter().map(|edge| edge.0));
+        let depot_costs = prefix_sums(&mut depot_edges.iter().map(|edge| edge.0));
+        let (routes, relaxed_cost) = (self.min_routes..=n)
+            .map(|routes| (routes, forest_costs[n - routes] + depot_costs[2 * routes]))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            .expect("No number of routes");
+
+        for degree in self.degrees.iter_mut() {
+            *degree = 0;
+        }
+        for &(_, a, b) in edges[..n - routes].iter() {
+            self.degrees[a] += 1;
+            self.degrees[b] += 1;
+        }
+        for &(_, customer) in depot_edges[..2 * routes].iter() {
+            self.degrees[customer] += 1;
+        }
+
+        relaxed_cost - 2.0 * self.multipliers.iter().sum::<f64>()
+    }
+}
+
+/// Radial bound of Haimovich and Rinnooy Kan (1985). A route costs at least the round trip to
+/// its farthest customer, which is at least the demand weighted average of the round trips to its
+/// customers, and the route load is at most the capacity.
+///
+/// The first step requires the triangle inequality, so the bound is only given for unrounded
+/// euclidean distances, and None for explicit or rounded distances.
+pub fn radial_lower_bound(ctx: &Context) -> Option<f64> {
+    let distance = &ctx.matrix_provider.distance;
+    if distance.is_explicit() || distance.is_rounded() {
+        return None;
+    }
+    let capacity = ctx.problem.vehicle.cap;
+    let bound = ctx
+        .problem
+        .nodes
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(customer, node)| {
+            let round_trip = distance.get(0, customer) + distance.get(customer, 0);
+            round_trip * node.demand / capacity
+        })
+        .sum();
+    Some(bound)
+}
+
+/// Relative gap in percent between the cost of a solution and a lower bound
+pub fn optimality_gap(cost: f64, lower_bound: f64) -> f64 {
+    100.0 * (cost - lower_bound) / cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::models::Matrix;
+    use crate::solver::testing::test_context;
+
+    fn distance_matrix(points: &[(f64, f64)]) -> DistanceMatrix {
+        let mut storage = Matrix::new(points.len(), points.len());
+        for (i, a) in points.iter().enumerate() {
+            for (j, b) in points.iter().enumerate() {
+                storage.set(i, j, ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt());
+            }
+        }
+        DistanceMatrix::from_input(storage, false, None)
+    }
+
+    #[test]
+    fn lower_bound_is_valid() {
+        // Depot in a corner of a square with the customers in the other corners
+        let points = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let distance = distance_matrix(&points);
+        let deadline = Instant::now() + std::time::Duration::from_secs(10);
+
+        // A single route visits the square
+        let bound = LowerBound::new(&distance, 3, 1)
+            .run(40.0, 1000, deadline)
+            .unwrap();
+        assert!((bound - 40.0).abs() < 1e-6);
+
+        // The best two routes have a cost of 40 + 10 * sqrt(2)
+        let optimal = 40.0 + 10.0 * 2f64.sqrt();
+        let bound = LowerBound::new(&distance, 3, 2)
+            .run(optimal, 1000, deadline)
+            .unwrap();
+        assert!(bound >= 50.0 - 1e-6);
+        assert!(bound <= optimal + 1e-6);
+    }
+
+    #[test]
+    fn asymmetric_distances_have_no_bound() {
+        let mut storage = Matrix::new(3, 3);
+        for i in 0..3 {
+            for j in 0..3 {
+                storage.set(i, j, if i < j { 1.0 } else { 2.0 * (i - j) as f64 });
+            }
+        }
+        let distance = DistanceMatrix::from_input(storage, false, None);
+        let deadline = Instant::now() + std::time::Duration::from_secs(10);
+        assert_eq!(
+            None,
+            LowerBound::new(&distance, 2, 1).run(4.0, 1000, deadline)
+        );
+    }
+
+    #[test]
+    fn radial_bound_requires_euclidean_distances() {
+        // Two customers at distance 5 from the depot, which fit in one route
+        let context = |round_distances: bool| {
+            let mut config = Config::default();
+            config.round_distances = round_distances;
+            test_context(
+                &[(0.0, 0.0), (3.0, 4.0), (-3.0, 4.0)],
+                &[1.0, 1.0],
+                2.0,
+                config,
+            )
+        };
+        assert_eq!(Some(10.0), radial_lower_bound(&context(false)));
+        assert_eq!(None, radial_lower_bound(&context(true)));
+
+        let mut ctx = context(false);
+        let mut storage = Matrix::new(3, 3);
+        for i in 0..3 {
+            for j in 0..3 {
+                storage.set(i, j, ctx.matrix_provider.distance.get(i, j));
+            }
+        }
+        ctx.matrix_provider.distance = DistanceMatrix::from_input(storage, false, None);
+        assert_eq!(None, radial_lower_bound(&ctx));
+    }
+}
@@ -1,11 +1,18 @@
 mod context;
 pub use self::context::*;
+pub mod dimacs;
 mod evaluate;
 pub use self::evaluate::*;
 mod solver;
 pub use self::solver::*;
+mod solve;
+pub use self::solve::*;
 mod history;
 pub use self::history::*;
+mod lower_bound;
+pub use self::lower_bound::*;
+#[cfg(test)]
+pub(crate) mod testing;
 
 pub mod genetic;
 pub mod improvement;
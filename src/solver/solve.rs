@@ -0,0 +1,404 @@
+use instant::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::models::{DistanceMatrixBuilder, Problem};
+use crate::solver::genetic::{DecomposedGeneticAlgorithm, GeneticAlgorithm};
+use crate::solver::improvement::route_distance;
+use crate::solver::{optimality_gap, Context, Solver};
+use crate::utils::FloatCompare;
+
+/// Parameters of the solver when used as a library.
+///
+/// The most common parameters are typed fields, while the remaining parameters are taken from
+/// `config`.
+#[derive(Clone, Debug)]
+pub struct Params {
+    /// Time limit in seconds
+    pub time_limit: u64,
+    /// Maximum number of iterations of the metaheuristic
+    pub max_iterations: Option<u64>,
+    pub max_iterations_without_improvement: u64,
+    /// Seed for a deterministic search, or `None` for a random seed
+    pub seed: Option<u64>,
+    /// Round the euclidean distances to the nearest integer
+    pub round_distances: bool,
+    /// Compute a lower bound and the optimality gap after the search
+    pub lower_bound: bool,
+    pub config: Config,
+}
+
+impl Params {
+    /// Typed parameters with the values of the config
+    pub fn from_config(config: Config) -> Self {
+        Self {
+            time_limit: config.time_limit,
+            max_iterations: config.max_iterations,
+            max_iterations_without_improvement: config.max_iterations_without_improvement,
+            seed: match config.deterministic {
+                true => Some(config.seed),
+                false => None,
+            },
+            round_distances: config.round_distances,
+            lower_bound: config.lower_bound,
+            config,
+        }
+    }
+
+    /// Config with the typed parameters applied
+    pub fn into_config(self) -> Config {
+        let mut config = self.config;
+        config.time_limit = self.time_limit;
+        config.max_iterations = self.max_iterations;
+        config.max_iterations_without_improvement = self.max_iterations_without_improvement;
+        config.deterministic = self.seed.is_some();
+        config.seed = self.seed.unwrap_or(config.seed);
+        config.round_distances = self.round_distances;
+        config.lower_bound = self.lower_bound;
+        config
+    }
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self::from_config(Config::default())
+    }
+}
+
+/// Route of a solution, where the customers are the indices of the nodes in the problem
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SolutionRoute {
+    pub customers: Vec<usize>,
+    pub load: f64,
+    pub distance: f64,
+}
+
+/// Statistics of the search
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Statistics {
+    pub iterations: u64,
+    pub runtime: Duration,
+    /// Time when the best solution was found
+    pub time_to_best: Option<Duration>,
+    /// Number of times a new best solution was found
+    pub improvements: usize,
+    pub lower_bound: Option<f64>,
+    /// Gap between the cost and the lower bound in percent
+    pub gap: Option<f64>,
+}
+
+/// Progress of the search, reported on every new best solution
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Progress {
+    pub cost: f64,
+    /// Time since the start of the search
+    pub time: Duration,
+    /// Iteration at which the solution was found
+    pub iteration: u64,
+}
+
+/// Best solution found by the solver
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Solution {
+    /// The non-empty routes, which exclude the depot
+    pub routes: Vec<SolutionRoute>,
+    /// Total distance of the routes, or infinity when no solution was found
+    pub cost: f64,
+    /// Whether all the routes are within the vehicle capacity
+    pub feasible: bool,
+    pub statistics: Statistics,
+}
+
+impl Solution {
+    /// Collect the best solution and the statistics of a completed search
+    pub fn from_context(ctx: &Context) -> Self {
+        let search_history = ctx.search_history.borrow();
+
+        let routes: Vec<SolutionRoute> = search_history
+            .last_entry()
+            .map(|entry| entry.solution.routes.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .filter(|route| !route.is_empty())
+            .map(|route| SolutionRoute {
+                customers: route.clone(),
+                load: route.iter().map(|&c| ctx.problem.nodes[c].demand).sum(),
+                distance: route_distance(&ctx.matrix_provider.distance, route),
+            })
+            .collect();
+
+        let (cost, feasible) = match search_history.last_entry() {
+            Some(_) => (
+                routes.iter().map(|route| route.distance).sum(),
+                routes
+                    .iter()
+                    .all(|route| route.load.approx_lte(ctx.problem.vehicle.cap)),
+            ),
+            None => (f64::INFINITY, false),
+        };
+
+        let lower_bound = search_history.lower_bound;
+        let statistics = Statistics {
+            iterations: *ctx.iteration.borrow(),
+            runtime: ctx.elapsed(),
+            time_to_best: search_history.last_entry().map(|entry| entry.timestamp),
+            improvements: search_history.entries().len(),
+            lower_bound,
+            gap: lower_bound
+                .filter(|_| cost.is_finite())
+                .map(|lower_bound| optimality_gap(cost, lower_bound)),
+        };
+
+        Self {
+            routes,
+            cost,
+            feasible,
+            statistics,
+        }
+    }
+}
+
+/// Solve a problem built in memory. Problems with at most one customer are solved directly,
+/// without running the search.
+pub fn solve(problem: Problem, params: Params) -> Solution {
+    solve_with_progress(problem, None, params, |_| true)
+}
+
+/// Solve a problem built in memory, with optional explicit distances in the lower row format of
+/// the parser. Every new best solution is reported to `progress`, which stops the search by
+/// returning false.
+pub fn solve_with_progress(
+    problem: Problem,
+    matrix: Option<Vec<Vec<f64>>>,
+    params: Params,
+    mut progress: impl FnMut(&Progress) -> bool,
+) -> Solution {
+    if problem.dim() < 3 {
+        return solve_trivial(problem, matrix, params, progress);
+    }
+    let ctx = Context::from_problem(problem, matrix, params.into_config(), Instant::now());
+    let mut reported = 0;
+    let ctx = run_solver_until(ctx, |ctx| {
+        !report_progress(ctx, &mut reported, &mut progress)
+    });
+    Solution::from_context(&ctx)
+}
+
+/// Solve a problem with at most one customer, which is served by a single route, without
+/// running the search
+fn solve_trivial(
+    problem: Problem,
+    matrix: Option<Vec<Vec<f64>>>,
+    params: Params,
+    mut progress: impl FnMut(&Progress) -> bool,
+) -> Solution {
+    let start = Instant::now();
+    let mut builder = DistanceMatrixBuilder::new()
+        .locations(problem.nodes.iter().map(|node| node.coord).collect())
+        .rounded(params.round_distances);
+    if let Some(matrix) = matrix {
+        builder = builder.input(matrix);
+    }
+    let distance = builder.build();
+
+    let routes: Vec<SolutionRoute> = (1..problem.dim())
+        .map(|customer| SolutionRoute {
+            customers: vec![customer],
+            load: problem.nodes[customer].demand,
+            distance: route_distance(&distance, &[customer]),
+        })
+        .collect();
+    let cost = routes.iter().map(|route| route.distance).sum();
+    let feasible = routes
+        .iter()
+        .all(|route| route.load.approx_lte(problem.vehicle.cap));
+    progress(&Progress {
+        cost,
+        time: start.elapsed(),
+        iteration: 0,
+    });
+
+    // The single route is optimal, so the cost is also the lower bound
+    let lower_bound = Some(cost).filter(|_| params.lower_bound);
+    Solution {
+        routes,
+        cost,
+        feasible,
+        statistics: Statistics {
+            iterations: 0,
+            runtime: start.elapsed(),
+            time_to_best: Some(Duration::from_secs(0)),
+            improvements: 1,
+            lower_bound,
+            gap: lower_bound.map(|lower_bound| optimality_gap(cost, lower_bound)),
+        },
+    }
+}
+
+/// Report the best solutions added to the search history after the first `reported` entries, and
+/// return false as soon as `progress` stops the search
+pub fn report_progress(
+    ctx: &Context,
+    reported: &mut usize,
+    progress: &mut impl FnMut(&Progress) -> bool,
+) -> bool {
+    let search_history = ctx.search_history.borrow();
+    while let Some(entry) = search_history.entries().get(*reported) {
+        *reported += 1;
+        let keep_going = progress(&Progress {
+            cost: entry.solution.cost,
+            time: entry.timestamp,
+            iteration: entry.iteration,
+        });
+        if !keep_going {
+            return false;
+        }
+    }
+    true
+}
+
+/// Run the metaheuristic suited to the size of the problem, and return the context holding the
+/// search history
+pub fn run_solver(ctx: Context) -> Context {
+    run_solver_until(ctx, |_| false)
+}
+
+/// Run the metaheuristic as in `run_solver`, where `stop` is checked after every iteration and
+/// ends the search early by returning true
+pub fn run_solver_until(ctx: Context, stop: impl FnMut(&Context) -> bool) -> Context {
+    let should_decompose =
+        ctx.problem.num_customers() as u64 >= ctx.config.borrow().decompose_limit;
+
+    match should_decompose {
+        true => {
+            log::info!("Algorithm: Decomposed Genetic Algorithm");
+            let metaheuristic = DecomposedGeneticAlgorithm::new(&ctx);
+            let mut solver = Solver::new(ctx, metaheuristic);
+            solver.run_until(stop);
+            solver.ctx
+        }
+        false => {
+            log::info!("Algorithm: Genetic Algorithm");
+            let metaheuristic = GeneticAlgorithm::new(&ctx);
+            let mut solver = Solver::new(ctx, metaheuristic);
+            solver.run_until(stop);
+            solver.ctx
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Coordinate, Node, ProblemBuilder, Vehicle};
+    use crate::solver::genetic::Individual;
+
+    fn problem() -> Problem {
+        let nodes = [
+            (0, 0, 0),
+            (10, 0, 2),
+            (10, 1, 2),
+            (0, 10, 1),
+            (1, 10, 2),
+            (-10, 0, 3),
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &(x, y, demand))| Node {
+            id,
+            coord: Coordinate {
+                lng: x as f64,
+                lat: y as f64,
+            },
+            demand: demand as f64,
+        })
+        .collect();
+        ProblemBuilder::new(nodes, Vehicle { id: 0, cap: 5.0 }).build()
+    }
+
+    #[test]
+    fn solve_in_memory_problem() {
+        let problem = problem();
+        let params = Params {
+            max_iterations: Some(50),
+            seed: Some(1),
+            ..Params::default()
+        };
+        let solution = solve(problem, params);
+
+        assert!(solution.feasible);
+        let mut customers: Vec<usize> = solution
+            .routes
+            .iter()
+            .flat_map(|route| route.customers.iter().cloned())
+            .collect();
+        customers.sort_unstable();
+        assert_eq!(vec![1, 2, 3, 4, 5], customers);
+        let distance: f64 = solution.routes.iter().map(|route| route.distance).sum();
+        assert_eq!(distance, solution.cost);
+        assert!(solution.statistics.improvements > 0);
+        assert_eq!(50, solution.statistics.iterations);
+    }
+
+    #[test]
+    fn solve_without_search() {
+        let nodes: Vec<Node> = problem().nodes.into_iter().take(2).collect();
+        let params = Params {
+            lower_bound: true,
+            ..Params::default()
+        };
+        let solution = solve(
+            ProblemBuilder::new(nodes.clone(), Vehicle { id: 0, cap: 5.0 }).build(),
+            params.clone(),
+        );
+        assert_eq!(1, solution.routes.len());
+        assert_eq!(vec![1], solution.routes[0].customers);
+        assert_eq!(20.0, solution.cost);
+        assert!(solution.feasible);
+        assert_eq!(Some(0.0), solution.statistics.gap);
+
+        let depot = nodes[..1].to_vec();
+        let solution = solve(
+            ProblemBuilder::new(depot, Vehicle { id: 0, cap: 5.0 }).build(),
+            params,
+        );
+        assert!(solution.routes.is_empty());
+        assert_eq!(0.0, solution.cost);
+    }
+
+    #[test]
+    fn stop_from_progress() {
+        let params = Params {
+            seed: Some(1),
+            ..Params::default()
+        };
+        let mut reported = Vec::new();
+        let solution = solve_with_progress(problem(), None, params, |progress| {
+            reported.push(progress.cost);
+            false
+        });
+
+        assert_eq!(vec![solution.cost], reported);
+        assert_eq!(1, solution.statistics.improvements);
+    }
+
+    #[test]
+    fn progress_at_iteration_of_solution() {
+        let ctx = Context::from_problem(problem(), None, Config::default(), Instant::now());
+        let individual = Individual::new_random(&ctx, 0);
+        for iteration in [3, 7].iter() {
+            *ctx.iteration.borrow_mut() = *iteration;
+            ctx.search_history.borrow_mut().add(&ctx, &individual);
+        }
+        *ctx.iteration.borrow_mut() = 10;
+
+        let mut iterations = Vec::new();
+        let mut reported = 0;
+        assert!(report_progress(&ctx, &mut reported, &mut |progress| {
+            iterations.push(progress.iteration);
+            true
+        }));
+        assert_eq!(vec![3, 7], iterations);
+        assert_eq!(2, reported);
+    }
+}
@@ -1,4 +1,6 @@
-use crate::solver::Context;
+use instant::{Duration, Instant};
+
+use crate::solver::{optimality_gap, radial_lower_bound, Context, LowerBound};
 
 pub trait Metaheuristic {
     fn iterate(&mut self, ctx: &Context);
@@ -22,9 +24,63 @@ where
     }
 
     pub fn run(&mut self) {
+        self.run_until(|_| false);
+    }
+
+    /// Run the search, where `stop` is checked after every iteration and ends the search early by
+    /// returning true
+    pub fn run_until(&mut self, mut stop: impl FnMut(&Context) -> bool) {
         while !self.metaheuristic.terminated() {
             self.metaheuristic.iterate(&self.ctx);
+            if stop(&self.ctx) {
+                break;
+            }
         }
         log::info!("Time: {:?}, Completed", self.ctx.elapsed());
+
+        if self.ctx.config.borrow().lower_bound {
+            self.compute_lower_bound();
+        }
+    }
+
+    /// Compute a lower bound on the distance and report the gap to the best solution
+    fn compute_lower_bound(&mut self) {
+        let best_cost = self.ctx.search_history.borrow().best_cost;
+        if !best_cost.is_finite() {
+            return;
+        }
+
+        let (max_iterations, time_limit, round_distances) = {
+            let config = self.ctx.config.borrow();
+            (
+                config.lower_bound_max_iterations,
+                config.lower_bound_time_limit,
+                config.round_distances,
+            )
+        };
+        let deadline = Instant::now() + Duration::from_secs_f64(time_limit);
+        let lagrangian =
+            LowerBound::from_context(&self.ctx).run(best_cost, max_iterations, deadline);
+        let mut lower_bound = match (lagrangian, radial_lower_bound(&self.ctx)) {
+            (Some(lagrangian), Some(radial)) => lagrangian.max(radial),
+            (Some(bound), None) | (None, Some(bound)) => bound,
+            (None, None) => {
+                log::info!("No lower bound for asymmetric distances");
+                return;
+            }
+        };
+
+        // The cost of a solution is integer with rounded distances
+        if round_distances {
+            lower_bound = (lower_bound - 1e-6).ceil();
+        }
+
+        log::info!(
+            "Time: {:?}, Lower bound: {:.2}, gap: {:.2}%",
+            self.ctx.elapsed(),
+            lower_bound,
+            optimality_gap(best_cost, lower_bound)
+        );
+        self.ctx.search_history.borrow_mut().lower_bound = Some(lower_bound);
     }
 }
@@ -0,0 +1,55 @@
+use instant::Instant;
+
+use crate::config::Config;
+use crate::models::{Coordinate, Node, ProblemBuilder, Vehicle};
+use crate::solver::genetic::{Individual, Split};
+use crate::solver::Context;
+
+/// Deterministic context for a problem with the depot at the first point and a customer at each
+/// of the other points, with the given demands
+pub fn test_context(
+    points: &[(f64, f64)],
+    demands: &[f64],
+    capacity: f64,
+    mut config: Config,
+) -> Context {
+    assert_eq!(points.len(), demands.len() + 1);
+    let nodes = points
+        .iter()
+        .enumerate()
+        .map(|(id, &(lng, lat))| Node {
+            id,
+            coord: Coordinate { lng, lat },
+            demand: if id == 0 { 0.0 } else { demands[id - 1] },
+        })
+        .collect();
+    let vehicle = Vehicle {
+        id: 0,
+        cap: capacity,
+    };
+    let problem = ProblemBuilder::new(nodes, vehicle).build();
+    config.deterministic = true;
+    Context::from_problem(problem, None, config, Instant::now())
+}
+
+/// Twelve customers spread around the depot, where a vehicle serves three or four of them
+pub fn spread_context(config: Config) -> Context {
+    let points: Vec<(f64, f64)> = (0..=12)
+        .map(|id| (((id * 7) % 13) as f64 - 6.0, ((id * 5) % 11) as f64 - 5.0))
+        .collect();
+    let demands: Vec<f64> = (1..=12).map(|id| (id % 3 + 1) as f64).collect();
+    test_context(&points, &demands, 7.0, config)
+}
+
+/// Random individuals split into routes
+pub fn random_individuals(ctx: &Context, count: u64) -> Vec<Individual> {
+    let mut split = Split::new(ctx);
+    let num_vehicles = ctx.config.borrow().num_vehicles;
+    (0..count)
+        .map(|number| {
+            let mut individual = Individual::new_random(ctx, number);
+            split.run(ctx, &mut individual, num_vehicles);
+            individual
+        })
+        .collect()
+}
@@ -0,0 +1,502 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::bench::{bench_instance, instance_name};
+use crate::config::Config;
+use crate::utils::Random;
+
+// Quantiles of the standard normal distribution used by the tests at the 5% significance level
+const NORMAL_QUANTILE_95: f64 = 1.644_853_6;
+const NORMAL_QUANTILE_975: f64 = 1.959_964;
+
+/// Values which a tuned parameter can take
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParameterDomain {
+    // Integer in [min, max]
+    Integer { min: i64, max: i64 },
+    // Real number in [min, max]
+    Real { min: f64, max: f64 },
+    // One of the values, such as booleans or selection strategies
+    Categorical { values: Vec<Value> },
+}
+
+/// Config fields to tune and their domains, read from YAML such as
+///
+/// ```yaml
+/// parameters:
+///   population_lambda: { type: integer, min: 10, max: 80 }
+///   rr_gamma: { type: real, min: 0.5, max: 2.0 }
+///   parent_selection: { type: categorical, values: [tournament, rank] }
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParameterSpace {
+    pub parameters: BTreeMap<String, ParameterDomain>,
+}
+
+/// Values of the tuned config fields
+pub type Parameters = BTreeMap<String, Value>;
+
+impl ParameterSpace {
+    pub fn load_yaml_file(filepath: &str) -> Self {
+        let file = File::open(filepath)
+            .unwrap_or_else(|err| panic!("Failed to open parameter space {}: {}", filepath, err));
+        let space: Self = serde_yaml::from_reader(BufReader::new(file))
+            .unwrap_or_else(|err| panic!("Invalid parameter space {}: {}", filepath, err));
+        space.check(&Config::default());
+        space
+    }
+
+    /// Panic on parameters which are not config fields or have empty domains
+    pub fn check(&self, config: &Config) {
+        let config: Value = serde_yaml::to_value(config).expect("Failed to serialize config");
+        assert!(!self.parameters.is_empty(), "The parameter space is empty");
+        for (name, domain) in self.parameters.iter() {
+            assert!(config.get(name).is_some(), "Unknown config field: {}", name);
+            let valid = match domain {
+                ParameterDomain::Integer { min, max } => min <= max,
+                ParameterDomain::Real { min, max } => min <= max,
+                ParameterDomain::Categorical { values } => !values.is_empty(),
+            };
+            assert!(valid, "Empty domain of parameter {}", name);
+        }
+    }
+
+    /// Sample every parameter uniformly from its domain
+    pub fn sample_uniform(&self, random: &Random) -> Parameters {
+        self.parameters
+            .iter()
+            .map(|(name, domain)| {
+                let value = match domain {
+                    ParameterDomain::Integer { min, max } => {
+                        let offset = random.range_usize(0, (max - min) as usize + 1);
+                        Value::from(min + offset as i64)
+                    }
+                    ParameterDomain::Real { min, max } => {
+                        Value::from(min + random.real() * (max - min))
+                    }
+                    ParameterDomain::Categorical { values } => {
+                        values[random.range_usize(0, values.len())].clone()
+                    }
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Sample around the parent, where numbers are normally distributed with a standard deviation
+    /// of `spread` times the range, and categories change with probability `spread`
+    pub fn sample_around(&self, parent: &Parameters, spread: f64, random: &Random) -> Parameters {
+        self.parameters
+            .iter()
+            .map(|(name, domain)| {
+                let value = match domain {
+                    ParameterDomain::Integer { min, max } => {
+                        let center = parent[name].as_i64().expect("Integer parameter") as f64;
+                        let value = center + normal(random) * spread * (max - min) as f64;
+                        Value::from((value.round() as i64).max(*min).min(*max))
+                    }
+                    ParameterDomain::Real { min, max } => {
+                        let center = parent[name].as_f64().expect("Real parameter");
+                        let value = center + normal(random) * spread * (max - min);
+                        Value::from(value.max(*min).min(*max))
+                    }
+                    ParameterDomain::Categorical { values } => match random.real() < spread {
+                        true => values[random.range_usize(0, values.len())].clone(),
+                        false => parent[name].clone(),
+                    },
+                };
+                (name.clone(), value)
+            })
+            .collect()
+    }
+}
+
+/// Apply the parameters to the config
+pub fn apply_parameters(config: &mut Config, parameters: &Parameters) {
+    let mut mapping = Mapping::new();
+    for (name, value) in parameters.iter() {
+        mapping.insert(Value::String(name.clone()), value.clone());
+    }
+    config.update(&Value::Mapping(mapping));
+}
+
+// Standard normal sample with the Box-Muller transform
+fn normal(random: &Random) -> f64 {
+    let u = 1.0 - random.real();
+    let v = random.real();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+/// Settings of the racing
+#[derive(Clone, Debug)]
+pub struct TuneSettings {
+    // Maximum number of solver runs
+    pub budget: usize,
+    // Number of blocks every candidate is run on before the first elimination test
+    pub first_test: usize,
+}
+
+/// Best configuration found by the racing
+#[derive(Clone, Debug)]
+pub struct TuneResult {
+    pub parameters: Parameters,
+    // Mean cost of the best configuration over the blocks it was run on
+    pub mean_cost: f64,
+    pub blocks: usize,
+    pub runs: usize,
+}
+
+/// Iterated racing as in irace, where each iteration samples new candidate configurations around
+/// the elites of the previous iteration, and races them over blocks of an instance and a seed.
+/// Candidates are eliminated when the Friedman test finds them worse than the best candidate.
+pub struct Tuner {
+    base_config: Config,
+    space: ParameterSpace,
+    // Instance path and seed of each block
+    blocks: Vec<(String, u64)>,
+    settings: TuneSettings,
+    random: Random,
+    candidates: Vec<Parameters>,
+    // Cost of the candidate on the block, by candidate and block index
+    costs: HashMap<(usize, usize), f64>,
+    runs: usize,
+}
+
+impl Tuner {
+    pub fn new(
+        base_config: Config,
+        space: ParameterSpace,
+        instance_paths: &[String],
+        seeds: &[u64],
+        settings: TuneSettings,
+    ) -> Self {
+        space.check(&base_config);
+        // Alternate the instances, so the first tests see as many instances as possible
+        let blocks = seeds
+            .iter()
+            .flat_map(|&seed| instance_paths.iter().map(move |path| (path.clone(), seed)))
+            .collect();
+        let random = Random::from_seed(base_config.seed);
+        Self {
+            base_config,
+            space,
+            blocks,
+            settings,
+            random,
+            candidates: Vec::new(),
+            costs: HashMap::new(),
+            runs: 0,
+        }
+    }
+
+    /// Race the candidates within the budget and return the best configuration
+    pub fn run(&mut self) -> TuneResult {
+        let num_parameters = self.space.parameters.len();
+        let num_iterations = 2 + (num_parameters as f64).log2().floor() as usize;
+        let num_elites = num_iterations;
+
+        let mut elites: Vec<usize> = Vec::new();
+        let mut tested_blocks = 0;
+        for iteration in 1..=num_iterations {
+            let remaining = self.settings.budget - self.runs;
+            let iteration_budget = remaining / (num_iterations - iteration + 1);
+            let num_candidates = iteration_budget / (self.settings.first_test + iteration.min(5));
+            if num_candidates < 2 {
+                break;
+            }
+            // Keep room for at least one new candidate when the budget is small
+            elites.truncate(num_candidates - 1);
+
+            let spread = 0.5
+                * (1.0 / num_candidates as f64)
+                    .powf((iteration - 1) as f64 / num_parameters as f64);
+            let mut alive = elites.clone();
+            for _ in elites.len()..num_candidates {
+                let parameters = match elites.is_empty() {
+                    true => self.space.sample_uniform(&self.random),
+                    false => {
+                        let parent = &self.candidates[self.select_elite(&elites)];
+                        self.space.sample_around(parent, spread, &self.random)
+                    }
+                };
+                self.candidates.push(parameters);
+                alive.push(self.candidates.len() - 1);
+            }
+
+            let (survivors, tested) = self.race(alive, iteration_budget, num_elites);
+            tested_blocks = tested;
+            elites = survivors.into_iter().take(num_elites).collect();
+            log::info!(
+                "Iteration {}: {} candidates, {} blocks, {} runs, best mean cost {:.2}",
+                iteration,
+                num_candidates,
+                tested,
+                self.runs,
+                self.mean_cost(elites[0], tested)
+            );
+        }
+
+        let best = *elites
+            .first()
+            .expect("The budget is too small for a single race");
+        TuneResult {
+            parameters: self.candidates[best].clone(),
+            mean_cost: self.mean_cost(best, tested_blocks),
+            blocks: tested_blocks,
+            runs: self.runs,
+        }
+    }
+
+    // Race the candidates over the blocks, and return the survivors by their mean rank and the
+    // number of blocks they were all run on
+    fn race(
+        &mut self,
+        mut alive: Vec<usize>,
+        budget: usize,
+        min_survivors: usize,
+    ) -> (Vec<usize>, usize) {
+        let start_runs = self.runs;
+        let mut tested = 0;
+        for block in 0..self.blocks.len() {
+            let missing: Vec<usize> = alive
+                .iter()
+                .cloned()
+                .filter(|&candidate| !self.costs.contains_key(&(candidate, block)))
+                .collect();
+            if self.runs - start_runs + missing.len() > budget {
+                break;
+            }
+            for candidate in missing {
+                let cost = self.evaluate(candidate, block);
+                self.costs.insert((candidate, block), cost);
+            }
+            tested = block + 1;
+
+            if tested >= self.settings.first_test.max(2) && alive.len() > min_survivors {
+                let costs = self.cost_table(&alive, tested);
+                alive = friedman_survivors(&costs)
+                    .into_iter()
+                    .map(|index| alive[index])
+                    .collect();
+            }
+            if alive.len() <= 1 {
+                break;
+            }
+        }
+
+        if tested == 0 {
+            return (alive, 0);
+        }
+        let rank_sums = rank_sums(&self.cost_table(&alive, tested));
+        let mut order: Vec<usize> = (0..alive.len()).collect();
+        order.sort_by(|&a, &b| rank_sums[a].partial_cmp(&rank_sums[b]).unwrap());
+        (
+            order.into_iter().map(|index| alive[index]).collect(),
+            tested,
+        )
+    }
+
+    // Solve the block with the candidate, where infeasible solutions have an infinite cost
+    fn evaluate(&mut self, candidate: usize, block: usize) -> f64 {
+        let (instance_path, seed) = self.blocks[block].clone();
+        let mut config = self.base_config.clone();
+        apply_parameters(&mut config, &self.candidates[candidate]);
+        config.instance_path = instance_path;
+        config.seed = seed;
+        config.deterministic = true;
+
+        let result = bench_instance(config);
+        self.runs += 1;
+        log::debug!(
+            "Candidate {} on {} with seed {}: {}",
+            candidate,
+            instance_name(&self.blocks[block].0),
+            seed,
+            result.cost
+        );
+        match result.feasible {
+            true => result.cost,
+            false => f64::INFINITY,
+        }
+    }
+
+    // Costs of the candidates on the first blocks, by block and candidate
+    fn cost_table(&self, candidates: &[usize], blocks: usize) -> Vec<Vec<f64>> {
+        (0..blocks)
+            .map(|block| {
+                candidates
+                    .iter()
+                    .map(|&candidate| self.costs[&(candidate, block)])
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn mean_cost(&self, candidate: usize, blocks: usize) -> f64 {
+        let total: f64 = (0..blocks)
+            .map(|block| self.costs[&(candidate, block)])
+            .sum();
+        total / blocks.max(1) as f64
+    }
+
+    // Select an elite as parent, with weights decreasing linearly with the rank
+    fn select_elite(&self, elites: &[usize]) -> usize {
+        let num_elites = elites.len();
+        let total = (num_elites * (num_elites + 1) / 2) as f64;
+        let mut target = self.random.real() * total;
+        for (rank, &elite) in elites.iter().enumerate() {
+            target -= (num_elites - rank) as f64;
+            if target < 0.0 {
+                return elite;
+            }
+        }
+        elites[num_elites - 1]
+    }
+}
+
+// Ranks of the values from 1, where ties get the average of their ranks
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].partial_cmp(&values[b]).unwrap());
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &index in order[start..end].iter() {
+            ranks[index] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+// Sum of the ranks of each candidate over the blocks
+fn rank_sums(costs: &[Vec<f64>]) -> Vec<f64> {
+    let mut sums = vec![0.0; costs[0].len()];
+    for block in costs.iter() {
+        for (sum, rank) in sums.iter_mut().zip(ranks(block)) {
+            *sum += rank;
+        }
+    }
+    sums
+}
+
+/// Indices of the candidates which survive the Friedman test on the costs by block and candidate.
+///
+/// When the test finds a difference, the candidates with a rank sum significantly worse than the
+/// best are eliminated by the Conover post-hoc test, as in F-race.
+pub fn friedman_survivors(costs: &[Vec<f64>]) -> Vec<usize> {
+    let num_blocks = costs.len() as f64;
+    let num_candidates = costs[0].len();
+    let k = num_candidates as f64;
+    let all = (0..num_candidates).collect();
+    if num_candidates < 2 || costs.len() < 2 {
+        return all;
+    }
+
+    let rank_sums = rank_sums(costs);
+    let squared_ranks: f64 = costs
+        .iter()
+        .flat_map(|block| ranks(block))
+        .map(|rank| rank * rank)
+        .sum();
+    let tie_correction = num_blocks * k * (k + 1.0).powi(2) / 4.0;
+    if squared_ranks - tie_correction <= 0.0 {
+        return all;
+    }
+
+    let expected = num_blocks * (k + 1.0) / 2.0;
+    let statistic = (k - 1.0)
+        * rank_sums
+            .iter()
+            .map(|sum| (sum - expected).powi(2))
+            .sum::<f64>()
+        / (squared_ranks - tie_correction);
+    if statistic <= chi_squared_quantile_95(k - 1.0) {
+        return all;
+    }
+
+    let degrees = (num_blocks - 1.0) * (k - 1.0);
+    let sum_squared_sums: f64 = rank_sums.iter().map(|sum| sum * sum).sum();
+    let critical = t_quantile_975(degrees)
+        * (2.0 * (num_blocks * squared_ranks - sum_squared_sums) / degrees).sqrt();
+    let best = rank_sums.iter().cloned().fold(f64::INFINITY, f64::min);
+    (0..num_candidates)
+        .filter(|&candidate| rank_sums[candidate] - best <= critical)
+        .collect()
+}
+
+// Wilson-Hilferty approximation of the 95% quantile of the chi-squared distribution
+fn chi_squared_quantile_95(degrees: f64) -> f64 {
+    let variance = 2.0 / (9.0 * degrees);
+    degrees * (1.0 - variance + NORMAL_QUANTILE_95 * variance.sqrt()).powi(3)
+}
+
+// Cornish-Fisher expansion of the 97.5% quantile of the t-distribution
+fn t_quantile_975(degrees: f64) -> f64 {
+    let z = NORMAL_QUANTILE_975;
+    z + (z.powi(3) + z) / (4.0 * degrees)
+        + (5.0 * z.powi(5) + 16.0 * z.powi(3) + 3.0 * z) / (96.0 * degrees.powi(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn friedman_elimination() {
+        // No difference between the candidates
+        let costs = vec![
+            vec![1.0, 2.0, 3.0],
+            vec![3.0, 1.0, 2.0],
+            vec![2.0, 3.0, 1.0],
+        ];
+        assert_eq!(vec![0, 1, 2], friedman_survivors(&costs));
+
+        // The last candidate is always the worst
+        let costs: Vec<Vec<f64>> = (0..8)
+            .map(|block| match block % 2 {
+                0 => vec![1.0, 2.0, 3.0, 9.0],
+                _ => vec![2.0, 1.0, 3.0, 9.0],
+            })
+            .collect();
+        let survivors = friedman_survivors(&costs);
+        assert!(survivors.contains(&0) && survivors.contains(&1));
+        assert!(!survivors.contains(&3));
+    }
+
+    #[test]
+    fn sample_within_domain() {
+        let space: ParameterSpace = serde_yaml::from_str(
+            "parameters:
+              population_lambda: { type: integer, min: 10, max: 20 }
+              rr_gamma: { type: real, min: 0.5, max: 2.0 }
+              parent_selection: { type: categorical, values: [tournament, rank] }",
+        )
+        .unwrap();
+        space.check(&Config::default());
+
+        let random = Random::from_seed(1);
+        let parent = space.sample_uniform(&random);
+        for _ in 0..100 {
+            let parameters = space.sample_around(&parent, 0.5, &random);
+            let lambda = parameters["population_lambda"].as_i64().unwrap();
+            let gamma = parameters["rr_gamma"].as_f64().unwrap();
+            assert!((10..=20).contains(&lambda));
+            assert!((0.5..=2.0).contains(&gamma));
+
+            let mut config = Config::default();
+            apply_parameters(&mut config, &parameters);
+            assert_eq!(lambda as u64, config.population_lambda);
+        }
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{
+    Coordinate, DistanceMatrix, LowerRowMatrix, Node, Problem, ProblemBuilder, Vehicle,
+};
+use crate::solver::{Context, Solution};
+
+/// Format of problem and solution files
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum FileFormat {
+    // Detected from the file extension
+    Auto,
+    Tsplib,
+    Json,
+    // Only for solutions
+    Csv,
+    #[value(name = "geojson")]
+    GeoJson,
+}
+
+impl FileFormat {
+    /// Resolve `Auto` from the extension of the path, which is `.json`, `.csv` or `.geojson` for
+    /// the other formats than TSPLIB
+    pub fn resolve(self, path: &str) -> Self {
+        match self {
+            FileFormat::Auto => {
+                let extension = Path::new(path)
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase());
+                match extension.as_deref() {
+                    Some("json") => FileFormat::Json,
+                    Some("csv") => FileFormat::Csv,
+                    Some("geojson") => FileFormat::GeoJson,
+                    _ => FileFormat::Tsplib,
+                }
+            }
+            format => format,
+        }
+    }
+}
+
+/// Problem in the JSON format described in `docs/json_format.md`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsonProblem {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub capacity: f64,
+    // The first node is the depot
+    pub nodes: Vec<JsonNode>,
+    // Full matrix of distances between the nodes, replacing the euclidean distances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distances: Option<Vec<Vec<f64>>>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsonNode {
+    pub id: usize,
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+    #[serde(default)]
+    pub demand: f64,
+}
+
+impl JsonProblem {
+    /// Problem with the explicit distances in the lower row format of the parser as a full matrix
+    pub fn from_problem(
+        problem: &Problem,
+        name: Option<String>,
+        matrix: Option<&[Vec<f64>]>,
+    ) -> Self {
+        let distances = matrix.map(|matrix| {
+            let dimension = problem.dim();
+            let mut distances = vec![vec![0.0; dimension]; dimension];
+            for (i, row) in matrix.iter().enumerate() {
+                for (j, &distance) in row.iter().enumerate() {
+                    distances[i + 1][j] = distance;
+                    distances[j][i + 1] = distance;
+                }
+            }
+            distances
+        });
+        Self {
+            name,
+            capacity: problem.vehicle.cap,
+            nodes: problem
+                .nodes
+                .iter()
+                .map(|node| JsonNode {
+                    id: node.id,
+                    x: node.coord.lng,
+                    y: node.coord.lat,
+                    demand: node.demand,
+                })
+                .collect(),
+            distances,
+        }
+    }
+
+    /// Build the problem, and the explicit distances in the lower row format of the parser.
+    /// Returns an error for problems without customers, for a capacity which is not positive, for
+    /// duplicate node ids, for demands which are negative, not finite or above the capacity, and
+    /// for distances which are not a symmetric matrix over the nodes.
+    pub fn into_problem(self) -> Result<(Problem, Option<LowerRowMatrix>), String> {
+        if self.nodes.len() < 2 {
+            return Err("The problem has no customers".to_owned());
+        }
+        if !(self.capacity.is_finite() && self.capacity > 0.0) {
+            return Err(format!(
+                "The capacity must be positive, got {}",
+                self.capacity
+            ));
+        }
+        let mut ids = HashSet::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            if !ids.insert(node.id) {
+                return Err(format!("Duplicate node id {}", node.id));
+            }
+            if !(node.demand.is_finite() && node.demand >= 0.0) {
+                return Err(format!(
+                    "The demand of node {} must be finite and not negative, got {}",
+                    node.id, node.demand
+                ));
+            }
+            if node.demand > self.capacity {
+                return Err(format!(
+                    "The demand {} of node {} exceeds the capacity {}",
+                    node.demand, node.id, self.capacity
+                ));
+            }
+        }
+        let dimension = self.nodes.len();
+        let matrix = match self.distances {
+            Some(distances) if distances.iter().any(|row| row.len() != dimension) => {
+                return Err(format!(
+                    "The distance matrix must be {}x{}",
+                    dimension, dimension
+                ));
+            }
+            Some(distances) => Some(DistanceMatrix::lower_row(&distances.concat(), dimension)?),
+            None => None,
+        };
+
+        let nodes = self
+            .nodes
+            .into_iter()
+            .map(|node| Node {
+                id: node.id,
+                coord: Coordinate {
+                    lng: node.x,
+                    lat: node.y,
+                },
+                demand: node.demand,
+            })
+            .collect();
+        let vehicle = Vehicle {
+            id: 0,
+            cap: self.capacity,
+        };
+        Ok((ProblemBuilder::new(nodes, vehicle).build(), matrix))
+    }
+}
+
+/// Solution in the JSON format described in `docs/json_format.md`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsonSolution {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub problem: Option<String>,
+    pub cost: f64,
+    pub feasible: bool,
+    // Total load and number of stops of the routes
+    #[serde(default)]
+    pub load: f64,
+    #[serde(default)]
+    pub stops: usize,
+    pub routes: Vec<JsonRoute>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JsonMetadata>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsonRoute {
+    // Ids of the customers in the order they are visited, without the depot
+    pub customers: Vec<usize>,
+    pub load: f64,
+    pub distance: f64,
+    // Fraction of the capacity used by the load
+    #[serde(default)]
+    pub utilization: f64,
+    #[serde(default)]
+    pub stops: usize,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JsonMetadata {
+    pub iterations: u64,
+    pub runtime: f64,
+    pub time_to_best: Option<f64>,
+    pub improvements: usize,
+    pub lower_bound: Option<f64>,
+    pub gap: Option<f64>,
+    pub seed: Option<u64>,
+}
+
+impl JsonSolution {
+    /// Solution with the customers identified by the ids of the problem nodes
+    pub fn new(ctx: &Context, solution: &Solution) -> Self {
+        let config = ctx.config.borrow();
+        let statistics = &solution.statistics;
+        Self {
+            problem: Path::new(&config.instance_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            cost: solution.cost,
+            feasible: solution.feasible,
+            load: solution.routes.iter().map(|route| route.load).sum(),
+            stops: solution
+                .routes
+                .iter()
+                .map(|route| route.customers.len())
+                .sum(),
+            routes: solution
+                .routes
+                .iter()
+                .map(|route| JsonRoute {
+                    customers: route
+                        .customers
+                        .iter()
+                        .map(|&customer| ctx.problem.nodes[customer].id)
+                        .collect(),
+                    load: route.load,
+                    distance: route.distance,
+                    utilization: route.load / ctx.problem.vehicle.cap,
+                    stops: route.customers.len(),
+                })
+                .collect(),
+            // A solution which is only read, such as when converting it, has no run
+            metadata: match ctx.search_history.borrow().is_searched() {
+                true => Some(JsonMetadata {
+                    iterations: statistics.iterations,
+                    runtime: statistics.runtime.as_secs_f64(),
+                    time_to_best: statistics.time_to_best.map(|time| time.as_secs_f64()),
+                    improvements: statistics.improvements,
+                    lower_bound: statistics.lower_bound,
+                    gap: statistics.gap,
+                    seed: match config.deterministic {
+                        true => Some(config.seed),
+                        false => None,
+                    },
+                }),
+                false => None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problem_with_distances() {
+        let json = r#"{
+            "capacity": 10,
+            "nodes": [
+                {"id": 1},
+                {"id": 2, "demand": 4},
+                {"id": 3, "demand": 5}
+            ],
+            "distances": [[0, 2, 3], [2, 0, 4], [3, 4, 0]]
+        }"#;
+        let (problem, matrix) = serde_json::from_str::<JsonProblem>(json)
+            .expect("Invalid problem")
+            .into_problem()
+            .unwrap();
+        assert_eq!(3, problem.dim());
+        assert_eq!(10.0, problem.vehicle.cap);
+        assert_eq!(5.0, problem.nodes[2].demand);
+        assert_eq!(Some(vec![vec![2.0], vec![3.0, 4.0]]), matrix);
+    }
+
+    #[test]
+    fn invalid_problems_are_errors() {
+        let problem = |nodes: &str, distances: &str| {
+            let json = format!(
+                r#"{{"capacity": 10, "nodes": {}, "distances": {}}}"#,
+                nodes, distances
+            );
+            serde_json::from_str::<JsonProblem>(&json)
+                .unwrap()
+                .into_problem()
+                .map(|_| ())
+        };
+        let nodes = r#"[{"id": 1}, {"id": 2}, {"id": 3}]"#;
+        assert!(problem(nodes, "null").is_ok());
+        assert_eq!(
+            Err("The problem has no customers".to_owned()),
+            problem(r#"[{"id": 1}]"#, "null")
+        );
+        assert_eq!(
+            Err("The distance matrix must be 3x3".to_owned()),
+            problem(nodes, "[[0, 2, 3], [2, 0], [3, 4, 0]]")
+        );
+
+        assert_eq!(
+            Err("Duplicate node id 2".to_owned()),
+            problem(r#"[{"id": 1}, {"id": 2}, {"id": 2}]"#, "null")
+        );
+        assert_eq!(
+            Err("The demand of node 2 must be finite and not negative, got -1".to_owned()),
+            problem(r#"[{"id": 1}, {"id": 2, "demand": -1}, {"id": 3}]"#, "null")
+        );
+        assert_eq!(
+            Err("The demand 11 of node 3 exceeds the capacity 10".to_owned()),
+            problem(r#"[{"id": 1}, {"id": 2}, {"id": 3, "demand": 11}]"#, "null")
+        );
+        let zero_capacity = r#"{"capacity": 0, "nodes": [{"id": 1}, {"id": 2}]}"#;
+        assert_eq!(
+            Err("The capacity must be positive, got 0".to_owned()),
+            serde_json::from_str::<JsonProblem>(zero_capacity)
+                .unwrap()
+                .into_problem()
+                .map(|_| ())
+        );
+
+        // Asymmetric distances are not symmetrised
+        let err = problem(nodes, "[[0, 2, 3], [2, 0, 4], [5, 4, 0]]").unwrap_err();
+        assert!(err.starts_with("The distance matrix must be symmetric"));
+    }
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(FileFormat::Json, FileFormat::Auto.resolve("problem.JSON"));
+        assert_eq!(
+            FileFormat::Tsplib,
+            FileFormat::Auto.resolve("X-n101-k25.vrp")
+        );
+        assert_eq!(FileFormat::Json, FileFormat::Json.resolve("output.sol"));
+        assert_eq!(
+            FileFormat::GeoJson,
+            FileFormat::Auto.resolve("routes.geojson")
+        );
+        assert_eq!(FileFormat::Csv, FileFormat::Auto.resolve("stops.csv"));
+    }
+}
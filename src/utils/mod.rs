@@ -1,6 +1,9 @@
 mod comparison;
 pub use self::comparison::*;
 
+mod json;
+pub use self::json::*;
+
 mod parser;
 pub use self::parser::*;
 
@@ -9,3 +12,6 @@ pub use self::random::*;
 
 mod serializer;
 pub use self::serializer::*;
+
+mod validator;
+pub use self::validator::*;
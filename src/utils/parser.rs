@@ -5,6 +5,7 @@ use std::str::FromStr;
 
 use crate::config::Config;
 use crate::models::{Coordinate, Node, Problem, ProblemBuilder, Vehicle};
+use crate::utils::{FileFormat, JsonProblem};
 
 type Lines = Vec<Vec<String>>;
 
@@ -53,44 +54,72 @@ impl ProblemParser {
         }
     }
 
+    /// Parse the instance in the config, and panic if it cannot be parsed
     pub fn parse(&mut self, config: &mut Config) {
+        if let Err(err) = self.try_parse(config) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Parse the instance in the config, and return an error if it cannot be parsed
+    pub fn try_parse(&mut self, config: &Config) -> Result<(), String> {
         let filepath = Path::new(&config.instance_path);
-        assert!(
-            filepath.exists(),
-            "Cannot find instance file: {}",
-            filepath.display()
-        );
-
-        let lines = Self::read_file(&filepath);
-
-        let dimension = Self::parse_dimension(&lines);
-        let capacity = Self::parse_capacity(&lines);
-        let coords = Self::parse_coords(&lines, dimension);
-        let demands = Self::parse_demands(&lines, dimension);
+        if !filepath.exists() {
+            return Err(format!("Cannot find instance file: {}", filepath.display()));
+        }
+
+        match config.instance_format.resolve(&config.instance_path) {
+            FileFormat::Json => return self.parse_json(filepath),
+            FileFormat::Csv | FileFormat::GeoJson => {
+                return Err("Problems can only be read as TSPLIB or JSON".to_owned());
+            }
+            _ => {}
+        }
+
+        let lines = Self::read_file(&filepath)?;
+
+        let dimension = Self::parse_dimension(&lines)?;
+        let capacity = Self::parse_capacity(&lines)?;
+        let coords = Self::parse_coords(&lines, dimension)?;
+        let demands = Self::parse_demands(&lines, dimension)?;
         let nodes = Self::create_nodes(coords, demands);
         let vehicle = Self::create_vehicle(0, capacity);
 
-        let problem_builder = ProblemBuilder::new(nodes, vehicle);
-        let problem = problem_builder.build();
-        self.problem = Some(problem);
-
-        match Self::parse_edge_weight_type(&lines) {
-            EdgeWeightType::Euclidian2D => {}
-            EdgeWeightType::Explicit => match Self::parse_edge_weight_format(&lines) {
+        let matrix = match Self::parse_edge_weight_type(&lines)? {
+            EdgeWeightType::Euclidian2D => None,
+            EdgeWeightType::Explicit => match Self::parse_edge_weight_format(&lines)? {
                 EdgeWeightFormat::LowerRow => {
-                    let matrix = Self::parse_lower_row_matrix(&lines, dimension);
-                    self.matrix = Some(matrix);
+                    Some(Self::parse_lower_row_matrix(&lines, dimension)?)
                 }
             },
         };
+
+        let problem_builder = ProblemBuilder::new(nodes, vehicle);
+        let problem = problem_builder.build();
+        self.problem = Some(problem);
+        self.matrix = matrix;
+        Ok(())
     }
 
-    fn read_file(path: &Path) -> Lines {
-        let file = File::open(path).expect("Failed to open file");
+    /// Parse a problem in the JSON format
+    fn parse_json(&mut self, path: &Path) -> Result<(), String> {
+        let file = File::open(path).map_err(|err| format!("Failed to open file: {}", err))?;
+        let json_problem: JsonProblem = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| format!("Failed to parse JSON problem: {}", err))?;
+        let (problem, matrix) = json_problem
+            .into_problem()
+            .map_err(|err| format!("Invalid JSON problem: {}", err))?;
+        self.problem = Some(problem);
+        self.matrix = matrix;
+        Ok(())
+    }
+
+    fn read_file(path: &Path) -> Result<Lines, String> {
+        let file = File::open(path).map_err(|err| format!("Failed to open file: {}", err))?;
         let reader = BufReader::new(file);
         let line_strings: Vec<String> = reader.lines().filter_map(|line| line.ok()).collect();
 
-        line_strings
+        Ok(line_strings
             .iter()
             .map(|line| {
                 let line_values: Vec<String> = line
@@ -102,102 +131,95 @@ impl ProblemParser {
                     .collect();
                 line_values
             })
-            .collect()
+            .collect())
     }
 
-    fn parse_dimension(lines: &Lines) -> usize {
-        for line in lines.iter() {
-            if !line.is_empty() && line[0] == "DIMENSION" {
-                return line[1].parse::<usize>().expect("Failed to parse dimension");
-            }
-        }
-        panic!("Could not find DIMENSION");
+    /// Value at the index of a line, parsed as `T`
+    fn parse_value<T: FromStr>(line: &[String], index: usize, name: &str) -> Result<T, String> {
+        line.get(index)
+            .and_then(|value| value.parse::<T>().ok())
+            .ok_or_else(|| format!("Failed to parse {}", name))
     }
 
-    fn parse_capacity(lines: &Lines) -> f64 {
+    /// Value of the `key: value` line with the key
+    fn parse_key<T: FromStr>(lines: &Lines, key: &str, name: &str) -> Result<T, String> {
         for line in lines.iter() {
-            if !line.is_empty() && line[0] == "CAPACITY" {
-                return line[1].parse::<f64>().expect("Failed to parse capacity");
+            if !line.is_empty() && line[0] == key {
+                return Self::parse_value(line, 1, name);
             }
         }
-        panic!("Could not find CAPACITY");
+        Err(format!("Could not find {}", key))
     }
 
-    fn parse_edge_weight_type(lines: &Lines) -> EdgeWeightType {
-        for line in lines.iter() {
-            if !line.is_empty() && line[0] == "EDGE_WEIGHT_TYPE" {
-                return match EdgeWeightType::from_str(&line[1]) {
-                    Ok(edge_type) => edge_type,
-                    Err(err) => {
-                        panic!("{}", err);
-                    }
-                };
-            }
-        }
-        panic!("Could not find EDGE_WEIGHT_TYPE");
+    /// Lines following the line starting the section
+    fn section<'l>(
+        lines: &'l Lines,
+        section: &str,
+    ) -> Result<impl Iterator<Item = &'l Vec<String>>, String> {
+        lines
+            .iter()
+            .position(|line| !line.is_empty() && line[0] == section)
+            .map(|line_number| lines.iter().skip(line_number + 1))
+            .ok_or_else(|| format!("Could not find {}", section))
     }
 
-    fn parse_edge_weight_format(lines: &Lines) -> EdgeWeightFormat {
-        for line in lines.iter() {
-            if !line.is_empty() && line[0] == "EDGE_WEIGHT_FORMAT" {
-                return match EdgeWeightFormat::from_str(&line[1]) {
-                    Ok(edge_format) => edge_format,
-                    Err(err) => {
-                        panic!("{}", err);
-                    }
-                };
-            }
-        }
-        panic!("Could not find EDGE_WEIGHT_FORMAT");
-    }
-
-    fn parse_coords(lines: &Lines, number: usize) -> Vec<Coordinate> {
-        for (line_number, line) in lines.iter().enumerate() {
-            if !line.is_empty() && line[0] == "NODE_COORD_SECTION" {
-                return lines
-                    .iter()
-                    .skip(line_number + 1)
-                    .take(number)
-                    .map(|line| Coordinate {
-                        lng: line[1].parse::<f64>().expect("Failed to parse coordinate"),
-                        lat: line[2].parse::<f64>().expect("Failed to parse coordinate"),
-                    })
-                    .collect();
-            }
+    fn parse_dimension(lines: &Lines) -> Result<usize, String> {
+        Self::parse_key(lines, "DIMENSION", "dimension")
+    }
+
+    fn parse_capacity(lines: &Lines) -> Result<f64, String> {
+        Self::parse_key(lines, "CAPACITY", "capacity")
+    }
+
+    fn parse_edge_weight_type(lines: &Lines) -> Result<EdgeWeightType, String> {
+        Self::parse_key::<String>(lines, "EDGE_WEIGHT_TYPE", "edge weight type")
+            .and_then(|edge_type| EdgeWeightType::from_str(&edge_type))
+    }
+
+    fn parse_edge_weight_format(lines: &Lines) -> Result<EdgeWeightFormat, String> {
+        Self::parse_key::<String>(lines, "EDGE_WEIGHT_FORMAT", "edge weight format")
+            .and_then(|edge_format| EdgeWeightFormat::from_str(&edge_format))
+    }
+
+    fn parse_coords(lines: &Lines, number: usize) -> Result<Vec<Coordinate>, String> {
+        let coords: Vec<Coordinate> = Self::section(lines, "NODE_COORD_SECTION")?
+            .take(number)
+            .map(|line| {
+                Ok(Coordinate {
+                    lng: Self::parse_value(line, 1, "coordinate")?,
+                    lat: Self::parse_value(line, 2, "coordinate")?,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        match coords.len() == number {
+            true => Ok(coords),
+            false => Err(format!("Expected {} coordinates", number)),
         }
-        panic!("Could not find NODE_COORD_SECTION");
     }
 
-    fn parse_demands(lines: &Lines, number: usize) -> Vec<f64> {
-        for (line_number, line) in lines.iter().enumerate() {
-            if !line.is_empty() && line[0] == "DEMAND_SECTION" {
-                return lines
-                    .iter()
-                    .skip(line_number + 1)
-                    .take(number)
-                    .map(|line| line[1].parse::<f64>().expect("Failed to parse demand"))
-                    .collect();
-            }
+    fn parse_demands(lines: &Lines, number: usize) -> Result<Vec<f64>, String> {
+        let demands: Vec<f64> = Self::section(lines, "DEMAND_SECTION")?
+            .take(number)
+            .map(|line| Self::parse_value(line, 1, "demand"))
+            .collect::<Result<_, String>>()?;
+        match demands.len() == number {
+            true => Ok(demands),
+            false => Err(format!("Expected {} demands", number)),
         }
-        panic!("Could not find DEMAND_SECTION");
-    }
-
-    fn parse_lower_row_matrix(lines: &Lines, number: usize) -> Vec<Vec<f64>> {
-        for (line_number, line) in lines.iter().enumerate() {
-            if !line.is_empty() && line[0] == "EDGE_WEIGHT_SECTION" {
-                return lines
-                    .iter()
-                    .skip(line_number + 1)
-                    .take(number - 1)
-                    .map(|line| {
-                        line.iter()
-                            .map(|val| val.parse::<f64>().expect("Failed to parse edge weight"))
-                            .collect()
+    }
+
+    fn parse_lower_row_matrix(lines: &Lines, number: usize) -> Result<Vec<Vec<f64>>, String> {
+        Self::section(lines, "EDGE_WEIGHT_SECTION")?
+            .take(number.saturating_sub(1))
+            .map(|line| {
+                line.iter()
+                    .map(|val| {
+                        val.parse::<f64>()
+                            .map_err(|_| "Failed to parse edge weight".to_owned())
                     })
-                    .collect();
-            }
-        }
-        panic!("Could not find EDGE_WEIGHT_SECTION");
+                    .collect()
+            })
+            .collect()
     }
 
     fn create_nodes(coords: Vec<Coordinate>, demands: Vec<f64>) -> Vec<Node> {
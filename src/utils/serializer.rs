@@ -1,7 +1,11 @@
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
 
-use crate::solver::Context;
+use serde_json::json;
+
+use crate::models::Problem;
+use crate::solver::{Context, Solution, TracePoint};
+use crate::utils::{FileFormat, JsonProblem, JsonSolution};
 
 pub fn write_solution_file(ctx: &Context) {
     if let Some(solution_path) = ctx.config.borrow().solution_path.as_ref() {
@@ -12,8 +16,420 @@ pub fn write_solution_file(ctx: &Context) {
             .open(solution_path)
             .unwrap();
 
-        if let Some(last_entry) = ctx.search_history.borrow().last_entry() {
-            writeln!(file, "{}", last_entry.solution).expect("Failed to write solution to file!");
+        let format = ctx.config.borrow().solution_format.resolve(solution_path);
+        write_solution(&mut file, ctx, format).expect("Failed to write solution to file!");
+    }
+}
+
+/// Write the statistics of the search at each log interval to the trace path of the config
+pub fn write_trace_file(ctx: &Context) {
+    if let Some(trace_path) = ctx.config.borrow().trace_path.as_ref() {
+        let file = File::create(trace_path)
+            .unwrap_or_else(|err| panic!("Failed to create {}: {}", trace_path, err));
+        let mut writer = BufWriter::new(file);
+        let format = match FileFormat::Auto.resolve(trace_path) {
+            FileFormat::Json => FileFormat::Json,
+            _ => FileFormat::Csv,
+        };
+        write_trace(&mut writer, &ctx.search_history.borrow().trace, format)
+            .and_then(|_| writer.flush())
+            .unwrap_or_else(|err| panic!("Failed to write {}: {}", trace_path, err));
+    }
+}
+
+/// Write the search statistics as a JSON array or as CSV, where each crossover operator has a
+/// column with its weight
+pub fn write_trace(
+    writer: &mut impl Write,
+    trace: &[TracePoint],
+    format: FileFormat,
+) -> io::Result<()> {
+    if format == FileFormat::Json {
+        serde_json::to_writer_pretty(&mut *writer, trace)?;
+        return writeln!(writer);
+    }
+
+    let operators: Vec<&str> = trace.first().map_or_else(Vec::new, |point| {
+        point
+            .crossover_weights
+            .iter()
+            .map(|(operator, _)| operator.as_str())
+            .collect()
+    });
+    write!(
+        writer,
+        "time,iteration,iterations_without_improvement,feasible_size,feasible_best,\
+         feasible_average,infeasible_size,infeasible_best,infeasible_average,feasible_diversity,\
+         infeasible_diversity,feasible_fraction,penalty"
+    )?;
+    for operator in operators.iter() {
+        write!(writer, ",crossover_{}", operator)?;
+    }
+    writeln!(writer)?;
+    for point in trace.iter() {
+        write!(
+            writer,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            point.time,
+            point.iteration,
+            point.iterations_without_improvement,
+            point.feasible_size,
+            point.feasible_best,
+            point.feasible_average,
+            point.infeasible_size,
+            point.infeasible_best,
+            point.infeasible_average,
+            point.feasible_diversity,
+            point.infeasible_diversity,
+            point.feasible_fraction,
+            point.penalty
+        )?;
+        for (_, weight) in point.crossover_weights.iter() {
+            write!(writer, ",{}", weight)?;
+        }
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+/// Write the best solution of the search in the given format
+pub fn write_solution(
+    writer: &mut impl Write,
+    ctx: &Context,
+    format: FileFormat,
+) -> io::Result<()> {
+    match format {
+        FileFormat::Json => {
+            let solution = JsonSolution::new(ctx, &Solution::from_context(ctx));
+            serde_json::to_writer_pretty(&mut *writer, &solution)?;
+            writeln!(writer)
+        }
+        FileFormat::Csv => write_csv(writer, ctx),
+        FileFormat::GeoJson => write_geojson(writer, ctx),
+        FileFormat::Auto | FileFormat::Tsplib => write_tsplib(writer, ctx),
+    }
+}
+
+/// Write a problem as TSPLIB or JSON, with the explicit distances in the lower row format of the
+/// parser
+pub fn write_problem(
+    writer: &mut impl Write,
+    problem: &Problem,
+    name: &str,
+    matrix: Option<&[Vec<f64>]>,
+    format: FileFormat,
+) -> io::Result<()> {
+    match format {
+        FileFormat::Json => {
+            let json_problem = JsonProblem::from_problem(problem, Some(name.to_owned()), matrix);
+            serde_json::to_writer_pretty(&mut *writer, &json_problem)?;
+            writeln!(writer)
+        }
+        FileFormat::Auto | FileFormat::Tsplib => {
+            write_tsplib_problem(writer, problem, name, matrix)
+        }
+        FileFormat::Csv | FileFormat::GeoJson => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Problems can only be written as TSPLIB or JSON",
+        )),
+    }
+}
+
+/// The CVRPLIB problem format, where the nodes are numbered from 1 with the depot first
+fn write_tsplib_problem(
+    writer: &mut impl Write,
+    problem: &Problem,
+    name: &str,
+    matrix: Option<&[Vec<f64>]>,
+) -> io::Result<()> {
+    writeln!(writer, "NAME : {}", name)?;
+    writeln!(writer, "TYPE : CVRP")?;
+    writeln!(writer, "DIMENSION : {}", problem.dim())?;
+    match matrix {
+        Some(_) => {
+            writeln!(writer, "EDGE_WEIGHT_TYPE : EXPLICIT")?;
+            writeln!(writer, "EDGE_WEIGHT_FORMAT : LOWER_ROW")?;
+        }
+        None => writeln!(writer, "EDGE_WEIGHT_TYPE : EUC_2D")?,
+    }
+    writeln!(writer, "CAPACITY : {}", problem.vehicle.cap)?;
+    if let Some(matrix) = matrix {
+        writeln!(writer, "EDGE_WEIGHT_SECTION")?;
+        for row in matrix.iter() {
+            let row: Vec<String> = row.iter().map(|distance| distance.to_string()).collect();
+            writeln!(writer, "{}", row.join(" "))?;
         }
     }
+    writeln!(writer, "NODE_COORD_SECTION")?;
+    for (number, node) in (1..).zip(problem.nodes.iter()) {
+        writeln!(writer, "{} {} {}", number, node.coord.lng, node.coord.lat)?;
+    }
+    writeln!(writer, "DEMAND_SECTION")?;
+    for (number, node) in (1..).zip(problem.nodes.iter()) {
+        writeln!(writer, "{} {}", number, node.demand)?;
+    }
+    writeln!(writer, "DEPOT_SECTION")?;
+    writeln!(writer, "1")?;
+    writeln!(writer, "-1")?;
+    writeln!(writer, "EOF")
+}
+
+/// The CVRPLIB text format with a line per route and the cost
+fn write_tsplib(writer: &mut impl Write, ctx: &Context) -> io::Result<()> {
+    if let Some(last_entry) = ctx.search_history.borrow().last_entry() {
+        writeln!(writer, "{}", last_entry.solution)?;
+    }
+    Ok(())
+}
+
+/// One row per stop, starting and ending at the depot, with the cumulative load and distance
+/// followed by the totals of the route. The totals of the solution, the run time and the seed are
+/// comment lines before the header, where the run time and the seed are left out for a solution
+/// which was not found by the search.
+fn write_csv(writer: &mut impl Write, ctx: &Context) -> io::Result<()> {
+    let solution = Solution::from_context(ctx);
+    let json_solution = JsonSolution::new(ctx, &solution);
+    let nodes = &ctx.problem.nodes;
+    let distance_matrix = &ctx.matrix_provider.distance;
+
+    writeln!(writer, "# cost: {}", json_solution.cost)?;
+    writeln!(writer, "# feasible: {}", json_solution.feasible)?;
+    writeln!(writer, "# load: {}", json_solution.load)?;
+    writeln!(writer, "# stops: {}", json_solution.stops)?;
+    if let Some(metadata) = json_solution.metadata.as_ref() {
+        writeln!(writer, "# runtime: {}", metadata.runtime)?;
+        if let Some(seed) = metadata.seed {
+            writeln!(writer, "# seed: {}", seed)?;
+        }
+    }
+    writeln!(
+        writer,
+        "route,sequence,id,x,y,demand,load,distance,route_load,route_distance,route_utilization,route_stops"
+    )?;
+    for (route_number, route) in solution.routes.iter().enumerate() {
+        let utilization = route.load / ctx.problem.vehicle.cap;
+        let stops = std::iter::once(0)
+            .chain(route.customers.iter().cloned())
+            .chain(std::iter::once(0));
+        let (mut load, mut distance, mut prev) = (0.0, 0.0, 0);
+        for (sequence, stop) in stops.enumerate() {
+            let node = &nodes[stop];
+            load += node.demand;
+            distance += distance_matrix.get(prev, stop);
+            prev = stop;
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{},{},{},{},{}",
+                route_number + 1,
+                sequence,
+                node.id,
+                node.coord.lng,
+                node.coord.lat,
+                node.demand,
+                load,
+                distance,
+                route.load,
+                route.distance,
+                utilization,
+                route.customers.len()
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// A feature collection with a LineString from the depot through the customers and back for each
+/// route, where the totals and the run metadata are a foreign member
+fn write_geojson(writer: &mut impl Write, ctx: &Context) -> io::Result<()> {
+    let solution = Solution::from_context(ctx);
+    let json_solution = JsonSolution::new(ctx, &solution);
+    let nodes = &ctx.problem.nodes;
+
+    let features: Vec<_> = solution
+        .routes
+        .iter()
+        .zip(json_solution.routes.iter())
+        .enumerate()
+        .map(|(route_number, (route, json_route))| {
+            let coordinates: Vec<_> = std::iter::once(0)
+                .chain(route.customers.iter().cloned())
+                .chain(std::iter::once(0))
+                .map(|stop| [nodes[stop].coord.lng, nodes[stop].coord.lat])
+                .collect();
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": coordinates,
+                },
+                "properties": {
+                    "route": route_number + 1,
+                    "customers": json_route.customers,
+                    "load": json_route.load,
+                    "distance": json_route.distance,
+                    "utilization": json_route.utilization,
+                    "stops": json_route.stops,
+                },
+            })
+        })
+        .collect();
+
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+        "solution": {
+            "problem": json_solution.problem,
+            "cost": json_solution.cost,
+            "feasible": json_solution.feasible,
+            "load": json_solution.load,
+            "stops": json_solution.stops,
+            "metadata": json_solution.metadata,
+        },
+    });
+    serde_json::to_writer_pretty(&mut *writer, &collection)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::solver::testing::{random_individuals, test_context};
+    use crate::solver::HistoricSolution;
+
+    // Two routes of two customers on the corners of a square around the depot, and an empty route
+    fn context() -> Context {
+        let coords = [
+            (0.0, 0.0),
+            (3.0, 4.0),
+            (-3.0, 4.0),
+            (-3.0, -4.0),
+            (3.0, -4.0),
+        ];
+        let ctx = test_context(&coords, &[1.0, 2.0, 3.0, 4.0], 10.0, Config::default());
+        ctx.search_history
+            .borrow_mut()
+            .add_solution(HistoricSolution {
+                routes: vec![vec![1, 2], vec![], vec![3, 4]],
+                cost: 32.0,
+            });
+        ctx
+    }
+
+    fn solution_output(ctx: &Context, format: FileFormat) -> String {
+        let mut output = Vec::new();
+        write_solution(&mut output, ctx, format).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn solution_csv() {
+        let ctx = context();
+        let output = solution_output(&ctx, FileFormat::Csv);
+        let mut lines = output.lines();
+
+        // The totals of the solution come first, without run metadata as there was no search
+        let comments: Vec<&str> = lines.by_ref().take(4).collect();
+        assert_eq!(
+            vec!["# cost: 32", "# feasible: true", "# load: 10", "# stops: 4"],
+            comments
+        );
+
+        assert_eq!(
+            "route,sequence,id,x,y,demand,load,distance,route_load,route_distance,route_utilization,route_stops",
+            lines.next().unwrap()
+        );
+        let rows: Vec<Vec<&str>> = lines.map(|line| line.split(',').collect()).collect();
+        assert!(rows.iter().all(|row| row.len() == 12));
+
+        // The stops of each route, starting and ending at the depot
+        for (route, customers) in [("1", ["1", "2"]), ("2", ["3", "4"])].iter() {
+            let route_rows: Vec<&Vec<&str>> = rows.iter().filter(|row| row[0] == *route).collect();
+            let ids: Vec<&str> = route_rows.iter().map(|row| row[2]).collect();
+            assert_eq!(vec!["0", customers[0], customers[1], "0"], ids);
+            let sequence: Vec<&str> = route_rows.iter().map(|row| row[1]).collect();
+            assert_eq!(vec!["0", "1", "2", "3"], sequence);
+
+            // The cumulative load and distance end at the totals of the route
+            let last = route_rows.last().unwrap();
+            assert_eq!(last[6], last[8]);
+            assert_eq!(last[7], last[9]);
+            assert_eq!("16", last[9]);
+            assert_eq!("2", last[11]);
+        }
+        assert_eq!(8, rows.len());
+
+        // The run time and the seed follow the totals of a solution found by the search
+        let individual = random_individuals(&ctx, 1).pop().unwrap();
+        ctx.search_history.borrow_mut().add(&ctx, &individual);
+        let output = solution_output(&ctx, FileFormat::Csv);
+        let comments: Vec<&str> = output.lines().skip(4).take(3).collect();
+        assert!(comments[0].starts_with("# runtime: "));
+        assert_eq!("# seed: 1", comments[1]);
+        assert!(comments[2].starts_with("route,"));
+    }
+
+    #[test]
+    fn solution_geojson() {
+        let ctx = context();
+        let output = solution_output(&ctx, FileFormat::GeoJson);
+        let collection: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!("FeatureCollection", collection["type"]);
+        assert_eq!(32.0, collection["solution"]["cost"]);
+
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(2, features.len());
+        for (number, feature) in features.iter().enumerate() {
+            assert_eq!("Feature", feature["type"]);
+            assert_eq!(number + 1, feature["properties"]["route"]);
+            assert_eq!("LineString", feature["geometry"]["type"]);
+
+            // Positions of the depot, the two customers and the depot
+            let coordinates = feature["geometry"]["coordinates"].as_array().unwrap();
+            assert_eq!(4, coordinates.len());
+            for position in coordinates.iter() {
+                let position = position.as_array().unwrap();
+                assert_eq!(2, position.len());
+                assert!(position.iter().all(|value| value.is_f64()));
+            }
+            assert_eq!(&json!([0.0, 0.0]), &coordinates[0]);
+            assert_eq!(coordinates[0], coordinates[3]);
+        }
+        assert_eq!(
+            json!([[0.0, 0.0], [3.0, 4.0], [-3.0, 4.0], [0.0, 0.0]]),
+            features[0]["geometry"]["coordinates"]
+        );
+    }
+
+    #[test]
+    fn trace_csv() {
+        let point = TracePoint {
+            time: 1.5,
+            iteration: 500,
+            iterations_without_improvement: 20,
+            feasible_size: 25,
+            feasible_best: 100.0,
+            feasible_average: 110.5,
+            infeasible_size: 30,
+            infeasible_best: 95.0,
+            infeasible_average: 120.0,
+            feasible_diversity: 0.25,
+            infeasible_diversity: 0.5,
+            feasible_fraction: 0.2,
+            penalty: 10.0,
+            crossover_weights: vec![("OX".to_owned(), 0.75), ("SREX".to_owned(), 0.25)],
+        };
+        let mut output = Vec::new();
+        write_trace(&mut output, &[point], FileFormat::Csv).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("time,iteration,"));
+        assert!(lines[0].ends_with(",penalty,crossover_OX,crossover_SREX"));
+        assert_eq!(
+            "1.5,500,20,25,100,110.5,30,95,120,0.25,0.5,0.2,10,0.75,0.25",
+            lines[1]
+        );
+    }
 }
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::models::{DistanceMatrix, DistanceMatrixBuilder, Problem};
+use crate::utils::{FileFormat, FloatCompare, JsonSolution, ProblemParser};
+
+/// How the nodes of a solution file are identified
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeNumbering {
+    // Index of the node in the problem, as in the TSPLIB solution format where the depot is 0
+    Index,
+    // Id of the node in the problem, as in the JSON solution format
+    Id,
+}
+
+/// Routes and cost as written in a solution file, before they are checked against the problem
+#[derive(Clone, Debug)]
+pub struct SolutionFile {
+    pub routes: Vec<Vec<usize>>,
+    pub cost: Option<f64>,
+    pub numbering: NodeNumbering,
+    // The cost is rounded to an integer, as in the CVRPLIB text format
+    pub rounded_cost: bool,
+}
+
+impl SolutionFile {
+    /// Read a TSPLIB or JSON solution file
+    pub fn read(path: &str, format: FileFormat) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+        match format.resolve(path) {
+            FileFormat::Json => Self::parse_json(&text),
+            FileFormat::Tsplib | FileFormat::Auto => Self::parse_tsplib(&text),
+            FileFormat::Csv | FileFormat::GeoJson => {
+                Err("Only TSPLIB and JSON solutions can be validated".to_owned())
+            }
+        }
+    }
+
+    /// Parse the CVRPLIB format with `Route #k: ...` lines followed by `Cost N`
+    pub fn parse_tsplib(text: &str) -> Result<Self, String> {
+        let mut routes = Vec::new();
+        let mut cost = None;
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if let Some(route) = line.strip_prefix("Route") {
+                let (_, stops) = route
+                    .split_once(':')
+                    .ok_or_else(|| format!("Line {}: missing ':' after route", line_number + 1))?;
+                let route = stops
+                    .split_whitespace()
+                    .map(|stop| {
+                        stop.parse::<usize>().map_err(|_| {
+                            format!("Line {}: invalid node '{}'", line_number + 1, stop)
+                        })
+                    })
+                    .collect::<Result<Vec<usize>, String>>()?;
+                routes.push(route);
+            } else if let Some(value) = line.strip_prefix("Cost") {
+                let value = value.trim();
+                cost =
+                    Some(value.parse::<f64>().map_err(|_| {
+                        format!("Line {}: invalid cost '{}'", line_number + 1, value)
+                    })?);
+            }
+        }
+        Ok(Self {
+            routes,
+            cost,
+            numbering: NodeNumbering::Index,
+            rounded_cost: true,
+        })
+    }
+
+    /// Parse the JSON solution format, where the customers are node ids
+    pub fn parse_json(text: &str) -> Result<Self, String> {
+        let solution: JsonSolution = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        Ok(Self {
+            routes: solution
+                .routes
+                .into_iter()
+                .map(|route| route.customers)
+                .collect(),
+            cost: Some(solution.cost),
+            numbering: NodeNumbering::Id,
+            rounded_cost: false,
+        })
+    }
+}
+
+/// Maps the nodes of a solution file to their index in the problem
+struct NodeLookup {
+    numbering: NodeNumbering,
+    dimension: usize,
+    index_of_id: HashMap<usize, usize>,
+}
+
+impl NodeLookup {
+    fn new(problem: &Problem, numbering: NodeNumbering) -> Self {
+        let index_of_id = match numbering {
+            NodeNumbering::Index => HashMap::new(),
+            NodeNumbering::Id => problem
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(index, node)| (node.id, index))
+                .collect(),
+        };
+        Self {
+            numbering,
+            dimension: problem.dim(),
+            index_of_id,
+        }
+    }
+
+    fn index(&self, node: usize) -> Option<usize> {
+        match self.numbering {
+            NodeNumbering::Index => Some(node).filter(|&index| index < self.dimension),
+            NodeNumbering::Id => self.index_of_id.get(&node).cloned(),
+        }
+    }
+}
+
+impl SolutionFile {
+    /// Routes with the customers as indices of the problem, failing on nodes which are not
+    /// customers of the problem
+    pub fn routes_by_index(&self, problem: &Problem) -> Result<Vec<Vec<usize>>, String> {
+        let lookup = NodeLookup::new(problem, self.numbering);
+        self.routes
+            .iter()
+            .map(|route| {
+                route
+                    .iter()
+                    .map(|&node| {
+                        lookup
+                            .index(node)
+                            .filter(|&index| index != 0)
+                            .ok_or_else(|| format!("Node {} is not a customer", node))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Violation found in a solution, where routes are numbered from 1 and nodes are identified as in
+/// the solution file
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationError {
+    // The solution file or the instance could not be read
+    Parse {
+        message: String,
+    },
+    // The node is out of range, or the depot inside a route
+    InvalidNode {
+        route: usize,
+        node: usize,
+    },
+    DuplicateVisit {
+        route: usize,
+        node: usize,
+    },
+    MissingCustomer {
+        node: usize,
+    },
+    Overload {
+        route: usize,
+        load: f64,
+        capacity: f64,
+    },
+    MissingCost,
+    CostMismatch {
+        reported: f64,
+        computed: f64,
+    },
+}
+
+/// Machine-readable result of validating a solution
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    // Number of non-empty routes
+    pub routes: usize,
+    pub customers_visited: usize,
+    pub reported_cost: Option<f64>,
+    // Distance of the routes, where invalid nodes are left out
+    pub computed_cost: f64,
+    pub errors: Vec<ValidationError>,
+}
+
+impl ValidationReport {
+    fn from_errors(errors: Vec<ValidationError>) -> Self {
+        Self {
+            valid: errors.is_empty(),
+            routes: 0,
+            customers_visited: 0,
+            reported_cost: None,
+            computed_cost: 0.0,
+            errors,
+        }
+    }
+}
+
+/// Check that every customer is visited exactly once, that the routes are within the capacity and
+/// that the reported cost matches the distance of the routes.
+///
+/// The reported cost matches if it is within a relative tolerance of the computed cost. With
+/// distances which are not rounded, the cost of a file format with rounded costs, such as the
+/// CVRPLIB text format, also matches the computed cost rounded to an integer.
+pub fn validate_solution(
+    problem: &Problem,
+    distance: &DistanceMatrix,
+    solution: &SolutionFile,
+) -> ValidationReport {
+    let lookup = NodeLookup::new(problem, solution.numbering);
+
+    let mut errors = Vec::new();
+    let mut visited = vec![false; problem.dim()];
+    let mut computed_cost = 0.0;
+    let mut customers_visited = 0;
+
+    for (route_number, route) in (1..).zip(solution.routes.iter()) {
+        let mut load = 0.0;
+        let mut prev = 0;
+        for &node in route.iter() {
+            let index = match lookup.index(node) {
+                Some(index) if index != 0 => index,
+                _ => {
+                    errors.push(ValidationError::InvalidNode {
+                        route: route_number,
+                        node,
+                    });
+                    continue;
+                }
+            };
+            match visited[index] {
+                true => errors.push(ValidationError::DuplicateVisit {
+                    route: route_number,
+                    node,
+                }),
+                false => {
+                    visited[index] = true;
+                    customers_visited += 1;
+                }
+            }
+            load += problem.nodes[index].demand;
+            computed_cost += distance.get(prev, index);
+            prev = index;
+        }
+        computed_cost += distance.get(prev, 0);
+
+        if load.approx_gt(problem.vehicle.cap) {
+            errors.push(ValidationError::Overload {
+                route: route_number,
+                load,
+                capacity: problem.vehicle.cap,
+            });
+        }
+    }
+
+    for (index, &visited) in visited.iter().enumerate().skip(1) {
+        if !visited {
+            let node = match solution.numbering {
+                NodeNumbering::Index => index,
+                NodeNumbering::Id => problem.nodes[index].id,
+            };
+            errors.push(ValidationError::MissingCustomer { node });
+        }
+    }
+
+    match solution.cost {
+        Some(reported) => {
+            let tolerance = 1e-6 * computed_cost.abs().max(1.0);
+            let rounded = solution.rounded_cost
+                && !distance.is_rounded()
+                && reported == computed_cost.round();
+            if (reported - computed_cost).abs() > tolerance && !rounded {
+                errors.push(ValidationError::CostMismatch {
+                    reported,
+                    computed: computed_cost,
+                });
+            }
+        }
+        None => errors.push(ValidationError::MissingCost),
+    }
+
+    ValidationReport {
+        valid: errors.is_empty(),
+        routes: solution
+            .routes
+            .iter()
+            .filter(|route| !route.is_empty())
+            .count(),
+        customers_visited,
+        reported_cost: solution.cost,
+        computed_cost,
+        errors,
+    }
+}
+
+/// Validate a solution file against the instance in the config, with the distances rounded as
+/// given by `round_distances`. An instance which cannot be parsed is reported as a parse error.
+pub fn validate_solution_file(config: &Config, solution_path: &str) -> ValidationReport {
+    let mut parser = ProblemParser::new();
+    let problem = match parser.try_parse(config) {
+        Ok(()) => parser.problem.take().expect("No problem after parsing"),
+        Err(message) => {
+            return ValidationReport::from_errors(vec![ValidationError::Parse { message }]);
+        }
+    };
+
+    // Only the distances along the routes are needed, so they are computed lazily
+    let mut builder = DistanceMatrixBuilder::new()
+        .locations(problem.nodes.iter().map(|node| node.coord).collect())
+        .rounded(config.round_distances);
+    if let Some(matrix) = parser.matrix.take() {
+        builder = builder.input(matrix);
+    }
+    let distance = builder.build();
+
+    match SolutionFile::read(solution_path, config.solution_format) {
+        Ok(solution) => validate_solution(&problem, &distance, &solution),
+        Err(message) => ValidationReport::from_errors(vec![ValidationError::Parse { message }]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Coordinate, Node, ProblemBuilder, Vehicle};
+
+    fn problem_at(coords: &[(f64, f64)], rounded: bool) -> (Problem, DistanceMatrix) {
+        let nodes: Vec<Node> = coords
+            .iter()
+            .enumerate()
+            .map(|(index, &(lng, lat))| Node {
+                id: index + 1,
+                coord: Coordinate { lng, lat },
+                demand: 1.0,
+            })
+            .collect();
+        let distance = DistanceMatrixBuilder::new()
+            .locations(nodes.iter().map(|node| node.coord).collect())
+            .rounded(rounded)
+            .build();
+        let problem = ProblemBuilder::new(nodes, Vehicle { id: 0, cap: 2.0 }).build();
+        (problem, distance)
+    }
+
+    fn problem() -> (Problem, DistanceMatrix) {
+        problem_at(&[(0.0, 0.0), (3.0, 0.0), (3.0, 4.0), (0.0, 4.0)], true)
+    }
+
+    #[test]
+    fn valid_solution() {
+        let (problem, distance) = problem();
+        let solution = SolutionFile::parse_tsplib("Route #1: 1 2\nRoute #2: 3\nCost 20\n").unwrap();
+        let report = validate_solution(&problem, &distance, &solution);
+        assert!(report.valid, "{:?}", report.errors);
+        assert_eq!(2, report.routes);
+        assert_eq!(3, report.customers_visited);
+        assert_eq!(20.0, report.computed_cost);
+    }
+
+    #[test]
+    fn invalid_solution() {
+        let (problem, distance) = problem();
+        let solution =
+            SolutionFile::parse_tsplib("Route #1: 1 2 1\nRoute #2: 4\nCost 19\n").unwrap();
+        let report = validate_solution(&problem, &distance, &solution);
+        assert!(!report.valid);
+        assert_eq!(
+            vec![
+                ValidationError::DuplicateVisit { route: 1, node: 1 },
+                ValidationError::Overload {
+                    route: 1,
+                    load: 3.0,
+                    capacity: 2.0
+                },
+                ValidationError::InvalidNode { route: 2, node: 4 },
+                ValidationError::MissingCustomer { node: 3 },
+                ValidationError::CostMismatch {
+                    reported: 19.0,
+                    computed: 14.0
+                },
+            ],
+            report.errors
+        );
+    }
+
+    #[test]
+    fn json_solution_by_id() {
+        let (problem, distance) = problem();
+        let json = r#"{"cost": 20.0, "feasible": true, "routes": [
+            {"customers": [2, 3], "load": 2, "distance": 12},
+            {"customers": [4], "load": 1, "distance": 8}
+        ]}"#;
+        let solution = SolutionFile::parse_json(json).unwrap();
+        let report = validate_solution(&problem, &distance, &solution);
+        assert!(report.valid, "{:?}", report.errors);
+    }
+
+    #[test]
+    fn cost_rounding() {
+        // The route has a distance of 2 * sqrt(2) = 2.83
+        let (problem, distance) = problem_at(&[(0.0, 0.0), (1.0, 1.0)], false);
+        let json = |cost: f64| {
+            let json = format!(
+                r#"{{"cost": {}, "feasible": true, "routes": [
+                    {{"customers": [2], "load": 1, "distance": {}}}
+                ]}}"#,
+                cost, cost
+            );
+            SolutionFile::parse_json(&json).unwrap()
+        };
+        let is_valid =
+            |solution: &SolutionFile| validate_solution(&problem, &distance, solution).valid;
+
+        assert!(is_valid(&json(2.0 * 2.0_f64.sqrt())));
+        // The JSON format has the exact cost, so the rounded cost does not match
+        assert!(!is_valid(&json(3.0)));
+        // The CVRPLIB text format has the rounded cost
+        assert!(is_valid(
+            &SolutionFile::parse_tsplib("Route #1: 1\nCost 3\n").unwrap()
+        ));
+        assert!(!is_valid(
+            &SolutionFile::parse_tsplib("Route #1: 1\nCost 2\n").unwrap()
+        ));
+
+        // With rounded distances, the cost is an integer, which must be exact
+        let (problem, distance) = problem_at(&[(0.0, 0.0), (1.0, 1.0)], true);
+        let solution = SolutionFile::parse_tsplib("Route #1: 1\nCost 3\n").unwrap();
+        assert!(!validate_solution(&problem, &distance, &solution).valid);
+    }
+
+    #[test]
+    fn unparsable_instance_is_reported() {
+        let mut config = Config::default();
+        config.instance_path = "missing.vrp".to_owned();
+        let report = validate_solution_file(&config, "missing.sol");
+        assert!(!report.valid);
+        assert_eq!(
+            vec![ValidationError::Parse {
+                message: "Cannot find instance file: missing.vrp".to_owned()
+            }],
+            report.errors
+        );
+    }
+}
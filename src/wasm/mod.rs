@@ -1,22 +1,57 @@
 use instant::Instant;
+use serde::Serialize;
+use serde_yaml::Value;
 use wasm_bindgen::prelude::*;
 
 use crate::config::Config;
-use crate::models::{Coordinate, Node, ProblemBuilder, Vehicle};
-use crate::solver::genetic::GeneticAlgorithm;
-use crate::solver::{Context, Metaheuristic, SearchHistory};
+use crate::models::{Coordinate, DistanceMatrix, Node, Problem, ProblemBuilder, Vehicle};
+use crate::solver::genetic::{GeneticAlgorithm, Individual};
+use crate::solver::{Context, Metaheuristic, Solution};
+use crate::utils::{FloatCompare, NodeNumbering, SolutionFile};
 
+#[derive(Default)]
 pub struct WasmProblem {
     pub nodes: Vec<Node>,
     pub vehicle: Option<Vehicle>,
+    // Full matrix of distances between the nodes in row-major order
+    pub distances: Option<Vec<f64>>,
+    // Routes of a solution to start from, with the customers as indices of the nodes
+    pub initial_routes: Option<Vec<Vec<usize>>>,
 }
 
 impl WasmProblem {
     pub fn new() -> Self {
-        Self {
-            nodes: Vec::new(),
-            vehicle: None,
+        Self::default()
+    }
+
+    /// The distances in the lower row format of the parser, which is taken by the context
+    fn lower_row_matrix(&self) -> Result<Option<Vec<Vec<f64>>>, String> {
+        self.distances
+            .as_ref()
+            .map(|distances| DistanceMatrix::lower_row(distances, self.nodes.len()))
+            .transpose()
+    }
+
+    /// The initial routes with the customers as node indices, which must visit every customer
+    /// exactly once
+    fn initial_routes(&self, problem: &Problem) -> Result<Option<Vec<Vec<usize>>>, String> {
+        let routes = match self.initial_routes.as_ref() {
+            Some(routes) => routes,
+            None => return Ok(None),
+        };
+        let solution = SolutionFile {
+            routes: routes.clone(),
+            cost: None,
+            numbering: NodeNumbering::Index,
+            rounded_cost: false,
+        };
+        let routes = solution.routes_by_index(problem)?;
+        let mut customers: Vec<usize> = routes.iter().flatten().copied().collect();
+        customers.sort_unstable();
+        if customers != (1..problem.nodes.len()).collect::<Vec<usize>>() {
+            return Err("The initial solution must visit every customer exactly once".to_string());
         }
+        Ok(Some(routes))
     }
 }
 
@@ -29,10 +64,8 @@ pub struct Solver {
     best_cost: f64,
 }
 
-#[wasm_bindgen]
-impl Solver {
-    pub fn new() -> Self {
-        console_error_panic_hook::set_once();
+impl Default for Solver {
+    fn default() -> Self {
         Self {
             ctx: None,
             config: Config::default(),
@@ -41,6 +74,14 @@ impl Solver {
             best_cost: f64::INFINITY,
         }
     }
+}
+
+#[wasm_bindgen]
+impl Solver {
+    pub fn new() -> Self {
+        console_error_panic_hook::set_once();
+        Self::default()
+    }
 
     pub fn clear(&mut self) {
         self.wasm_problem = WasmProblem::new();
@@ -49,7 +90,8 @@ impl Solver {
         self.best_cost = f64::INFINITY;
     }
 
-    pub fn add_node(&mut self, id: usize, demand: i32, x: i32, y: i32) {
+    /// Add a node, where the first node is the depot
+    pub fn add_node(&mut self, id: usize, demand: f64, x: f64, y: f64) {
         let new_node = Node {
             id,
             coord: Coordinate { lng: x, lat: y },
@@ -58,99 +100,283 @@ impl Solver {
         self.wasm_problem.nodes.push(new_node);
     }
 
-    pub fn add_capacity(&mut self, capacity: i32) {
+    pub fn add_capacity(&mut self, capacity: f64) {
         self.wasm_problem.vehicle = Some(Vehicle {
             id: 0,
             cap: capacity,
         });
     }
 
-    pub fn load_problem(&mut self) {
-        let start_time = Instant::now();
-        // Initialize search history
-        let search_history = SearchHistory::new(start_time);
+    /// Explicit distances as a full matrix in row-major order, replacing the euclidean distances
+    pub fn set_distance_matrix(&mut self, distances: Vec<f64>) {
+        self.wasm_problem.distances = Some(distances);
+    }
 
-        let problem_builder = ProblemBuilder::new(
-            self.wasm_problem.nodes.clone(),
-            self.wasm_problem.vehicle.take().expect("No vehicle"),
-        );
+    /// Routes of a solution to start the search from, as arrays of node indices
+    pub fn set_initial_solution(&mut self, routes: JsValue) -> Result<(), JsValue> {
+        self.wasm_problem.initial_routes = Some(serde_wasm_bindgen::from_value(routes)?);
+        Ok(())
+    }
+
+    /// The full config as an object
+    pub fn config(&self) -> Result<JsValue, JsValue> {
+        to_js(&self.config)
+    }
+
+    /// Update the config fields given in the object, which are used when the problem is loaded
+    pub fn set_config(&mut self, values: JsValue) -> Result<(), JsValue> {
+        let values: Value = serde_wasm_bindgen::from_value(values)?;
+        let config: Value = serde_yaml::to_value(&self.config).expect("Failed to serialize config");
+        match values.as_mapping() {
+            Some(mapping) => {
+                for (key, _) in mapping.iter() {
+                    if config.get(key).is_none() {
+                        return Err(JsValue::from_str(&format!(
+                            "Unknown config field: {:?}",
+                            key
+                        )));
+                    }
+                }
+            }
+            None => return Err(JsValue::from_str("The config must be an object")),
+        }
         let mut config = self.config.clone();
+        config.update(&values);
+        self.config = config;
+        Ok(())
+    }
+
+    /// Build the context of the added problem and start a new search
+    pub fn load_problem(&mut self) -> Result<(), JsValue> {
+        let nodes = self.wasm_problem.nodes.clone();
+        let dimension = nodes.len();
+        if dimension < 2 {
+            return Err(JsValue::from_str("The problem has no customers"));
+        }
+        let vehicle = self
+            .wasm_problem
+            .vehicle
+            .clone()
+            .ok_or_else(|| JsValue::from_str("No vehicle"))?;
 
-        let problem = problem_builder.build(&mut config);
+        let matrix = self
+            .wasm_problem
+            .lower_row_matrix()
+            .map_err(|err| JsValue::from_str(&err))?;
+        let problem = ProblemBuilder::new(nodes, vehicle).build();
+        let initial_routes = self
+            .wasm_problem
+            .initial_routes(&problem)
+            .map_err(|err| JsValue::from_str(&err))?;
 
-        let ctx = Context::new(problem, config, search_history);
-        self.metaheuristic = Some(GeneticAlgorithm::new(&ctx));
+        let ctx = Context::from_problem(problem, matrix, self.config.clone(), Instant::now());
+        let mut genetic = GeneticAlgorithm::new(&ctx);
+        if let Some(routes) = initial_routes {
+            genetic.add_initial(&ctx, Individual::from_routes(&ctx, routes, 0));
+        }
+        self.metaheuristic = Some(genetic);
         self.ctx = Some(ctx);
+        self.best_cost = f64::INFINITY;
+        Ok(())
     }
 
-    pub fn update_time_limit(&mut self, value: i32) {
-        self.config.time_limit = value as u64;
+    /// Run an iteration of the search, and return the solution when a new best is found and null
+    /// otherwise
+    pub fn iterate(&mut self) -> Result<JsValue, JsValue> {
+        let ctx = self
+            .ctx
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("No problem is loaded"))?;
+        if let Some(genetic) = self.metaheuristic.as_mut() {
+            if !genetic.terminated() {
+                genetic.iterate(ctx);
+            }
+        }
+
+        let best_cost = ctx.search_history.borrow().best_cost;
+        if best_cost.approx_lt(self.best_cost) {
+            self.best_cost = best_cost;
+            return to_js(&Solution::from_context(ctx));
+        }
+        Ok(JsValue::NULL)
     }
 
-    pub fn update_min_population_size(&mut self, value: i32) {
-        self.config.min_population_size = value as u64;
+    /// Whether the search has reached its time or iteration limit
+    pub fn terminated(&self) -> bool {
+        self.metaheuristic
+            .as_ref()
+            .map_or(true, |genetic| genetic.terminated())
     }
 
-    pub fn update_initial_individuals(&mut self, value: i32) {
-        self.config.initial_individuals = value as u64;
+    /// The best solution found so far
+    pub fn solution(&self) -> Result<JsValue, JsValue> {
+        match self.ctx.as_ref() {
+            Some(ctx) => to_js(&Solution::from_context(ctx)),
+            None => Err(JsValue::from_str("No problem is loaded")),
+        }
     }
+}
 
-    pub fn update_generation_size(&mut self, value: i32) {
-        self.config.population_lambda = value as u64;
+// Convert to a plain JavaScript object
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(value)?)
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn solver() -> Solver {
+        let mut solver = Solver::new();
+        for (id, &(x, y, demand)) in [
+            (0.0, 0.0, 0.0),
+            (3.0, 0.0, 1.0),
+            (3.0, 4.0, 1.0),
+            (0.0, 4.0, 1.0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            solver.add_node(id, demand, x, y);
+        }
+        solver.add_capacity(2.0);
+        let values =
+            js_sys::JSON::parse(r#"{"max_iterations": 20, "deterministic": true}"#).unwrap();
+        solver.set_config(values).unwrap();
+        solver
     }
 
-    pub fn update_local_search_granularity(&mut self, value: i32) {
-        self.config.local_search_granularity = value as u64;
+    fn solve(solver: &mut Solver) -> Solution {
+        solver.load_problem().unwrap();
+        while !solver.terminated() {
+            solver.iterate().unwrap();
+        }
+        serde_wasm_bindgen::from_value(solver.solution().unwrap()).unwrap()
     }
 
-    pub fn update_number_of_elites(&mut self, value: i32) {
-        self.config.num_elites = value as u64;
+    #[wasm_bindgen_test]
+    fn config_object() {
+        let mut solver = solver();
+        let config: Config = serde_wasm_bindgen::from_value(solver.config().unwrap()).unwrap();
+        assert_eq!(Some(20), config.max_iterations);
+
+        let unknown = js_sys::JSON::parse(r#"{"no_such_field": 1}"#).unwrap();
+        assert!(solver.set_config(unknown).is_err());
     }
 
-    pub fn update_feasibility_proportion_target(&mut self, value: f64) {
-        self.config.feasibility_proportion_target = value;
+    #[wasm_bindgen_test]
+    fn solve_euclidean() {
+        let solution = solve(&mut solver());
+        assert!(solution.feasible);
+        assert_eq!(18.0, solution.cost.round());
     }
 
-    pub fn update_rr_start_temp(&mut self, value: f64) {
-        self.config.rr_start_temp = value;
+    #[wasm_bindgen_test]
+    fn solve_explicit_matrix() {
+        let mut solver = solver();
+        #[rustfmt::skip]
+        let distances = vec![
+            0.0, 1.0, 2.0, 1.0,
+            1.0, 0.0, 1.0, 2.0,
+            2.0, 1.0, 0.0, 1.0,
+            1.0, 2.0, 1.0, 0.0,
+        ];
+        solver.set_distance_matrix(distances);
+        let solution = solve(&mut solver);
+        assert!(solution.feasible);
+        assert_eq!(6.0, solution.cost);
+
+        solver.set_distance_matrix(vec![0.0; 9]);
+        assert!(solver.load_problem().is_err());
     }
 
-    pub fn update_rr_gamma(&mut self, value: f64) {
-        self.config.rr_gamma = value;
+    #[wasm_bindgen_test]
+    fn warm_start() {
+        let mut solver = solver();
+        let routes = js_sys::JSON::parse("[[3], [1, 2]]").unwrap();
+        solver.set_initial_solution(routes).unwrap();
+        solver.load_problem().unwrap();
+        let solution: Solution = serde_wasm_bindgen::from_value(solver.iterate().unwrap()).unwrap();
+        assert_eq!(20.0, solution.cost.round());
+
+        let partial = js_sys::JSON::parse("[[1, 2]]").unwrap();
+        solver.set_initial_solution(partial).unwrap();
+        assert!(solver.load_problem().is_err());
     }
+}
 
-    pub fn update_elite_start_temp(&mut self, value: f64) {
-        self.config.elite_education_start_temp = value;
+#[cfg(test)]
+mod conversion_tests {
+    use super::*;
+
+    fn wasm_problem() -> WasmProblem {
+        let mut wasm_problem = WasmProblem::new();
+        wasm_problem.nodes = (0..4)
+            .map(|id| Node {
+                id,
+                coord: Coordinate {
+                    lng: id as f64,
+                    lat: 0.0,
+                },
+                demand: if id == 0 { 0.0 } else { 1.0 },
+            })
+            .collect();
+        wasm_problem.vehicle = Some(Vehicle { id: 0, cap: 2.0 });
+        wasm_problem
     }
 
-    pub fn update_elite_gamma(&mut self, value: f64) {
-        self.config.elite_education_gamma = value;
+    fn problem(wasm_problem: &WasmProblem) -> Problem {
+        ProblemBuilder::new(
+            wasm_problem.nodes.clone(),
+            wasm_problem.vehicle.clone().unwrap(),
+        )
+        .build()
     }
 
-    pub fn update_elite_education(&mut self, value: bool) {
-        self.config.elite_education = value;
+    #[test]
+    fn lower_row_matrix() {
+        let mut wasm_problem = wasm_problem();
+        assert_eq!(Ok(None), wasm_problem.lower_row_matrix());
+
+        // The distance between nodes i and j is i + j
+        let distances = (0..16).map(|value| (value / 4 + value % 4) as f64);
+        wasm_problem.distances = Some(distances.collect());
+        let expected = vec![vec![1.0], vec![2.0, 3.0], vec![3.0, 4.0, 5.0]];
+        assert_eq!(Ok(Some(expected)), wasm_problem.lower_row_matrix());
+
+        wasm_problem.distances = Some((0..16).map(|value| value as f64).collect());
+        assert!(wasm_problem.lower_row_matrix().is_err());
+
+        wasm_problem.distances = Some(vec![0.0; 9]);
+        assert_eq!(
+            Err("The distance matrix must be 4x4".to_string()),
+            wasm_problem.lower_row_matrix()
+        );
     }
 
-    pub fn iterate(&mut self) -> JsValue {
-        if let Some(genetic) = self.metaheuristic.as_mut() {
-            if !genetic.terminated() {
-                genetic.iterate(&self.ctx.as_ref().unwrap());
-            }
-        }
+    #[test]
+    fn initial_routes() {
+        let mut wasm_problem = wasm_problem();
+        let problem = problem(&wasm_problem);
+        assert_eq!(Ok(None), wasm_problem.initial_routes(&problem));
 
-        if let Some(entry) = self
-            .ctx
-            .as_ref()
-            .unwrap()
-            .search_history
-            .borrow()
-            .last_entry()
+        wasm_problem.initial_routes = Some(vec![vec![3], vec![1, 2]]);
+        assert_eq!(
+            Ok(Some(vec![vec![3], vec![1, 2]])),
+            wasm_problem.initial_routes(&problem)
+        );
+
+        // Missing, repeated and unknown customers
+        for routes in [
+            vec![vec![1, 2]],
+            vec![vec![1, 2], vec![2, 3]],
+            vec![vec![1, 2], vec![3, 4]],
+        ]
+        .iter()
         {
-            if entry.solution.cost + EPSILON < self.best_cost {
-                self.best_cost = entry.solution.cost;
-                return JsValue::from_serde(&entry.solution).unwrap();
-            }
+            wasm_problem.initial_routes = Some(routes.clone());
+            assert!(wasm_problem.initial_routes(&problem).is_err());
         }
-        return JsValue::NULL;
     }
 }